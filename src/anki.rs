@@ -1,69 +1,132 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
 
-use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::Config;
+use crate::error::{AnkiCliError, Result};
+use crate::http;
+
+const SERVICE: &str = "AnkiConnect";
 
 #[derive(Debug)]
 pub struct AnkiConnectClient {
     http: Client,
     base_url: String,
+    /// Most notes sent in one `addNotes` request.
+    add_notes_chunk_size: usize,
+    /// Deck names seen so far, fetched once on the first `ensure_deck_exists`.
+    known_decks: Mutex<Option<HashSet<String>>>,
+    /// Decks that do not exist yet. Each is created in the same `multi`
+    /// request as the first notes added to it, or by `create_pending_decks`.
+    pending_decks: Mutex<HashSet<String>>,
 }
 
 impl AnkiConnectClient {
-    pub fn new(base_url: String) -> Self {
-        Self {
-            http: Client::new(),
-            base_url,
+    pub fn new(config: &Config) -> Result<Self> {
+        let http = http::client_builder(config)?.build().map_err(|err| {
+            AnkiCliError::network("failed to build HTTP client for AnkiConnect", err)
+        })?;
+
+        Ok(Self {
+            http,
+            base_url: config.anki_connect_url.clone(),
+            add_notes_chunk_size: config.add_notes_chunk_size.max(1),
+            known_decks: Mutex::new(None),
+            pending_decks: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Run several actions in one `multi` request. Each action gets its own
+    /// result, in order: the action's `result` value, or
+    /// [`AnkiCliError::AnkiApi`] with the error AnkiConnect reported for it.
+    pub async fn multi(&self, actions: &[MultiAction]) -> Result<Vec<Result<serde_json::Value>>> {
+        let responses: Vec<AnkiResponse<serde_json::Value>> =
+            self.invoke("multi", MultiParams { actions }).await?;
+        if responses.len() != actions.len() {
+            return Err(AnkiCliError::AnkiApi(format!(
+                "multi returned {} result(s) for {} action(s)",
+                responses.len(),
+                actions.len()
+            )));
+        }
+        Ok(responses
+            .into_iter()
+            .map(|response| match response.error {
+                Some(error) => Err(AnkiCliError::AnkiApi(error)),
+                None => Ok(response.result.unwrap_or(serde_json::Value::Null)),
+            })
+            .collect())
+    }
+
+    fn pending_decks(&self) -> MutexGuard<'_, HashSet<String>> {
+        self.pending_decks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Record a newly created deck, along with the missing parents of a
+    /// nested deck that `createDeck` creates with it.
+    fn deck_created(&self, deck_name: &str) {
+        self.pending_decks().remove(deck_name);
+        let mut known = self.known_decks();
+        let decks = known.get_or_insert_with(HashSet::new);
+        let mut prefix = String::new();
+        for part in deck_name.split("::") {
+            if !prefix.is_empty() {
+                prefix.push_str("::");
+            }
+            prefix.push_str(part);
+            decks.insert(prefix.clone());
         }
     }
 
-    pub async fn ensure_deck_exists(&self, deck_name: &str) -> Result<()> {
+    fn known_decks(&self) -> MutexGuard<'_, Option<HashSet<String>>> {
+        self.known_decks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    async fn invoke<T, R>(&self, action: &str, params: T) -> Result<R>
+    where
+        T: Serialize,
+        R: for<'de> Deserialize<'de>,
+    {
         let request = AnkiRequest {
-            action: "createDeck",
+            action,
             version: 6,
-            params: CreateDeckParams { deck: deck_name },
+            params,
         };
 
-        let response: AnkiResponse<Option<serde_json::Value>> = self
-            .post(&request)
-            .await
-            .with_context(|| format!("failed to ensure deck {deck_name} exists"))?;
+        let response: AnkiResponse<R> = self.post(&request).await?;
 
         if let Some(error) = response.error {
-            if error.contains("exists") {
-                tracing::debug!("deck {} already exists", deck_name);
-                return Ok(());
-            }
-            anyhow::bail!("Anki returned error: {error}");
+            return Err(AnkiCliError::AnkiApi(error));
         }
 
-        Ok(())
+        response.result.ok_or_else(|| {
+            AnkiCliError::AnkiApi(format!("missing result payload from {action} response"))
+        })
     }
 
-    pub async fn add_notes(&self, notes: &[Note]) -> Result<Vec<Option<i64>>> {
-        if notes.is_empty() {
-            return Ok(vec![]);
-        }
-
+    /// Like [`Self::invoke`] for actions whose result is `null` on success.
+    async fn invoke_unit<T: Serialize>(&self, action: &str, params: T) -> Result<()> {
         let request = AnkiRequest {
-            action: "addNotes",
+            action,
             version: 6,
-            params: AddNotesParams { notes },
+            params,
         };
 
-        let response: AnkiResponse<Vec<Option<i64>>> = self
-            .post(&request)
-            .await
-            .context("failed to add notes via AnkiConnect")?;
-
-        if let Some(error) = response.error {
-            anyhow::bail!("Anki returned error: {error}");
+        let response: AnkiResponse<serde_json::Value> = self.post(&request).await?;
+        match response.error {
+            Some(error) => Err(AnkiCliError::AnkiApi(error)),
+            None => Ok(()),
         }
-
-        response
-            .result
-            .context("missing result payload from AnkiConnect addNotes response")
     }
 
     async fn post<'a, T, R>(&self, payload: &'a AnkiRequest<'a, T>) -> Result<AnkiResponse<R>>
@@ -78,20 +141,676 @@ impl AnkiConnectClient {
             .json(payload)
             .send()
             .await
-            .context("failed to reach AnkiConnect")?;
+            .map_err(|err| AnkiCliError::network("failed to reach AnkiConnect", err))?;
+
+        let response = http::ensure_success(SERVICE, response).await?;
+        http::read_json(SERVICE, response).await
+    }
+}
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("AnkiConnect HTTP error {status}: {body}");
+/// Operations the flows need from Anki. Implemented by [`AnkiConnectClient`]
+/// for a running Anki and by [`MockAnkiClient`] for tests.
+#[async_trait]
+pub trait AnkiClient: Send + Sync {
+    /// Create the deck unless it already exists. A deck that exists only
+    /// with different casing is not created; the call fails with
+    /// [`AnkiCliError::DeckCaseMismatch`] naming the existing deck.
+    /// [`AnkiConnectClient`] defers creating a new deck until the first notes
+    /// are added to it, so both go out in one request, or until the next deck
+    /// read or [`create_pending_decks`](Self::create_pending_decks).
+    async fn ensure_deck_exists(&self, deck_name: &str) -> Result<()>;
+
+    /// Add notes, returning the new note ID or `None` (duplicate) for each.
+    /// Notes are sent in sequential [`add_notes_chunk`](Self::add_notes_chunk)
+    /// requests of at most [`add_notes_chunk_size`](Self::add_notes_chunk_size);
+    /// the results keep the order of `notes`.
+    async fn add_notes(&self, notes: &[Note]) -> Result<Vec<Option<i64>>> {
+        let mut results = Vec::with_capacity(notes.len());
+        for chunk in notes.chunks(self.add_notes_chunk_size().max(1)) {
+            let ids = self.add_notes_chunk(chunk).await?;
+            if ids.len() != chunk.len() {
+                return Err(AnkiCliError::AnkiApi(format!(
+                    "addNotes returned {} result(s) for {} note(s)",
+                    ids.len(),
+                    chunk.len()
+                )));
+            }
+            results.extend(ids);
         }
+        Ok(results)
+    }
 
-        let parsed = response
-            .json::<AnkiResponse<R>>()
+    /// Create the decks [`ensure_deck_exists`](Self::ensure_deck_exists)
+    /// deferred that no notes were added to, e.g. after a dry run or when
+    /// every word was filtered out.
+    async fn create_pending_decks(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Add one chunk of notes in a single request.
+    async fn add_notes_chunk(&self, notes: &[Note]) -> Result<Vec<Option<i64>>>;
+
+    /// Most notes sent in one [`add_notes_chunk`](Self::add_notes_chunk).
+    fn add_notes_chunk_size(&self) -> usize {
+        usize::MAX
+    }
+
+    /// Add a single note, returning its ID or [`AnkiCliError::NoteRejected`]
+    /// with the reason it was not added.
+    async fn add_note(&self, note: &Note) -> Result<i64> {
+        match self.add_notes(std::slice::from_ref(note)).await?.first() {
+            Some(Some(note_id)) => Ok(*note_id),
+            _ => Err(AnkiCliError::NoteRejected(
+                self.rejection_reason(note).await,
+            )),
+        }
+    }
+
+    /// Why a note `add_notes` returned `None` for was not added.
+    async fn rejection_reason(&self, _note: &Note) -> String {
+        "duplicate".to_string()
+    }
+
+    /// Trigger a sync of the local collection with AnkiWeb.
+    async fn sync(&self) -> Result<()>;
+
+    /// Return the AnkiConnect API version reported by the add-on.
+    async fn version(&self) -> Result<u32>;
+
+    async fn deck_names(&self) -> Result<Vec<String>>;
+
+    /// Every deck name with its deck ID.
+    async fn deck_names_and_ids(&self) -> Result<BTreeMap<String, i64>>;
+
+    async fn model_names(&self) -> Result<Vec<String>>;
+
+    async fn model_field_names(&self, model_name: &str) -> Result<Vec<String>>;
+
+    /// Card templates of a note type, keyed by card name.
+    async fn model_templates(&self, model_name: &str) -> Result<BTreeMap<String, CardTemplate>>;
+
+    /// CSS shared by the card templates of a note type.
+    async fn model_styling(&self, model_name: &str) -> Result<String>;
+
+    /// Fetch model, fields and generated cards for existing notes.
+    async fn notes_info(&self, note_ids: &[i64]) -> Result<Vec<NoteInfo>>;
+
+    /// Card counts for each of `decks` that exists, in the order given.
+    async fn deck_stats(&self, decks: &[&str]) -> Result<Vec<DeckStats>>;
+
+    /// Return the IDs of notes matching an Anki search query.
+    async fn find_notes(&self, query: &str) -> Result<Vec<i64>>;
+
+    /// Overwrite the given fields of an existing note; other fields are kept.
+    async fn update_note_fields(
+        &self,
+        note_id: i64,
+        fields: &BTreeMap<String, String>,
+    ) -> Result<()>;
+
+    /// Replace `old` with `new` in the tags of each note.
+    async fn replace_tags(&self, note_ids: &[i64], old: &str, new: &str) -> Result<()>;
+
+    /// Open Anki's card browser filtered by `query` and return the matching card IDs.
+    async fn gui_browse(&self, query: &str) -> Result<Vec<i64>>;
+
+    /// Store a file in the collection's media folder, returning the name Anki
+    /// saved it under.
+    async fn store_media_file(&self, filename: &str, data: &[u8]) -> Result<String>;
+}
+
+#[async_trait]
+impl AnkiClient for AnkiConnectClient {
+    async fn ensure_deck_exists(&self, deck_name: &str) -> Result<()> {
+        if self.known_decks().is_none() {
+            let decks = self.deck_names_and_ids().await?;
+            self.known_decks()
+                .get_or_insert_with(HashSet::new)
+                .extend(decks.into_keys());
+        }
+
+        if let Some(decks) = self.known_decks().as_ref() {
+            if decks.contains(deck_name) {
+                tracing::debug!("deck {} already exists", deck_name);
+                return Ok(());
+            }
+            if let Some(existing) = decks
+                .iter()
+                .find(|existing| existing.to_lowercase() == deck_name.to_lowercase())
+            {
+                return Err(AnkiCliError::DeckCaseMismatch {
+                    requested: deck_name.to_string(),
+                    existing: existing.clone(),
+                });
+            }
+        }
+
+        // Created together with its first notes; see `add_notes_chunk`.
+        tracing::debug!("deck {} will be created with its first notes", deck_name);
+        self.pending_decks().insert(deck_name.to_string());
+        Ok(())
+    }
+
+    /// Send one `addNotes` request. Pending decks of these notes are created
+    /// first in the same `multi` request, saving a round trip.
+    async fn add_notes_chunk(&self, notes: &[Note]) -> Result<Vec<Option<i64>>> {
+        let new_decks: BTreeSet<String> = {
+            let pending = self.pending_decks();
+            notes
+                .iter()
+                .filter(|note| pending.contains(&note.deck_name))
+                .map(|note| note.deck_name.clone())
+                .collect()
+        };
+        if new_decks.is_empty() {
+            return self.invoke("addNotes", AddNotesParams { notes }).await;
+        }
+
+        let mut actions: Vec<MultiAction> = new_decks
+            .iter()
+            .map(|deck| MultiAction::new("createDeck", json!({ "deck": deck })))
+            .collect();
+        actions.push(MultiAction::new("addNotes", json!({ "notes": notes })));
+        let mut results = self.multi(&actions).await?;
+        let added = results.pop().unwrap_or(Ok(serde_json::Value::Null));
+        for (deck, created) in new_decks.iter().zip(results) {
+            if let Err(AnkiCliError::AnkiApi(error)) = created {
+                return Err(AnkiCliError::AnkiApi(format!(
+                    "createDeck failed for deck '{deck}': {error}"
+                )));
+            }
+            self.deck_created(deck);
+        }
+        let added = added?;
+        serde_json::from_value(added.clone()).map_err(|source| {
+            AnkiCliError::invalid_response(SERVICE, None, &added.to_string(), source)
+        })
+    }
+
+    fn add_notes_chunk_size(&self) -> usize {
+        self.add_notes_chunk_size
+    }
+
+    /// Also run before every deck read, so a deferred deck is visible to it.
+    async fn create_pending_decks(&self) -> Result<()> {
+        let decks: BTreeSet<String> = self.pending_decks().iter().cloned().collect();
+        if decks.is_empty() {
+            return Ok(());
+        }
+        let actions: Vec<MultiAction> = decks
+            .iter()
+            .map(|deck| MultiAction::new("createDeck", json!({ "deck": deck })))
+            .collect();
+        for (deck, created) in decks.iter().zip(self.multi(&actions).await?) {
+            if let Err(AnkiCliError::AnkiApi(error)) = created {
+                return Err(AnkiCliError::AnkiApi(format!(
+                    "createDeck failed for deck '{deck}': {error}"
+                )));
+            }
+            self.deck_created(deck);
+        }
+        Ok(())
+    }
+
+    /// addNotes only reports null; ask why. Older AnkiConnect versions lack
+    /// this action, in which case the reason stays generic.
+    async fn rejection_reason(&self, note: &Note) -> String {
+        let notes = std::slice::from_ref(note);
+        match self
+            .invoke::<_, Vec<CanAddDetail>>("canAddNotesWithErrorDetail", AddNotesParams { notes })
+            .await
+        {
+            Ok(details) => details
+                .into_iter()
+                .next()
+                .and_then(|detail| detail.error)
+                .unwrap_or_else(|| "duplicate".to_string()),
+            Err(err) => {
+                tracing::debug!("canAddNotesWithErrorDetail unavailable: {}", err);
+                "duplicate or otherwise invalid (this AnkiConnect version does not report why)"
+                    .to_string()
+            }
+        }
+    }
+
+    async fn sync(&self) -> Result<()> {
+        let request = AnkiRequest {
+            action: "sync",
+            version: 6,
+            params: NoParams {},
+        };
+
+        let response: AnkiResponse<serde_json::Value> = self.post(&request).await?;
+
+        if let Some(error) = response.error {
+            return Err(AnkiCliError::AnkiApi(error));
+        }
+
+        Ok(())
+    }
+
+    async fn version(&self) -> Result<u32> {
+        self.invoke("version", NoParams {}).await
+    }
+
+    async fn deck_names(&self) -> Result<Vec<String>> {
+        self.create_pending_decks().await?;
+        self.invoke("deckNames", NoParams {}).await
+    }
+
+    async fn deck_names_and_ids(&self) -> Result<BTreeMap<String, i64>> {
+        self.create_pending_decks().await?;
+        self.invoke("deckNamesAndIds", NoParams {}).await
+    }
+
+    async fn model_names(&self) -> Result<Vec<String>> {
+        self.invoke("modelNames", NoParams {}).await
+    }
+
+    async fn model_field_names(&self, model_name: &str) -> Result<Vec<String>> {
+        self.invoke("modelFieldNames", ModelNameParams { model_name })
+            .await
+    }
+
+    async fn model_templates(&self, model_name: &str) -> Result<BTreeMap<String, CardTemplate>> {
+        self.invoke("modelTemplates", ModelNameParams { model_name })
             .await
-            .context("failed to parse AnkiConnect response body")?;
+    }
 
-        Ok(parsed)
+    async fn model_styling(&self, model_name: &str) -> Result<String> {
+        let styling: ModelStyling = self
+            .invoke("modelStyling", ModelNameParams { model_name })
+            .await?;
+        Ok(styling.css)
+    }
+
+    async fn notes_info(&self, note_ids: &[i64]) -> Result<Vec<NoteInfo>> {
+        self.invoke("notesInfo", NotesInfoParams { notes: note_ids })
+            .await
+    }
+
+    async fn deck_stats(&self, decks: &[&str]) -> Result<Vec<DeckStats>> {
+        // getDeckStats creates any deck it is asked about, so only pass on
+        // the names that already exist.
+        let existing = self.deck_names().await?;
+        let decks: Vec<&str> = decks
+            .iter()
+            .copied()
+            .filter(|deck| existing.iter().any(|name| name == deck))
+            .collect();
+        if decks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let stats: HashMap<String, DeckStats> = self
+            .invoke("getDeckStats", DeckStatsParams { decks: &decks })
+            .await?;
+        let mut stats: Vec<DeckStats> = stats.into_values().collect();
+        stats.sort_by_key(|stat| decks.iter().position(|deck| *deck == stat.name));
+        Ok(stats)
+    }
+
+    async fn find_notes(&self, query: &str) -> Result<Vec<i64>> {
+        self.create_pending_decks().await?;
+        self.invoke("findNotes", FindNotesParams { query }).await
+    }
+
+    async fn update_note_fields(
+        &self,
+        note_id: i64,
+        fields: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let note = UpdateNote {
+            id: note_id,
+            fields,
+        };
+        self.invoke_unit("updateNoteFields", UpdateNoteFieldsParams { note })
+            .await
+    }
+
+    async fn replace_tags(&self, note_ids: &[i64], old: &str, new: &str) -> Result<()> {
+        let params = ReplaceTagsParams {
+            notes: note_ids,
+            tag_to_replace: old,
+            replace_with_tag: new,
+        };
+        self.invoke_unit("replaceTags", params).await
+    }
+
+    async fn gui_browse(&self, query: &str) -> Result<Vec<i64>> {
+        self.invoke("guiBrowse", GuiBrowseParams { query }).await
+    }
+
+    async fn store_media_file(&self, filename: &str, data: &[u8]) -> Result<String> {
+        let data = BASE64.encode(data);
+        self.invoke(
+            "storeMediaFile",
+            StoreMediaFileParams {
+                filename,
+                data: &data,
+            },
+        )
+        .await
+    }
+}
+
+/// In-memory [`AnkiClient`] that records decks and notes instead of talking
+/// to Anki, for exercising the flows without a running collection.
+#[derive(Debug, Default)]
+pub struct MockAnkiClient {
+    state: Mutex<MockState>,
+    add_notes_chunk_size: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    decks: Vec<String>,
+    notes: Vec<(i64, Note)>,
+    media: Vec<String>,
+    syncs: usize,
+    /// Size of each `add_notes_chunk` request, in order.
+    add_notes_chunks: Vec<usize>,
+}
+
+impl MockAnkiClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Split `add_notes` into requests of at most `size` notes, like
+    /// [`AnkiConnectClient`] does with `add_notes_chunk_size`.
+    pub fn with_add_notes_chunk_size(size: usize) -> Self {
+        Self {
+            add_notes_chunk_size: Some(size),
+            ..Self::default()
+        }
+    }
+
+    /// Number of notes in each add request received so far.
+    pub fn add_notes_chunks(&self) -> Vec<usize> {
+        self.state().add_notes_chunks.clone()
+    }
+
+    /// Notes added so far, in the order they were sent.
+    pub fn added_notes(&self) -> Vec<Note> {
+        self.state()
+            .notes
+            .iter()
+            .map(|(_, note)| note.clone())
+            .collect()
+    }
+
+    /// Decks created so far.
+    pub fn decks(&self) -> Vec<String> {
+        self.state().decks.clone()
+    }
+
+    /// Names of the media files stored so far.
+    pub fn media_files(&self) -> Vec<String> {
+        self.state().media.clone()
+    }
+
+    /// Number of sync requests received.
+    pub fn sync_count(&self) -> usize {
+        self.state().syncs
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl AnkiClient for MockAnkiClient {
+    async fn ensure_deck_exists(&self, deck_name: &str) -> Result<()> {
+        let mut state = self.state();
+        if state.decks.iter().any(|deck| deck == deck_name) {
+            return Ok(());
+        }
+        if let Some(existing) = state
+            .decks
+            .iter()
+            .find(|deck| deck.to_lowercase() == deck_name.to_lowercase())
+        {
+            return Err(AnkiCliError::DeckCaseMismatch {
+                requested: deck_name.to_string(),
+                existing: existing.clone(),
+            });
+        }
+        state.decks.push(deck_name.to_string());
+        Ok(())
+    }
+
+    async fn add_notes_chunk(&self, notes: &[Note]) -> Result<Vec<Option<i64>>> {
+        let mut state = self.state();
+        state.add_notes_chunks.push(notes.len());
+        let mut results = Vec::with_capacity(notes.len());
+        for note in notes {
+            let duplicate = state.notes.iter().any(|(_, existing)| {
+                existing.deck_name == note.deck_name && existing.fields == note.fields
+            });
+            if duplicate {
+                results.push(None);
+            } else {
+                let id = state.notes.len() as i64 + 1;
+                state.notes.push((id, note.clone()));
+                results.push(Some(id));
+            }
+        }
+        Ok(results)
+    }
+
+    fn add_notes_chunk_size(&self) -> usize {
+        self.add_notes_chunk_size.unwrap_or(usize::MAX)
+    }
+
+    async fn sync(&self) -> Result<()> {
+        self.state().syncs += 1;
+        Ok(())
+    }
+
+    async fn version(&self) -> Result<u32> {
+        Ok(6)
+    }
+
+    async fn deck_names(&self) -> Result<Vec<String>> {
+        Ok(self.decks())
+    }
+
+    async fn deck_names_and_ids(&self) -> Result<BTreeMap<String, i64>> {
+        Ok(self.decks().into_iter().zip(1..).collect())
+    }
+
+    async fn model_names(&self) -> Result<Vec<String>> {
+        Ok(vec![
+            "Basic".to_string(),
+            "Basic (and reversed card)".to_string(),
+            "Cloze".to_string(),
+        ])
+    }
+
+    async fn model_field_names(&self, model_name: &str) -> Result<Vec<String>> {
+        match model_name {
+            "Basic" | "Basic (and reversed card)" => {
+                Ok(vec!["Front".to_string(), "Back".to_string()])
+            }
+            "Cloze" => Ok(vec!["Text".to_string(), "Back Extra".to_string()]),
+            other => Err(AnkiCliError::AnkiApi(format!(
+                "model was not found: {other}"
+            ))),
+        }
+    }
+
+    /// Anki's stock templates for the built-in note types.
+    async fn model_templates(&self, model_name: &str) -> Result<BTreeMap<String, CardTemplate>> {
+        let template = |front: &str, back: &str| CardTemplate {
+            front: front.to_string(),
+            back: back.to_string(),
+        };
+        let forward = template("{{Front}}", "{{FrontSide}}\n\n<hr id=answer>\n\n{{Back}}");
+        match model_name {
+            "Basic" => Ok(BTreeMap::from([("Card 1".to_string(), forward)])),
+            "Basic (and reversed card)" => Ok(BTreeMap::from([
+                ("Card 1".to_string(), forward),
+                (
+                    "Card 2".to_string(),
+                    template("{{Back}}", "{{FrontSide}}\n\n<hr id=answer>\n\n{{Front}}"),
+                ),
+            ])),
+            "Cloze" => Ok(BTreeMap::from([(
+                "Cloze".to_string(),
+                template("{{cloze:Text}}", "{{cloze:Text}}<br>\n{{Back Extra}}"),
+            )])),
+            other => Err(AnkiCliError::AnkiApi(format!(
+                "model was not found: {other}"
+            ))),
+        }
+    }
+
+    async fn model_styling(&self, _model_name: &str) -> Result<String> {
+        Ok(".card {\n    font-family: arial;\n    font-size: 20px;\n    text-align: center;\n    color: black;\n    background-color: white;\n}\n".to_string())
+    }
+
+    async fn notes_info(&self, note_ids: &[i64]) -> Result<Vec<NoteInfo>> {
+        let state = self.state();
+        Ok(note_ids
+            .iter()
+            .map(
+                |id| match state.notes.iter().find(|(existing, _)| existing == id) {
+                    Some((id, note)) => NoteInfo {
+                        note_id: Some(*id),
+                        model_name: note.model_name.clone(),
+                        fields: note
+                            .fields
+                            .iter()
+                            .enumerate()
+                            .map(|(order, (name, value))| {
+                                (
+                                    name.clone(),
+                                    NoteFieldInfo {
+                                        value: value.clone(),
+                                        order: order as u32,
+                                    },
+                                )
+                            })
+                            .collect(),
+                        tags: note.tags.clone(),
+                        cards: vec![*id],
+                    },
+                    None => NoteInfo {
+                        note_id: None,
+                        model_name: String::new(),
+                        fields: BTreeMap::new(),
+                        tags: Vec::new(),
+                        cards: Vec::new(),
+                    },
+                },
+            )
+            .collect())
+    }
+
+    /// Every recorded note counts as one new card.
+    async fn deck_stats(&self, decks: &[&str]) -> Result<Vec<DeckStats>> {
+        let state = self.state();
+        Ok(decks
+            .iter()
+            .filter(|deck| state.decks.iter().any(|name| name == *deck))
+            .map(|deck| {
+                let count = state
+                    .notes
+                    .iter()
+                    .filter(|(_, note)| note.deck_name == *deck)
+                    .count() as u64;
+                DeckStats {
+                    name: deck.to_string(),
+                    new_count: count,
+                    learn_count: 0,
+                    review_count: 0,
+                    total_in_deck: count,
+                }
+            })
+            .collect())
+    }
+
+    /// Supports only space-separated `tag:<name>` terms (quoted or not),
+    /// optionally grouped as `(tag:a OR tag:b)`.
+    async fn find_notes(&self, query: &str) -> Result<Vec<i64>> {
+        // Each group matches if any of its tags does; all groups must match.
+        let mut groups: Vec<Vec<String>> = Vec::new();
+        let mut or_next = false;
+        for term in query.split_whitespace() {
+            let term = term.trim_matches(|c| c == '(' || c == ')');
+            if term == "OR" {
+                or_next = true;
+                continue;
+            }
+            let Some(tag) = term.strip_prefix("tag:") else {
+                continue;
+            };
+            let tag = tag.trim_matches('"').replace('\\', "");
+            match groups.last_mut() {
+                Some(group) if or_next => group.push(tag),
+                _ => groups.push(vec![tag]),
+            }
+            or_next = false;
+        }
+        Ok(self
+            .state()
+            .notes
+            .iter()
+            .filter(|(_, note)| {
+                groups.iter().all(|group| {
+                    group.iter().any(|tag| {
+                        note.tags
+                            .iter()
+                            .any(|existing| existing.to_lowercase() == tag.to_lowercase())
+                    })
+                })
+            })
+            .map(|(id, _)| *id)
+            .collect())
+    }
+
+    async fn update_note_fields(
+        &self,
+        note_id: i64,
+        fields: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let mut state = self.state();
+        let Some((_, note)) = state.notes.iter_mut().find(|(id, _)| *id == note_id) else {
+            return Err(AnkiCliError::AnkiApi(format!(
+                "note was not found: {note_id}"
+            )));
+        };
+        note.fields.extend(fields.clone());
+        Ok(())
+    }
+
+    async fn replace_tags(&self, note_ids: &[i64], old: &str, new: &str) -> Result<()> {
+        let mut state = self.state();
+        for (_, note) in state
+            .notes
+            .iter_mut()
+            .filter(|(id, _)| note_ids.contains(id))
+        {
+            for tag in note.tags.iter_mut() {
+                if tag.eq_ignore_ascii_case(old) {
+                    *tag = new.to_string();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn gui_browse(&self, _query: &str) -> Result<Vec<i64>> {
+        Ok(self.state().notes.iter().map(|(id, _)| *id).collect())
+    }
+
+    async fn store_media_file(&self, filename: &str, _data: &[u8]) -> Result<String> {
+        self.state().media.push(filename.to_string());
+        Ok(filename.to_string())
     }
 }
 
@@ -116,6 +835,48 @@ pub struct NoteOptions {
     pub duplicate_scope: Option<String>,
 }
 
+/// A stored note as reported by `notesInfo`. Unknown note ids come back as
+/// empty objects, hence the defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteInfo {
+    #[serde(default)]
+    pub note_id: Option<i64>,
+    #[serde(default)]
+    pub model_name: String,
+    #[serde(default)]
+    pub fields: BTreeMap<String, NoteFieldInfo>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub cards: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NoteFieldInfo {
+    pub value: String,
+    pub order: u32,
+}
+
+/// Question and answer side of one card template, as reported by `modelTemplates`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CardTemplate {
+    #[serde(rename = "Front")]
+    pub front: String,
+    #[serde(rename = "Back")]
+    pub back: String,
+}
+
+/// Card counts for one deck as reported by `getDeckStats`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeckStats {
+    pub name: String,
+    pub new_count: u64,
+    pub learn_count: u64,
+    pub review_count: u64,
+    pub total_in_deck: u64,
+}
+
 #[derive(Debug, Serialize)]
 struct AnkiRequest<'a, T> {
     action: &'a str,
@@ -123,9 +884,27 @@ struct AnkiRequest<'a, T> {
     params: T,
 }
 
+/// One action of an AnkiConnect `multi` request.
+#[derive(Debug, Clone, Serialize)]
+pub struct MultiAction {
+    action: String,
+    version: u8,
+    params: serde_json::Value,
+}
+
+impl MultiAction {
+    pub fn new(action: impl Into<String>, params: serde_json::Value) -> Self {
+        Self {
+            action: action.into(),
+            version: 6,
+            params,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
-struct CreateDeckParams<'a> {
-    deck: &'a str,
+struct MultiParams<'a> {
+    actions: &'a [MultiAction],
 }
 
 #[derive(Debug, Serialize)]
@@ -133,8 +912,105 @@ struct AddNotesParams<'a> {
     notes: &'a [Note],
 }
 
+/// One entry of a `canAddNotesWithErrorDetail` result; only the error matters here.
+#[derive(Debug, Deserialize)]
+struct CanAddDetail {
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FindNotesParams<'a> {
+    query: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateNoteFieldsParams<'a> {
+    note: UpdateNote<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateNote<'a> {
+    id: i64,
+    fields: &'a BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReplaceTagsParams<'a> {
+    notes: &'a [i64],
+    tag_to_replace: &'a str,
+    replace_with_tag: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct NotesInfoParams<'a> {
+    notes: &'a [i64],
+}
+
+#[derive(Debug, Serialize)]
+struct DeckStatsParams<'a> {
+    decks: &'a [&'a str],
+}
+
+#[derive(Debug, Serialize)]
+struct NoParams {}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelNameParams<'a> {
+    model_name: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelStyling {
+    css: String,
+}
+
+#[derive(Debug, Serialize)]
+struct GuiBrowseParams<'a> {
+    query: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct StoreMediaFileParams<'a> {
+    filename: &'a str,
+    data: &'a str,
+}
+
 #[derive(Debug, Deserialize)]
 struct AnkiResponse<T> {
     result: Option<T>,
     error: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(front: &str) -> Note {
+        Note {
+            deck_name: "Test".to_string(),
+            model_name: "Basic".to_string(),
+            fields: BTreeMap::from([("Front".to_string(), front.to_string())]),
+            tags: Vec::new(),
+            options: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn add_notes_chunks_keep_note_order() {
+        let anki = MockAnkiClient::with_add_notes_chunk_size(2);
+        let notes: Vec<Note> = ["a", "b", "c", "a", "e"].into_iter().map(note).collect();
+
+        let ids = anki.add_notes(&notes).await.unwrap();
+
+        assert_eq!(anki.add_notes_chunks(), vec![2, 2, 1]);
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3), None, Some(4)]);
+        let fronts: Vec<String> = anki
+            .added_notes()
+            .iter()
+            .map(|note| note.fields["Front"].clone())
+            .collect();
+        assert_eq!(fronts, ["a", "b", "c", "e"]);
+    }
+}
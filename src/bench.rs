@@ -0,0 +1,202 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use crate::Language;
+use crate::input::{EnglishMode, WordEntry};
+use crate::llm::{GenerationOptions, OpenAiClient};
+use crate::workflows::RunContext;
+
+/// One model and temperature combination to generate the word list with.
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub model: String,
+    pub temperature: f32,
+}
+
+impl BenchConfig {
+    fn label(&self) -> String {
+        format!("{} @ {}", self.model, self.temperature)
+    }
+
+    /// File name for the generated cards, e.g. `gpt-4o_t0.7.txt`.
+    fn file_name(&self) -> String {
+        let model: String = self
+            .model
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || matches!(c, '-' | '.') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{model}_t{}.txt", self.temperature)
+    }
+}
+
+struct BenchResult {
+    label: String,
+    generated: usize,
+    failed: usize,
+    total_latency: Duration,
+    max_latency: Duration,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    output: PathBuf,
+}
+
+/// Generate cards for every word with each model/temperature combination and
+/// print a comparison of latency and token usage. Nothing is sent to Anki;
+/// the cards of each combination are written to a file in `out_dir` for
+/// reviewing their quality side by side.
+pub async fn run_bench(
+    language: Language,
+    words: &[WordEntry],
+    configs: &[BenchConfig],
+    out_dir: &Path,
+    ctx: &RunContext<'_>,
+) -> Result<()> {
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create output directory {}", out_dir.display()))?;
+
+    let mut results = Vec::with_capacity(configs.len());
+    for bench in configs {
+        tracing::info!("Benchmarking {} on {} word(s)", bench.label(), words.len());
+        let mut config = ctx.config.clone();
+        config.openai_model = bench.model.clone();
+        config.temperature = bench.temperature;
+        let llm = OpenAiClient::new(&config)?;
+        let options = GenerationOptions {
+            temperature: bench.temperature,
+            ..ctx.generation_options()
+        };
+
+        let mut review = String::new();
+        let mut result = BenchResult {
+            label: bench.label(),
+            generated: 0,
+            failed: 0,
+            total_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            output: out_dir.join(bench.file_name()),
+        };
+        for entry in words {
+            let started = Instant::now();
+            let card = render_card(&llm, language, entry, &options, ctx).await;
+            let latency = started.elapsed();
+            result.total_latency += latency;
+            result.max_latency = result.max_latency.max(latency);
+            match card {
+                Ok(text) => {
+                    result.generated += 1;
+                    review.push_str(&text);
+                }
+                Err(err) => {
+                    result.failed += 1;
+                    tracing::warn!("{} failed on '{}': {:#}", bench.label(), entry.word, err);
+                    let _ = writeln!(review, "{}\n  FAILED: {:#}", entry.word, err);
+                }
+            }
+            review.push('\n');
+        }
+        (result.prompt_tokens, result.completion_tokens) = llm.token_usage();
+
+        fs::write(&result.output, review).with_context(|| {
+            format!(
+                "failed to write benchmark cards to {}",
+                result.output.display()
+            )
+        })?;
+        results.push(result);
+    }
+
+    print_table(&results, words.len());
+    Ok(())
+}
+
+/// Generate one word's card and render it as text for the review file.
+async fn render_card(
+    llm: &OpenAiClient,
+    language: Language,
+    entry: &WordEntry,
+    options: &GenerationOptions,
+    ctx: &RunContext<'_>,
+) -> Result<String> {
+    // Per-word temperatures are ignored, so every card uses the temperature
+    // the comparison attributes it to.
+    let word = &entry.word;
+    let meaning = entry.definition.as_deref();
+    let mut text = String::new();
+    match language {
+        Language::Hindi => {
+            let card = llm
+                .generate_hindi_card(word, entry.pos, meaning, options)
+                .await?;
+            let _ = writeln!(text, "{}", card.word);
+            for example in &card.examples {
+                let _ = writeln!(text, "  Hindi  : {}", example.hindi_sentence);
+                let _ = writeln!(text, "  English: {}", example.english_sentence);
+            }
+        }
+        Language::English => match entry.english_mode.unwrap_or(ctx.english_mode) {
+            EnglishMode::Cloze => {
+                let cards = llm
+                    .generate_english_clozes(word, entry.pos, meaning, options)
+                    .await?;
+                let _ = writeln!(text, "{word}");
+                for card in &cards {
+                    let _ = writeln!(text, "  Cloze      : {}", card.cloze_sentence);
+                    let _ = writeln!(text, "  Explanation: {}", card.translation);
+                }
+            }
+            EnglishMode::Basic => {
+                let card = llm
+                    .generate_english_definition(word, entry.pos, meaning, options)
+                    .await?;
+                let _ = writeln!(text, "{}", card.word);
+                let _ = writeln!(text, "  Definition: {}", card.definition);
+                if let Some(example) = &card.example {
+                    let _ = writeln!(text, "  Example   : {example}");
+                }
+            }
+        },
+    }
+    Ok(text)
+}
+
+fn print_table(results: &[BenchResult], words: usize) {
+    let width = results
+        .iter()
+        .map(|result| result.label.chars().count())
+        .chain(["config".len()])
+        .max()
+        .unwrap_or(0);
+    println!(
+        "{:<width$}  {:>6}  {:>6}  {:>8}  {:>8}  {:>10}  {:>10}",
+        "config", "ok", "failed", "avg ms", "max ms", "prompt tok", "compl tok"
+    );
+    for result in results {
+        let average = result.total_latency.as_millis() / words.max(1) as u128;
+        println!(
+            "{:<width$}  {:>6}  {:>6}  {:>8}  {:>8}  {:>10}  {:>10}",
+            result.label,
+            result.generated,
+            result.failed,
+            average,
+            result.max_latency.as_millis(),
+            result.prompt_tokens,
+            result.completion_tokens
+        );
+    }
+    println!();
+    for result in results {
+        println!("{}: {}", result.label, result.output.display());
+    }
+}
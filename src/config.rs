@@ -1,21 +1,152 @@
+use std::collections::BTreeMap;
 use std::env;
-use std::fs;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use clap::ValueEnum;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4o";
+pub const DEFAULT_IMAGE_MODEL: &str = "gpt-image-1";
+pub const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+pub const DEFAULT_ANKI_CONNECT_URL: &str = "http://127.0.0.1:8765";
+pub const DEFAULT_HINDI_DECK: &str = "Hindi Sentence Practice";
+pub const DEFAULT_ENGLISH_DECK: &str = "English Cloze Practice";
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_ADD_NOTES_CHUNK_SIZE: usize = 100;
+pub const DEFAULT_MAX_WORDS_PER_RUN: usize = 200;
+pub const DEFAULT_COST_CONFIRM_THRESHOLD: usize = 50;
+pub const DEFAULT_APPEND_MAX_EXAMPLES: usize = 5;
+/// Terms `safe_mode` rejects in generated text unless
+/// `use_default_banned_words = false`; `banned_words` adds to it. Words with
+/// an everyday meaning or spelling are left out to avoid false hits.
+pub const DEFAULT_BANNED_WORDS: &[&str] = &[
+    "fuck",
+    "fucking",
+    "shit",
+    "bitch",
+    "bastard",
+    "asshole",
+    "cunt",
+    "pussy",
+    "slut",
+    "whore",
+    "wanker",
+    "nigger",
+    "faggot",
+    "chutiya",
+    "madarchod",
+    "behenchod",
+    "bhosdike",
+    "harami",
+    "चूतिया",
+    "मादरचोद",
+    "बहनचोद",
+    "भोसड़ीके",
+    "रंडी",
+    "हरामी",
+];
+pub const DEFAULT_EXPLANATION_LANGUAGE: &str = "English";
+pub const DEFAULT_DATE_TAG_FORMAT: &str = "%Y-%m-%d";
+pub const DEFAULT_WORD_TAG_PREFIX: &str = "word_";
+pub const DEFAULT_PROMPT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const PROMPT_WORD_PLACEHOLDER: &str = "{word}";
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub openai_api_key: String,
     pub openai_model: String,
     pub openai_base_url: String,
+    pub openai_org: Option<String>,
+    pub openai_project: Option<String>,
+    pub provider: Provider,
+    /// Headers sent with every OpenAI request, e.g. `HTTP-Referer`.
+    pub extra_headers: BTreeMap<String, String>,
+    /// Query parameters added to every OpenAI request URL, e.g. `api-version`.
+    pub extra_query: BTreeMap<String, String>,
     pub anki_connect_url: String,
     pub hindi_deck: String,
     pub english_deck: String,
     pub temperature: f32,
+    pub top_p: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
     pub tags: Vec<String>,
+    pub word_tag_prefix: String,
+    pub namespace_word_tags: bool,
+    pub date_tag: bool,
+    pub date_tag_format: String,
+    pub difficulty: Option<Difficulty>,
+    pub request_timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    pub requests_per_minute: Option<u32>,
+    pub max_completion_tokens: Option<u32>,
+    pub add_notes_chunk_size: usize,
+    pub max_words_per_run: usize,
+    /// Most entries `--append-to-field` lets a field grow to.
+    pub append_max_examples: usize,
+    pub cost_confirm_threshold: usize,
+    pub hindi_direction: HindiDirection,
+    pub hindi_model_mode: HindiModelMode,
+    pub allow_duplicate: bool,
+    pub duplicate_scope: DuplicateScope,
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub ca_cert_path: Option<PathBuf>,
+    pub auto_sync: bool,
+    /// Remember the deck of each run in the config file.
+    pub auto_save_deck: bool,
+    /// Names of the built-in enrichers run on every generated card, in order.
+    pub enrichers: Vec<String>,
+    pub seed: Option<u64>,
+    pub include_ipa: bool,
+    pub hint_policy: HintPolicy,
+    pub explanation_language: String,
+    /// Register every prompt asks for, e.g. "Write in a casual, conversational register."
+    pub style: Option<String>,
+    pub image_model: String,
+    /// Ask for classroom-appropriate content in every system prompt.
+    pub safe_mode: bool,
+    /// In safe mode, also check generated text against `banned_words`.
+    pub banned_word_check: bool,
+    /// Start `banned_words` from [`DEFAULT_BANNED_WORDS`].
+    pub use_default_banned_words: bool,
+    /// Built-in banned terms (unless disabled) plus those from the config,
+    /// lowercased.
+    pub banned_words: Vec<String>,
+    pub structured_output: bool,
+    pub tool_calling: bool,
+    pub stream: bool,
+    pub print_prompt: bool,
+    /// Reject cards whose sentence lacks the word or cloze markup instead of
+    /// warning.
+    pub strict: bool,
+    /// JSONL file that every OpenAI request and response is appended to.
+    pub llm_log: Option<PathBuf>,
+    pub hindi_sentence_words: WordRange,
+    pub english_sentence_words: WordRange,
+    pub tag_deck_map: TagDeckMap,
+    pub presets: BTreeMap<String, DeckPreset>,
+    pub frequency_list: Option<PathBuf>,
+    pub hindi_prompt_template: Option<String>,
+    pub english_prompt_template: Option<String>,
+    pub prompt_version: String,
+    pub prompt_version_field: Option<String>,
+    /// Note field that records how the note was generated.
+    pub source_field: Option<String>,
+    pub word_field: Option<String>,
+    /// Cloze note type field that receives the cloze sentence, for note
+    /// types whose first field is not `Text`.
+    pub cloze_field: Option<String>,
+    pub cloze_fields: ClozeFieldMap,
     config_path: Option<PathBuf>,
 }
 
@@ -24,11 +155,833 @@ struct FileConfig {
     openai_api_key: Option<String>,
     openai_model: Option<String>,
     openai_base_url: Option<String>,
+    openai_org: Option<String>,
+    openai_project: Option<String>,
+    provider: Option<Provider>,
+    extra_headers: Option<BTreeMap<String, String>>,
+    extra_query: Option<BTreeMap<String, String>>,
     anki_connect_url: Option<String>,
     hindi_deck: Option<String>,
     english_deck: Option<String>,
     temperature: Option<f32>,
+    top_p: Option<f32>,
+    frequency_penalty: Option<f32>,
+    presence_penalty: Option<f32>,
     tags: Option<Vec<String>>,
+    word_tag_prefix: Option<String>,
+    namespace_word_tags: Option<bool>,
+    date_tag: Option<bool>,
+    date_tag_format: Option<String>,
+    difficulty: Option<Difficulty>,
+    request_timeout_secs: Option<u64>,
+    connect_timeout_secs: Option<u64>,
+    requests_per_minute: Option<u32>,
+    max_completion_tokens: Option<u32>,
+    add_notes_chunk_size: Option<usize>,
+    max_words_per_run: Option<usize>,
+    append_max_examples: Option<usize>,
+    cost_confirm_threshold: Option<usize>,
+    hindi_reverse: Option<bool>,
+    hindi_direction: Option<HindiDirection>,
+    hindi_model_mode: Option<HindiModelMode>,
+    allow_duplicate: Option<bool>,
+    duplicate_scope: Option<DuplicateScope>,
+    http_proxy: Option<String>,
+    https_proxy: Option<String>,
+    ca_cert_path: Option<PathBuf>,
+    auto_sync: Option<bool>,
+    auto_save_deck: Option<bool>,
+    enrichers: Option<Vec<String>>,
+    seed: Option<u64>,
+    include_ipa: Option<bool>,
+    hint_policy: Option<HintPolicy>,
+    explanation_language: Option<String>,
+    style: Option<String>,
+    image_model: Option<String>,
+    safe_mode: Option<bool>,
+    banned_word_check: Option<bool>,
+    use_default_banned_words: Option<bool>,
+    banned_words: Option<Vec<String>>,
+    structured_output: Option<bool>,
+    tool_calling: Option<bool>,
+    stream: Option<bool>,
+    print_prompt: Option<bool>,
+    strict: Option<bool>,
+    llm_log: Option<PathBuf>,
+    hindi_sentence_words: Option<WordRange>,
+    english_sentence_words: Option<WordRange>,
+    tag_deck_map: Option<TagDeckMap>,
+    presets: Option<BTreeMap<String, DeckPreset>>,
+    frequency_list: Option<PathBuf>,
+    hindi_prompt_template: Option<String>,
+    english_prompt_template: Option<String>,
+    prompt_version: Option<String>,
+    prompt_version_field: Option<String>,
+    source_field: Option<String>,
+    word_field: Option<String>,
+    cloze_field: Option<String>,
+    cloze_fields: Option<ClozeFieldMap>,
+}
+
+/// CEFR level used to constrain vocabulary and sentence complexity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    A1,
+    A2,
+    B1,
+    B2,
+    C1,
+}
+
+impl Difficulty {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Difficulty::A1 => "a1",
+            Difficulty::A2 => "a2",
+            Difficulty::B1 => "b1",
+            Difficulty::B2 => "b2",
+            Difficulty::C1 => "c1",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Difficulty::A1 => {
+                "beginner: only very common everyday words and simple present-tense sentences"
+            }
+            Difficulty::A2 => {
+                "elementary: common vocabulary and simple sentences about familiar topics"
+            }
+            Difficulty::B1 => "intermediate: everyday vocabulary with simple connected clauses",
+            Difficulty::B2 => {
+                "upper-intermediate: broader vocabulary and some complex sentence structures"
+            }
+            Difficulty::C1 => "advanced: rich vocabulary, idiomatic usage and complex structures",
+        }
+    }
+}
+
+/// Which recall directions Hindi notes are created for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HindiDirection {
+    /// Hindi→English and English→Hindi.
+    #[default]
+    Both,
+    /// Only Hindi→English (Hindi on the front).
+    Forward,
+    /// Only English→Hindi (English on the front).
+    Reverse,
+}
+
+impl HindiDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HindiDirection::Both => "both",
+            HindiDirection::Forward => "forward",
+            HindiDirection::Reverse => "reverse",
+        }
+    }
+}
+
+/// How the two directions of a Hindi card are stored in Anki.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HindiModelMode {
+    /// Two separate `Basic` notes, Hindi→English and English→Hindi.
+    #[default]
+    TwoNotes,
+    /// One `Basic (and reversed card)` note that produces both cards.
+    ReversedModel,
+}
+
+impl HindiModelMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HindiModelMode::TwoNotes => "two_notes",
+            HindiModelMode::ReversedModel => "reversed_model",
+        }
+    }
+}
+
+/// Kind of OpenAI-compatible endpoint, which decides the request URLs and
+/// how the API key is sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// `{base}/chat/completions` with a bearer token.
+    #[default]
+    OpenAi,
+    /// Azure OpenAI: `{base}/openai/deployments/{model}/chat/completions`
+    /// with an `api-key` header and an `api-version` query parameter.
+    Azure,
+}
+
+impl Provider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::OpenAi => "openai",
+            Provider::Azure => "azure",
+        }
+    }
+}
+
+/// Where the hint of an English cloze (`{{c1::answer::hint}}`) comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HintPolicy {
+    /// Whatever hint the model suggests, if any.
+    #[default]
+    Model,
+    /// The first letter of the answer, e.g. `w...`.
+    FirstLetter,
+    /// One dash per letter of the answer, e.g. `----`.
+    Length,
+    /// No hint.
+    None,
+}
+
+impl HintPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HintPolicy::Model => "model",
+            HintPolicy::FirstLetter => "first_letter",
+            HintPolicy::Length => "length",
+            HintPolicy::None => "none",
+        }
+    }
+}
+
+/// Where AnkiConnect looks for an existing note with the same first field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateScope {
+    /// Only notes in the target deck count as duplicates.
+    #[default]
+    Deck,
+    /// Notes anywhere in the collection count as duplicates.
+    Collection,
+}
+
+impl DuplicateScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicateScope::Deck => "deck",
+            DuplicateScope::Collection => "collection",
+        }
+    }
+}
+
+/// Inclusive min/max word count for generated sentences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WordRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl WordRange {
+    pub const fn new(min: u32, max: u32) -> Self {
+        Self { min, max }
+    }
+
+    fn validate(&self, key: &str) -> Result<()> {
+        if self.min == 0 || self.max == 0 {
+            anyhow::bail!("{key} bounds must be greater than zero");
+        }
+        if self.min > self.max {
+            anyhow::bail!(
+                "{key} min ({}) must not exceed max ({})",
+                self.min,
+                self.max
+            );
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for WordRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.min, self.max)
+    }
+}
+
+pub const DEFAULT_HINDI_SENTENCE_WORDS: WordRange = WordRange::new(5, 12);
+pub const DEFAULT_ENGLISH_SENTENCE_WORDS: WordRange = WordRange::new(8, 16);
+
+/// Per-language mapping from word tag to the deck that tagged words go to.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TagDeckMap {
+    #[serde(default)]
+    pub hindi: BTreeMap<String, String>,
+    #[serde(default)]
+    pub english: BTreeMap<String, String>,
+}
+
+impl TagDeckMap {
+    fn describe(&self) -> String {
+        let routes: Vec<String> = [("hindi", &self.hindi), ("english", &self.english)]
+            .into_iter()
+            .flat_map(|(language, map)| {
+                map.iter()
+                    .map(move |(tag, deck)| format!("{language}:{tag} -> {deck}"))
+            })
+            .collect();
+
+        if routes.is_empty() {
+            "<none>".to_string()
+        } else {
+            routes.join(", ")
+        }
+    }
+}
+
+/// Cloze note fields that receive parts of the generated card on their own.
+/// Parts without a field stay in `Back Extra`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ClozeFieldMap {
+    pub translation: Option<String>,
+    pub hint: Option<String>,
+    pub ipa: Option<String>,
+    pub synonyms: Option<String>,
+    pub antonyms: Option<String>,
+}
+
+impl ClozeFieldMap {
+    /// Names of the mapped fields.
+    pub fn fields(&self) -> Vec<&str> {
+        [
+            &self.translation,
+            &self.hint,
+            &self.ipa,
+            &self.synonyms,
+            &self.antonyms,
+        ]
+        .into_iter()
+        .filter_map(|field| field.as_deref())
+        .collect()
+    }
+
+    fn describe(&self) -> String {
+        let routes: Vec<String> = [
+            ("translation", &self.translation),
+            ("hint", &self.hint),
+            ("ipa", &self.ipa),
+            ("synonyms", &self.synonyms),
+            ("antonyms", &self.antonyms),
+        ]
+        .into_iter()
+        .filter_map(|(part, field)| field.as_ref().map(|field| format!("{part} -> {field}")))
+        .collect();
+
+        if routes.is_empty() {
+            "<none>".to_string()
+        } else {
+            routes.join(", ")
+        }
+    }
+}
+
+/// Named pair of decks selected with `--preset`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DeckPreset {
+    pub hindi_deck: Option<String>,
+    pub english_deck: Option<String>,
+}
+
+/// Where a resolved configuration value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Cli,
+    Env,
+    File,
+    Default,
+}
+
+impl ConfigSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigSource::Cli => "cli",
+            ConfigSource::Env => "env",
+            ConfigSource::File => "file",
+            ConfigSource::Default => "default",
+        }
+    }
+}
+
+/// Per-field provenance for a resolved [`Config`].
+#[derive(Debug, Clone)]
+pub struct ConfigSources {
+    pub openai_api_key: ConfigSource,
+    pub openai_model: ConfigSource,
+    pub openai_base_url: ConfigSource,
+    pub openai_org: ConfigSource,
+    pub openai_project: ConfigSource,
+    pub provider: ConfigSource,
+    pub extra_headers: ConfigSource,
+    pub extra_query: ConfigSource,
+    pub anki_connect_url: ConfigSource,
+    pub hindi_deck: ConfigSource,
+    pub english_deck: ConfigSource,
+    pub temperature: ConfigSource,
+    pub top_p: ConfigSource,
+    pub frequency_penalty: ConfigSource,
+    pub presence_penalty: ConfigSource,
+    pub tags: ConfigSource,
+    pub word_tag_prefix: ConfigSource,
+    pub namespace_word_tags: ConfigSource,
+    pub date_tag: ConfigSource,
+    pub date_tag_format: ConfigSource,
+    pub difficulty: ConfigSource,
+    pub request_timeout_secs: ConfigSource,
+    pub connect_timeout_secs: ConfigSource,
+    pub requests_per_minute: ConfigSource,
+    pub max_completion_tokens: ConfigSource,
+    pub add_notes_chunk_size: ConfigSource,
+    pub max_words_per_run: ConfigSource,
+    pub append_max_examples: ConfigSource,
+    pub cost_confirm_threshold: ConfigSource,
+    pub hindi_direction: ConfigSource,
+    pub hindi_model_mode: ConfigSource,
+    pub allow_duplicate: ConfigSource,
+    pub duplicate_scope: ConfigSource,
+    pub http_proxy: ConfigSource,
+    pub https_proxy: ConfigSource,
+    pub ca_cert_path: ConfigSource,
+    pub auto_sync: ConfigSource,
+    pub auto_save_deck: ConfigSource,
+    pub enrichers: ConfigSource,
+    pub seed: ConfigSource,
+    pub include_ipa: ConfigSource,
+    pub hint_policy: ConfigSource,
+    pub explanation_language: ConfigSource,
+    pub style: ConfigSource,
+    pub image_model: ConfigSource,
+    pub safe_mode: ConfigSource,
+    pub banned_word_check: ConfigSource,
+    pub use_default_banned_words: ConfigSource,
+    pub banned_words: ConfigSource,
+    pub structured_output: ConfigSource,
+    pub tool_calling: ConfigSource,
+    pub stream: ConfigSource,
+    pub print_prompt: ConfigSource,
+    pub strict: ConfigSource,
+    pub llm_log: ConfigSource,
+    pub hindi_sentence_words: ConfigSource,
+    pub english_sentence_words: ConfigSource,
+    pub tag_deck_map: ConfigSource,
+    pub presets: ConfigSource,
+    pub frequency_list: ConfigSource,
+    pub hindi_prompt_template: ConfigSource,
+    pub english_prompt_template: ConfigSource,
+    pub prompt_version: ConfigSource,
+    pub prompt_version_field: ConfigSource,
+    pub source_field: ConfigSource,
+    pub word_field: ConfigSource,
+    pub cloze_field: ConfigSource,
+    pub cloze_fields: ConfigSource,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigResolution {
+    pub config: Config,
+    pub sources: ConfigSources,
+}
+
+impl ConfigResolution {
+    /// Key, display value and source for each setting, with secrets redacted.
+    pub fn entries(&self) -> Vec<(&'static str, String, ConfigSource)> {
+        let config = &self.config;
+        let sources = &self.sources;
+        let built_in_banned_words = if config.use_default_banned_words {
+            DEFAULT_BANNED_WORDS.len()
+        } else {
+            0
+        };
+        vec![
+            (
+                "openai_api_key",
+                if config.openai_api_key.is_empty() {
+                    "<none>".to_string()
+                } else {
+                    redact_secret(&config.openai_api_key)
+                },
+                sources.openai_api_key,
+            ),
+            (
+                "openai_model",
+                config.openai_model.clone(),
+                sources.openai_model,
+            ),
+            (
+                "openai_base_url",
+                config.openai_base_url.clone(),
+                sources.openai_base_url,
+            ),
+            (
+                "openai_org",
+                display_optional(config.openai_org.as_deref()),
+                sources.openai_org,
+            ),
+            (
+                "openai_project",
+                display_optional(config.openai_project.as_deref()),
+                sources.openai_project,
+            ),
+            (
+                "provider",
+                config.provider.as_str().to_string(),
+                sources.provider,
+            ),
+            (
+                "extra_headers",
+                describe_names(&config.extra_headers),
+                sources.extra_headers,
+            ),
+            (
+                "extra_query",
+                describe_pairs(&config.extra_query),
+                sources.extra_query,
+            ),
+            (
+                "anki_connect_url",
+                config.anki_connect_url.clone(),
+                sources.anki_connect_url,
+            ),
+            ("hindi_deck", config.hindi_deck.clone(), sources.hindi_deck),
+            (
+                "english_deck",
+                config.english_deck.clone(),
+                sources.english_deck,
+            ),
+            (
+                "temperature",
+                config.temperature.to_string(),
+                sources.temperature,
+            ),
+            (
+                "top_p",
+                display_optional(config.top_p.map(|top_p| top_p.to_string()).as_deref()),
+                sources.top_p,
+            ),
+            (
+                "frequency_penalty",
+                display_optional(
+                    config
+                        .frequency_penalty
+                        .map(|penalty| penalty.to_string())
+                        .as_deref(),
+                ),
+                sources.frequency_penalty,
+            ),
+            (
+                "presence_penalty",
+                display_optional(
+                    config
+                        .presence_penalty
+                        .map(|penalty| penalty.to_string())
+                        .as_deref(),
+                ),
+                sources.presence_penalty,
+            ),
+            ("tags", config.tags.join(", "), sources.tags),
+            (
+                "word_tag_prefix",
+                config.word_tag_prefix.clone(),
+                sources.word_tag_prefix,
+            ),
+            (
+                "namespace_word_tags",
+                config.namespace_word_tags.to_string(),
+                sources.namespace_word_tags,
+            ),
+            ("date_tag", config.date_tag.to_string(), sources.date_tag),
+            (
+                "date_tag_format",
+                config.date_tag_format.clone(),
+                sources.date_tag_format,
+            ),
+            (
+                "difficulty",
+                display_optional(config.difficulty.map(|level| level.as_str())),
+                sources.difficulty,
+            ),
+            (
+                "request_timeout_secs",
+                config.request_timeout_secs.to_string(),
+                sources.request_timeout_secs,
+            ),
+            (
+                "connect_timeout_secs",
+                config.connect_timeout_secs.to_string(),
+                sources.connect_timeout_secs,
+            ),
+            (
+                "requests_per_minute",
+                display_optional(
+                    config
+                        .requests_per_minute
+                        .map(|rpm| rpm.to_string())
+                        .as_deref(),
+                ),
+                sources.requests_per_minute,
+            ),
+            (
+                "max_completion_tokens",
+                display_optional(
+                    config
+                        .max_completion_tokens
+                        .map(|tokens| tokens.to_string())
+                        .as_deref(),
+                ),
+                sources.max_completion_tokens,
+            ),
+            (
+                "add_notes_chunk_size",
+                config.add_notes_chunk_size.to_string(),
+                sources.add_notes_chunk_size,
+            ),
+            (
+                "max_words_per_run",
+                config.max_words_per_run.to_string(),
+                sources.max_words_per_run,
+            ),
+            (
+                "append_max_examples",
+                config.append_max_examples.to_string(),
+                sources.append_max_examples,
+            ),
+            (
+                "cost_confirm_threshold",
+                config.cost_confirm_threshold.to_string(),
+                sources.cost_confirm_threshold,
+            ),
+            (
+                "hindi_direction",
+                config.hindi_direction.as_str().to_string(),
+                sources.hindi_direction,
+            ),
+            (
+                "hindi_model_mode",
+                config.hindi_model_mode.as_str().to_string(),
+                sources.hindi_model_mode,
+            ),
+            (
+                "allow_duplicate",
+                config.allow_duplicate.to_string(),
+                sources.allow_duplicate,
+            ),
+            (
+                "duplicate_scope",
+                config.duplicate_scope.as_str().to_string(),
+                sources.duplicate_scope,
+            ),
+            (
+                "http_proxy",
+                display_optional(config.http_proxy.as_deref()),
+                sources.http_proxy,
+            ),
+            (
+                "https_proxy",
+                display_optional(config.https_proxy.as_deref()),
+                sources.https_proxy,
+            ),
+            (
+                "ca_cert_path",
+                display_optional(
+                    config
+                        .ca_cert_path
+                        .as_ref()
+                        .map(|path| path.display().to_string())
+                        .as_deref(),
+                ),
+                sources.ca_cert_path,
+            ),
+            ("auto_sync", config.auto_sync.to_string(), sources.auto_sync),
+            (
+                "auto_save_deck",
+                config.auto_save_deck.to_string(),
+                sources.auto_save_deck,
+            ),
+            (
+                "enrichers",
+                display_optional(
+                    Some(config.enrichers.join(", "))
+                        .filter(|names| !names.is_empty())
+                        .as_deref(),
+                ),
+                sources.enrichers,
+            ),
+            (
+                "seed",
+                display_optional(config.seed.map(|seed| seed.to_string()).as_deref()),
+                sources.seed,
+            ),
+            (
+                "include_ipa",
+                config.include_ipa.to_string(),
+                sources.include_ipa,
+            ),
+            (
+                "hint_policy",
+                config.hint_policy.as_str().to_string(),
+                sources.hint_policy,
+            ),
+            (
+                "explanation_language",
+                config.explanation_language.clone(),
+                sources.explanation_language,
+            ),
+            (
+                "style",
+                display_optional(config.style.as_deref()),
+                sources.style,
+            ),
+            (
+                "image_model",
+                config.image_model.clone(),
+                sources.image_model,
+            ),
+            ("safe_mode", config.safe_mode.to_string(), sources.safe_mode),
+            (
+                "banned_word_check",
+                config.banned_word_check.to_string(),
+                sources.banned_word_check,
+            ),
+            (
+                "use_default_banned_words",
+                config.use_default_banned_words.to_string(),
+                sources.use_default_banned_words,
+            ),
+            (
+                "banned_words",
+                format!(
+                    "{} built-in + {} configured",
+                    built_in_banned_words,
+                    config.banned_words.len() - built_in_banned_words
+                ),
+                sources.banned_words,
+            ),
+            (
+                "structured_output",
+                config.structured_output.to_string(),
+                sources.structured_output,
+            ),
+            (
+                "tool_calling",
+                config.tool_calling.to_string(),
+                sources.tool_calling,
+            ),
+            ("stream", config.stream.to_string(), sources.stream),
+            (
+                "print_prompt",
+                config.print_prompt.to_string(),
+                sources.print_prompt,
+            ),
+            ("strict", config.strict.to_string(), sources.strict),
+            (
+                "llm_log",
+                display_optional(
+                    config
+                        .llm_log
+                        .as_ref()
+                        .map(|path| path.display().to_string())
+                        .as_deref(),
+                ),
+                sources.llm_log,
+            ),
+            (
+                "hindi_sentence_words",
+                config.hindi_sentence_words.to_string(),
+                sources.hindi_sentence_words,
+            ),
+            (
+                "english_sentence_words",
+                config.english_sentence_words.to_string(),
+                sources.english_sentence_words,
+            ),
+            (
+                "tag_deck_map",
+                config.tag_deck_map.describe(),
+                sources.tag_deck_map,
+            ),
+            (
+                "presets",
+                if config.presets.is_empty() {
+                    "<none>".to_string()
+                } else {
+                    config
+                        .presets
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                },
+                sources.presets,
+            ),
+            (
+                "frequency_list",
+                display_optional(
+                    config
+                        .frequency_list
+                        .as_ref()
+                        .map(|path| path.display().to_string())
+                        .as_deref(),
+                ),
+                sources.frequency_list,
+            ),
+            (
+                "hindi_prompt_template",
+                display_template(config.hindi_prompt_template.as_deref()),
+                sources.hindi_prompt_template,
+            ),
+            (
+                "english_prompt_template",
+                display_template(config.english_prompt_template.as_deref()),
+                sources.english_prompt_template,
+            ),
+            (
+                "prompt_version",
+                config.prompt_version.clone(),
+                sources.prompt_version,
+            ),
+            (
+                "prompt_version_field",
+                display_optional(config.prompt_version_field.as_deref()),
+                sources.prompt_version_field,
+            ),
+            (
+                "source_field",
+                display_optional(config.source_field.as_deref()),
+                sources.source_field,
+            ),
+            (
+                "word_field",
+                display_optional(config.word_field.as_deref()),
+                sources.word_field,
+            ),
+            (
+                "cloze_field",
+                display_optional(config.cloze_field.as_deref()),
+                sources.cloze_field,
+            ),
+            (
+                "cloze_fields",
+                config.cloze_fields.describe(),
+                sources.cloze_fields,
+            ),
+        ]
+    }
+}
+
+/// Values collected by the `init` wizard for a fresh config file.
+#[derive(Debug, Clone)]
+pub(crate) struct InitialConfig {
+    pub(crate) openai_api_key: Option<String>,
+    pub(crate) openai_model: String,
+    pub(crate) anki_connect_url: String,
+    pub(crate) hindi_deck: String,
+    pub(crate) english_deck: String,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -37,62 +990,436 @@ pub struct ConfigOverrides {
     pub anki_url: Option<String>,
     pub hindi_deck: Option<String>,
     pub english_deck: Option<String>,
+    pub preset: Option<String>,
     pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
     pub extra_tags: Option<Vec<String>>,
+    pub word_tag_prefix: Option<String>,
+    pub difficulty: Option<Difficulty>,
+    pub request_timeout_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+    pub requests_per_minute: Option<u32>,
+    pub max_completion_tokens: Option<u32>,
+    pub hindi_direction: Option<HindiDirection>,
+    pub allow_duplicate: Option<bool>,
+    pub duplicate_scope: Option<DuplicateScope>,
+    pub auto_sync: Option<bool>,
+    pub auto_save_deck: Option<bool>,
+    pub stream: Option<bool>,
+    pub print_prompt: Option<bool>,
+    pub strict: Option<bool>,
+    pub llm_log: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub explanation_language: Option<String>,
+    pub style: Option<String>,
+    pub prompt_version: Option<String>,
 }
 
 impl Config {
-    pub fn load(config_path: Option<PathBuf>, overrides: ConfigOverrides) -> Result<Self> {
+    /// Resolve the effective configuration, recording where each value came from.
+    pub fn resolve(
+        config_path: Option<PathBuf>,
+        overrides: ConfigOverrides,
+    ) -> Result<ConfigResolution> {
+        Self::resolve_with(config_path, overrides, true)
+    }
+
+    /// Like [`Config::resolve`], but a missing API key is left empty instead
+    /// of failing, so the configuration can be inspected before one is set.
+    pub fn resolve_for_display(
+        config_path: Option<PathBuf>,
+        overrides: ConfigOverrides,
+    ) -> Result<ConfigResolution> {
+        Self::resolve_with(config_path, overrides, false)
+    }
+
+    fn resolve_with(
+        config_path: Option<PathBuf>,
+        overrides: ConfigOverrides,
+        require_api_key: bool,
+    ) -> Result<ConfigResolution> {
         let file_config = load_file_config(config_path.as_ref())?;
 
-        let openai_api_key = file_config
-            .openai_api_key
-            .clone()
-            .or_else(|| env::var("OPENAI_API_KEY").ok())
-            .context("missing OpenAI API key; set OPENAI_API_KEY or add to config")?;
+        let api_key = first_set([
+            (ConfigSource::File, file_config.openai_api_key.clone()),
+            (ConfigSource::Env, env::var("OPENAI_API_KEY").ok()),
+        ]);
+        let (openai_api_key, openai_api_key_source) = match api_key {
+            Some(api_key) => api_key,
+            None if !require_api_key => (String::new(), ConfigSource::Default),
+            None => anyhow::bail!("missing OpenAI API key; set OPENAI_API_KEY or add to config"),
+        };
 
-        let openai_model = overrides
-            .model
-            .clone()
-            .or(file_config.openai_model.clone())
-            .or_else(|| env::var("OPENAI_MODEL").ok())
-            .unwrap_or_else(|| "gpt-4o".to_string());
+        let (openai_model, openai_model_source) = first_set([
+            (ConfigSource::Cli, overrides.model.clone()),
+            (ConfigSource::File, file_config.openai_model.clone()),
+            (ConfigSource::Env, env::var("OPENAI_MODEL").ok()),
+        ])
+        .unwrap_or_else(|| (DEFAULT_OPENAI_MODEL.to_string(), ConfigSource::Default));
 
-        let openai_base_url = file_config
-            .openai_base_url
-            .clone()
-            .or_else(|| env::var("OPENAI_BASE_URL").ok())
-            .unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+        let (openai_base_url, openai_base_url_source) = first_set([
+            (ConfigSource::File, file_config.openai_base_url.clone()),
+            (ConfigSource::Env, env::var("OPENAI_BASE_URL").ok()),
+        ])
+        .unwrap_or_else(|| (DEFAULT_OPENAI_BASE_URL.to_string(), ConfigSource::Default));
 
-        let anki_connect_url = overrides
-            .anki_url
-            .clone()
-            .or(file_config.anki_connect_url.clone())
-            .or_else(|| env::var("ANKI_CONNECT_URL").ok())
-            .unwrap_or_else(|| "http://127.0.0.1:8765".to_string());
+        let (openai_org, openai_org_source) = match first_set([
+            (
+                ConfigSource::File,
+                non_empty(file_config.openai_org.clone()),
+            ),
+            (ConfigSource::Env, non_empty(env::var("OPENAI_ORG_ID").ok())),
+        ]) {
+            Some((org, source)) => (Some(org), source),
+            None => (None, ConfigSource::Default),
+        };
 
-        let hindi_deck = overrides
-            .hindi_deck
-            .clone()
-            .or(file_config.hindi_deck.clone())
-            .unwrap_or_else(|| "Hindi Sentence Practice".to_string());
+        let (openai_project, openai_project_source) = match first_set([
+            (
+                ConfigSource::File,
+                non_empty(file_config.openai_project.clone()),
+            ),
+            (
+                ConfigSource::Env,
+                non_empty(env::var("OPENAI_PROJECT_ID").ok()),
+            ),
+        ]) {
+            Some((project, source)) => (Some(project), source),
+            None => (None, ConfigSource::Default),
+        };
 
-        let english_deck = overrides
-            .english_deck
-            .clone()
-            .or(file_config.english_deck.clone())
-            .unwrap_or_else(|| "English Cloze Practice".to_string());
+        let (anki_connect_url, anki_connect_url_source) = first_set([
+            (ConfigSource::Cli, overrides.anki_url.clone()),
+            (ConfigSource::File, file_config.anki_connect_url.clone()),
+            (ConfigSource::Env, env::var("ANKI_CONNECT_URL").ok()),
+        ])
+        .unwrap_or_else(|| (DEFAULT_ANKI_CONNECT_URL.to_string(), ConfigSource::Default));
+
+        let preset = match &overrides.preset {
+            Some(name) => {
+                let presets = file_config.presets.as_ref();
+                let preset = presets.and_then(|presets| presets.get(name)).cloned();
+                Some(preset.with_context(|| {
+                    let available = presets
+                        .map(|presets| presets.keys().cloned().collect::<Vec<_>>().join(", "))
+                        .filter(|names| !names.is_empty())
+                        .unwrap_or_else(|| "none defined".to_string());
+                    format!("unknown deck preset '{name}' (available: {available})")
+                })?)
+            }
+            None => None,
+        };
+        let (preset_hindi_deck, preset_english_deck) = preset
+            .map(|preset| (preset.hindi_deck, preset.english_deck))
+            .unwrap_or_default();
 
-        let temperature = overrides
-            .temperature
-            .or(file_config.temperature)
-            .or_else(|| {
+        let (hindi_deck, hindi_deck_source) = first_set([
+            (ConfigSource::Cli, overrides.hindi_deck.clone()),
+            (ConfigSource::Cli, preset_hindi_deck),
+            (ConfigSource::File, file_config.hindi_deck.clone()),
+        ])
+        .unwrap_or_else(|| (DEFAULT_HINDI_DECK.to_string(), ConfigSource::Default));
+
+        let (english_deck, english_deck_source) = first_set([
+            (ConfigSource::Cli, overrides.english_deck.clone()),
+            (ConfigSource::Cli, preset_english_deck),
+            (ConfigSource::File, file_config.english_deck.clone()),
+        ])
+        .unwrap_or_else(|| (DEFAULT_ENGLISH_DECK.to_string(), ConfigSource::Default));
+
+        let (temperature, temperature_source) = first_set([
+            (ConfigSource::Cli, overrides.temperature),
+            (ConfigSource::File, file_config.temperature),
+            (
+                ConfigSource::Env,
                 env::var("OPENAI_TEMPERATURE")
                     .ok()
-                    .and_then(|v| v.parse().ok())
-            })
-            .unwrap_or(0.7);
+                    .and_then(|v| v.parse().ok()),
+            ),
+        ])
+        .unwrap_or((0.7, ConfigSource::Default));
+
+        let (top_p, top_p_source) = match first_set([
+            (ConfigSource::Cli, overrides.top_p),
+            (ConfigSource::File, file_config.top_p),
+        ]) {
+            Some((top_p, source)) => (Some(top_p), source),
+            None => (None, ConfigSource::Default),
+        };
+        if let Some(top_p) = top_p
+            && !(top_p > 0.0 && top_p <= 1.0)
+        {
+            anyhow::bail!("top_p must be greater than 0 and at most 1, got {top_p}");
+        }
+        if top_p.is_some() && temperature_source != ConfigSource::Default {
+            tracing::warn!(
+                "Both temperature and top_p are set; OpenAI recommends adjusting only one of them"
+            );
+        }
+
+        if file_config.provider == Some(Provider::Azure)
+            && !file_config
+                .extra_query
+                .as_ref()
+                .is_some_and(|query| query.contains_key("api-version"))
+        {
+            anyhow::bail!(
+                "provider = \"azure\" requires an api-version in extra_query, e.g. extra_query = {{ \"api-version\" = \"2024-10-21\" }}"
+            );
+        }
+
+        let (frequency_penalty, frequency_penalty_source) = resolve_penalty(
+            "frequency_penalty",
+            overrides.frequency_penalty,
+            file_config.frequency_penalty,
+        )?;
+        let (presence_penalty, presence_penalty_source) = resolve_penalty(
+            "presence_penalty",
+            overrides.presence_penalty,
+            file_config.presence_penalty,
+        )?;
+
+        let (difficulty, difficulty_source) = match first_set([
+            (ConfigSource::Cli, overrides.difficulty),
+            (ConfigSource::File, file_config.difficulty),
+        ]) {
+            Some((level, source)) => (Some(level), source),
+            None => (None, ConfigSource::Default),
+        };
+
+        let (request_timeout_secs, request_timeout_secs_source) = first_set([
+            (ConfigSource::Cli, overrides.request_timeout_secs),
+            (ConfigSource::File, file_config.request_timeout_secs),
+        ])
+        .unwrap_or((DEFAULT_REQUEST_TIMEOUT_SECS, ConfigSource::Default));
+        if request_timeout_secs == 0 {
+            anyhow::bail!("request_timeout_secs must be greater than zero");
+        }
+
+        let (connect_timeout_secs, connect_timeout_secs_source) = first_set([
+            (ConfigSource::Cli, overrides.connect_timeout_secs),
+            (ConfigSource::File, file_config.connect_timeout_secs),
+        ])
+        .unwrap_or((DEFAULT_CONNECT_TIMEOUT_SECS, ConfigSource::Default));
+        if connect_timeout_secs == 0 {
+            anyhow::bail!("connect_timeout_secs must be greater than zero");
+        }
+
+        let (requests_per_minute, requests_per_minute_source) = match first_set([
+            (ConfigSource::Cli, overrides.requests_per_minute),
+            (ConfigSource::File, file_config.requests_per_minute),
+        ]) {
+            Some((rpm, source)) => (Some(rpm), source),
+            None => (None, ConfigSource::Default),
+        };
+        if requests_per_minute == Some(0) {
+            anyhow::bail!("requests_per_minute must be greater than zero");
+        }
+
+        let (max_completion_tokens, max_completion_tokens_source) = match first_set([
+            (ConfigSource::Cli, overrides.max_completion_tokens),
+            (ConfigSource::File, file_config.max_completion_tokens),
+        ]) {
+            Some((tokens, source)) => (Some(tokens), source),
+            None => (None, ConfigSource::Default),
+        };
+        if max_completion_tokens == Some(0) {
+            anyhow::bail!("max_completion_tokens must be greater than zero");
+        }
+        if file_config.add_notes_chunk_size == Some(0) {
+            anyhow::bail!("add_notes_chunk_size must be greater than zero");
+        }
+        if file_config.max_words_per_run == Some(0) {
+            anyhow::bail!("max_words_per_run must be greater than zero");
+        }
+        if file_config.append_max_examples == Some(0) {
+            anyhow::bail!("append_max_examples must be greater than zero");
+        }
+
+        // `hindi_reverse = false` predates `hindi_direction` and means `forward`.
+        let legacy_direction = file_config.hindi_reverse.map(|reverse| {
+            if reverse {
+                HindiDirection::Both
+            } else {
+                HindiDirection::Forward
+            }
+        });
+        let (hindi_direction, hindi_direction_source) = first_set([
+            (ConfigSource::Cli, overrides.hindi_direction),
+            (ConfigSource::File, file_config.hindi_direction),
+            (ConfigSource::File, legacy_direction),
+        ])
+        .unwrap_or((HindiDirection::default(), ConfigSource::Default));
+
+        let (allow_duplicate, allow_duplicate_source) = first_set([
+            (ConfigSource::Cli, overrides.allow_duplicate),
+            (ConfigSource::File, file_config.allow_duplicate),
+        ])
+        .unwrap_or((false, ConfigSource::Default));
+
+        let (duplicate_scope, duplicate_scope_source) = first_set([
+            (ConfigSource::Cli, overrides.duplicate_scope),
+            (ConfigSource::File, file_config.duplicate_scope),
+        ])
+        .unwrap_or((DuplicateScope::default(), ConfigSource::Default));
+
+        let (seed, seed_source) = match first_set([
+            (ConfigSource::Cli, overrides.seed),
+            (ConfigSource::File, file_config.seed),
+        ]) {
+            Some((seed, source)) => (Some(seed), source),
+            None => (None, ConfigSource::Default),
+        };
+
+        let (explanation_language, explanation_language_source) = first_set([
+            (
+                ConfigSource::Cli,
+                non_empty(overrides.explanation_language.clone()),
+            ),
+            (
+                ConfigSource::File,
+                non_empty(file_config.explanation_language.clone()),
+            ),
+        ])
+        .unwrap_or_else(|| {
+            (
+                DEFAULT_EXPLANATION_LANGUAGE.to_string(),
+                ConfigSource::Default,
+            )
+        });
+
+        let (style, style_source) = match first_set([
+            (ConfigSource::Cli, overrides.style.clone()),
+            (ConfigSource::File, file_config.style.clone()),
+        ]) {
+            Some((style, source)) => (Some(style.trim().to_string()), source),
+            None => (None, ConfigSource::Default),
+        };
+        if style.as_deref() == Some("") {
+            anyhow::bail!("style must not be empty");
+        }
+
+        let use_default_banned_words = file_config.use_default_banned_words.unwrap_or(true);
+        let mut banned_words: Vec<String> = if use_default_banned_words {
+            DEFAULT_BANNED_WORDS
+                .iter()
+                .map(|word| word.to_string())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        for word in file_config.banned_words.iter().flatten() {
+            let word = word.trim().to_lowercase();
+            if word.is_empty() {
+                anyhow::bail!("banned_words must not contain empty entries");
+            }
+            if !banned_words.contains(&word) {
+                banned_words.push(word);
+            }
+        }
+
+        let (image_model, image_model_source) = first_set([(
+            ConfigSource::File,
+            non_empty(file_config.image_model.clone()),
+        )])
+        .unwrap_or_else(|| (DEFAULT_IMAGE_MODEL.to_string(), ConfigSource::Default));
+
+        let (prompt_version, prompt_version_source) = first_set([
+            (
+                ConfigSource::Cli,
+                non_empty(overrides.prompt_version.clone()),
+            ),
+            (
+                ConfigSource::File,
+                non_empty(file_config.prompt_version.clone()),
+            ),
+        ])
+        .unwrap_or_else(|| (DEFAULT_PROMPT_VERSION.to_string(), ConfigSource::Default));
+
+        let (word_tag_prefix, word_tag_prefix_source) = first_set([
+            (
+                ConfigSource::Cli,
+                non_empty(overrides.word_tag_prefix.clone()),
+            ),
+            (
+                ConfigSource::File,
+                non_empty(file_config.word_tag_prefix.clone()),
+            ),
+        ])
+        .unwrap_or_else(|| (DEFAULT_WORD_TAG_PREFIX.to_string(), ConfigSource::Default));
+
+        let (auto_sync, auto_sync_source) = first_set([
+            (ConfigSource::Cli, overrides.auto_sync),
+            (ConfigSource::File, file_config.auto_sync),
+        ])
+        .unwrap_or((false, ConfigSource::Default));
+
+        let (auto_save_deck, auto_save_deck_source) = first_set([
+            (ConfigSource::Cli, overrides.auto_save_deck),
+            (ConfigSource::File, file_config.auto_save_deck),
+        ])
+        .unwrap_or((true, ConfigSource::Default));
+
+        let (stream, stream_source) = first_set([
+            (ConfigSource::Cli, overrides.stream),
+            (ConfigSource::File, file_config.stream),
+        ])
+        .unwrap_or((false, ConfigSource::Default));
+
+        let (print_prompt, print_prompt_source) = first_set([
+            (ConfigSource::Cli, overrides.print_prompt),
+            (ConfigSource::File, file_config.print_prompt),
+        ])
+        .unwrap_or((false, ConfigSource::Default));
+
+        let (strict, strict_source) = first_set([
+            (ConfigSource::Cli, overrides.strict),
+            (ConfigSource::File, file_config.strict),
+        ])
+        .unwrap_or((false, ConfigSource::Default));
+
+        let (llm_log, llm_log_source) = match first_set([
+            (ConfigSource::Cli, overrides.llm_log),
+            (ConfigSource::File, file_config.llm_log.clone()),
+        ]) {
+            Some((path, source)) => (Some(path), source),
+            None => (None, ConfigSource::Default),
+        };
+
+        let hindi_sentence_words = file_config
+            .hindi_sentence_words
+            .unwrap_or(DEFAULT_HINDI_SENTENCE_WORDS);
+        hindi_sentence_words.validate("hindi_sentence_words")?;
+
+        let english_sentence_words = file_config
+            .english_sentence_words
+            .unwrap_or(DEFAULT_ENGLISH_SENTENCE_WORDS);
+        english_sentence_words.validate("english_sentence_words")?;
+
+        let date_tag_format = file_config
+            .date_tag_format
+            .clone()
+            .unwrap_or_else(|| DEFAULT_DATE_TAG_FORMAT.to_string());
+        if StrftimeItems::new(&date_tag_format).any(|item| matches!(item, Item::Error)) {
+            anyhow::bail!("date_tag_format '{date_tag_format}' is not a valid strftime pattern");
+        }
 
+        validate_prompt_template(
+            "hindi_prompt_template",
+            file_config.hindi_prompt_template.as_deref(),
+        )?;
+        validate_prompt_template(
+            "english_prompt_template",
+            file_config.english_prompt_template.as_deref(),
+        )?;
+
+        let mut tags_source = if file_config.tags.is_some() {
+            ConfigSource::File
+        } else {
+            ConfigSource::Default
+        };
         let mut tags: Vec<String> = file_config
             .tags
             .unwrap_or_else(|| vec!["generated".to_string()])
@@ -109,6 +1436,7 @@ impl Config {
 
         if tags.is_empty() {
             tags.push("generated".to_string());
+            tags_source = ConfigSource::Default;
         }
         if let Some(extra) = overrides.extra_tags {
             for tag in extra {
@@ -116,6 +1444,7 @@ impl Config {
                 if cleaned.is_empty() {
                     continue;
                 }
+                tags_source = ConfigSource::Cli;
                 if !tags
                     .iter()
                     .any(|existing| existing.eq_ignore_ascii_case(cleaned))
@@ -132,17 +1461,162 @@ impl Config {
             default_config_path()
         };
 
-        Ok(Self {
+        let sources = ConfigSources {
+            openai_api_key: openai_api_key_source,
+            openai_model: openai_model_source,
+            openai_base_url: openai_base_url_source,
+            openai_org: openai_org_source,
+            openai_project: openai_project_source,
+            provider: optional_source(&file_config.provider),
+            extra_headers: optional_source(&file_config.extra_headers),
+            extra_query: optional_source(&file_config.extra_query),
+            anki_connect_url: anki_connect_url_source,
+            hindi_deck: hindi_deck_source,
+            english_deck: english_deck_source,
+            temperature: temperature_source,
+            top_p: top_p_source,
+            frequency_penalty: frequency_penalty_source,
+            presence_penalty: presence_penalty_source,
+            tags: tags_source,
+            word_tag_prefix: word_tag_prefix_source,
+            namespace_word_tags: optional_source(&file_config.namespace_word_tags),
+            date_tag: optional_source(&file_config.date_tag),
+            date_tag_format: optional_source(&file_config.date_tag_format),
+            difficulty: difficulty_source,
+            request_timeout_secs: request_timeout_secs_source,
+            connect_timeout_secs: connect_timeout_secs_source,
+            requests_per_minute: requests_per_minute_source,
+            max_completion_tokens: max_completion_tokens_source,
+            add_notes_chunk_size: optional_source(&file_config.add_notes_chunk_size),
+            max_words_per_run: optional_source(&file_config.max_words_per_run),
+            append_max_examples: optional_source(&file_config.append_max_examples),
+            cost_confirm_threshold: optional_source(&file_config.cost_confirm_threshold),
+            hindi_direction: hindi_direction_source,
+            hindi_model_mode: optional_source(&file_config.hindi_model_mode),
+            allow_duplicate: allow_duplicate_source,
+            duplicate_scope: duplicate_scope_source,
+            http_proxy: optional_source(&file_config.http_proxy),
+            https_proxy: optional_source(&file_config.https_proxy),
+            ca_cert_path: optional_source(&file_config.ca_cert_path),
+            auto_sync: auto_sync_source,
+            auto_save_deck: auto_save_deck_source,
+            enrichers: optional_source(&file_config.enrichers),
+            seed: seed_source,
+            include_ipa: optional_source(&file_config.include_ipa),
+            hint_policy: optional_source(&file_config.hint_policy),
+            explanation_language: explanation_language_source,
+            style: style_source,
+            image_model: image_model_source,
+            safe_mode: optional_source(&file_config.safe_mode),
+            banned_word_check: optional_source(&file_config.banned_word_check),
+            use_default_banned_words: optional_source(&file_config.use_default_banned_words),
+            banned_words: optional_source(&file_config.banned_words),
+            structured_output: optional_source(&file_config.structured_output),
+            tool_calling: optional_source(&file_config.tool_calling),
+            stream: stream_source,
+            print_prompt: print_prompt_source,
+            strict: strict_source,
+            llm_log: llm_log_source,
+            hindi_sentence_words: optional_source(&file_config.hindi_sentence_words),
+            english_sentence_words: optional_source(&file_config.english_sentence_words),
+            tag_deck_map: optional_source(&file_config.tag_deck_map),
+            presets: optional_source(&file_config.presets),
+            frequency_list: optional_source(&file_config.frequency_list),
+            hindi_prompt_template: optional_source(&file_config.hindi_prompt_template),
+            english_prompt_template: optional_source(&file_config.english_prompt_template),
+            prompt_version: prompt_version_source,
+            prompt_version_field: optional_source(&file_config.prompt_version_field),
+            source_field: optional_source(&file_config.source_field),
+            word_field: optional_source(&file_config.word_field),
+            cloze_field: optional_source(&file_config.cloze_field),
+            cloze_fields: optional_source(&file_config.cloze_fields),
+        };
+
+        let config = Self {
             openai_api_key,
             openai_model,
             openai_base_url,
+            openai_org,
+            openai_project,
+            provider: file_config.provider.unwrap_or_default(),
+            extra_headers: file_config.extra_headers.unwrap_or_default(),
+            extra_query: file_config.extra_query.unwrap_or_default(),
             anki_connect_url,
             hindi_deck,
             english_deck,
             temperature,
+            top_p,
+            frequency_penalty,
+            presence_penalty,
             tags,
+            word_tag_prefix,
+            namespace_word_tags: file_config.namespace_word_tags.unwrap_or(false),
+            date_tag: file_config.date_tag.unwrap_or(false),
+            date_tag_format,
+            difficulty,
+            request_timeout_secs,
+            connect_timeout_secs,
+            requests_per_minute,
+            max_completion_tokens,
+            add_notes_chunk_size: file_config
+                .add_notes_chunk_size
+                .unwrap_or(DEFAULT_ADD_NOTES_CHUNK_SIZE),
+            max_words_per_run: file_config
+                .max_words_per_run
+                .unwrap_or(DEFAULT_MAX_WORDS_PER_RUN),
+            append_max_examples: file_config
+                .append_max_examples
+                .unwrap_or(DEFAULT_APPEND_MAX_EXAMPLES),
+            cost_confirm_threshold: file_config
+                .cost_confirm_threshold
+                .unwrap_or(DEFAULT_COST_CONFIRM_THRESHOLD),
+            hindi_direction,
+            hindi_model_mode: file_config.hindi_model_mode.unwrap_or_default(),
+            allow_duplicate,
+            duplicate_scope,
+            http_proxy: file_config.http_proxy,
+            https_proxy: file_config.https_proxy,
+            ca_cert_path: file_config.ca_cert_path,
+            auto_sync,
+            auto_save_deck,
+            enrichers: file_config.enrichers.unwrap_or_default(),
+            seed,
+            include_ipa: file_config.include_ipa.unwrap_or(false),
+            hint_policy: file_config.hint_policy.unwrap_or_default(),
+            explanation_language,
+            style,
+            image_model,
+            safe_mode: file_config.safe_mode.unwrap_or(false),
+            banned_word_check: file_config.banned_word_check.unwrap_or(true),
+            use_default_banned_words,
+            banned_words,
+            structured_output: file_config.structured_output.unwrap_or(false),
+            tool_calling: file_config.tool_calling.unwrap_or(false),
+            stream,
+            print_prompt,
+            strict,
+            llm_log,
+            hindi_sentence_words,
+            english_sentence_words,
+            tag_deck_map: file_config.tag_deck_map.unwrap_or_default(),
+            presets: file_config.presets.unwrap_or_default(),
+            frequency_list: file_config.frequency_list,
+            hindi_prompt_template: file_config.hindi_prompt_template,
+            english_prompt_template: file_config.english_prompt_template,
+            prompt_version,
+            prompt_version_field: non_empty(file_config.prompt_version_field),
+            source_field: non_empty(file_config.source_field),
+            word_field: non_empty(file_config.word_field),
+            cloze_field: non_empty(file_config.cloze_field),
+            cloze_fields: file_config.cloze_fields.unwrap_or_default(),
             config_path,
-        })
+        };
+
+        Ok(ConfigResolution { config, sources })
+    }
+
+    pub fn config_path(&self) -> Option<&Path> {
+        self.config_path.as_deref()
     }
 
     /// Save the Hindi deck name to the config file for future use
@@ -162,6 +1636,10 @@ impl Config {
                 .context("could not determine config file path to save deck")?,
         };
 
+        // Hold the lock across the read-modify-write so concurrent runs
+        // cannot clobber each other's deck
+        let _lock = ConfigLock::acquire(&config_path)?;
+
         // Load existing config or create new one
         let mut file_config = if config_path.exists() {
             read_config_from_path(&config_path)?
@@ -176,24 +1654,213 @@ impl Config {
             _ => anyhow::bail!("unknown deck field: {}", field),
         }
 
-        // Ensure the config directory exists
+        write_config_to_path(&config_path, &file_config)?;
+
+        tracing::debug!("Saved {} to config file: {}", field, value);
+        Ok(())
+    }
+}
+
+/// Write a new config file containing the values gathered by `init`.
+pub(crate) fn write_initial_config(path: &Path, initial: InitialConfig) -> Result<()> {
+    let file_config = FileConfig {
+        openai_api_key: initial.openai_api_key,
+        openai_model: Some(initial.openai_model),
+        anki_connect_url: Some(initial.anki_connect_url),
+        hindi_deck: Some(initial.hindi_deck),
+        english_deck: Some(initial.english_deck),
+        ..FileConfig::default()
+    };
+
+    write_config_to_path(path, &file_config)
+}
+
+fn write_config_to_path(path: &Path, file_config: &FileConfig) -> Result<()> {
+    // Ensure the config directory exists
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| {
+            format!("failed to create config directory at {}", parent.display())
+        })?;
+    }
+
+    // Serialize and write the config to a temporary file next to it, then
+    // rename it into place so readers never see a partial file
+    let toml_string =
+        toml::to_string_pretty(file_config).context("failed to serialize config to TOML")?;
+    let temp_path = sibling_path(path, &format!(".tmp-{}", std::process::id()));
+    fs::write(&temp_path, toml_string)
+        .with_context(|| format!("failed to write config file to {}", temp_path.display()))?;
+    fs::rename(&temp_path, path).map_err(|err| {
+        let _ = fs::remove_file(&temp_path);
+        anyhow::Error::new(err).context(format!(
+            "failed to replace config file at {}",
+            path.display()
+        ))
+    })
+}
+
+/// How long a config save waits for another run to release the lock.
+const CONFIG_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Age after which a lock file is assumed to be left over from a crashed run.
+const CONFIG_LOCK_STALE_AFTER: Duration = Duration::from_secs(30);
+
+/// Advisory lock on a config file: `<config>.lock` exists while it is held
+/// and is removed when the lock is dropped.
+struct ConfigLock {
+    path: PathBuf,
+}
+
+impl ConfigLock {
+    fn acquire(config_path: &Path) -> Result<Self> {
         if let Some(parent) = config_path.parent() {
             fs::create_dir_all(parent).with_context(|| {
                 format!("failed to create config directory at {}", parent.display())
             })?;
         }
 
-        // Serialize and write the config
-        let toml_string =
-            toml::to_string_pretty(&file_config).context("failed to serialize config to TOML")?;
-        fs::write(&config_path, toml_string)
-            .with_context(|| format!("failed to write config file to {}", config_path.display()))?;
+        let path = sibling_path(config_path, ".lock");
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    if lock_is_stale(&path) {
+                        tracing::warn!("Removing stale config lock {}", path.display());
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if started.elapsed() >= CONFIG_LOCK_TIMEOUT {
+                        anyhow::bail!(
+                            "timed out waiting for config lock {}; remove it if no other anki-cli run is active",
+                            path.display()
+                        );
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("failed to create config lock {}", path.display())
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ConfigLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
 
-        tracing::debug!("Saved {} to config file: {}", field, value);
-        Ok(())
+fn lock_is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > CONFIG_LOCK_STALE_AFTER)
+}
+
+/// `path` with `suffix` appended to its file name, e.g. `config.toml.lock`.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Mask all but the last four characters of a secret.
+fn redact_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 4 {
+        return "****".to_string();
+    }
+
+    let visible: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{visible}")
+}
+
+/// Just the keys of a map, for values that may hold secrets.
+fn describe_names(map: &BTreeMap<String, String>) -> String {
+    if map.is_empty() {
+        "<none>".to_string()
+    } else {
+        map.keys().cloned().collect::<Vec<_>>().join(", ")
+    }
+}
+
+fn describe_pairs(map: &BTreeMap<String, String>) -> String {
+    if map.is_empty() {
+        "<none>".to_string()
+    } else {
+        map.iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 }
 
+fn display_optional(value: Option<&str>) -> String {
+    value.unwrap_or("<none>").to_string()
+}
+
+fn display_template(template: Option<&str>) -> String {
+    match template {
+        Some(template) => format!("<custom, {} chars>", template.chars().count()),
+        None => "<built-in>".to_string(),
+    }
+}
+
+/// Prompt templates must at least name the target word; the sentence-length
+/// placeholders are optional.
+fn validate_prompt_template(key: &str, template: Option<&str>) -> Result<()> {
+    if let Some(template) = template
+        && !template.contains(PROMPT_WORD_PLACEHOLDER)
+    {
+        anyhow::bail!(
+            "{key} must contain the {PROMPT_WORD_PLACEHOLDER} placeholder (optional: {{min_words}}, {{max_words}})"
+        );
+    }
+    Ok(())
+}
+
+fn optional_source<T>(value: &Option<T>) -> ConfigSource {
+    if value.is_some() {
+        ConfigSource::File
+    } else {
+        ConfigSource::Default
+    }
+}
+
+/// Treat blank strings (e.g. `OPENAI_ORG_ID=`) as unset.
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Resolve an OpenAI penalty setting, which the API only accepts in -2.0..=2.0.
+fn resolve_penalty(
+    key: &str,
+    cli: Option<f32>,
+    file: Option<f32>,
+) -> Result<(Option<f32>, ConfigSource)> {
+    match first_set([(ConfigSource::Cli, cli), (ConfigSource::File, file)]) {
+        Some((penalty, _)) if !(-2.0..=2.0).contains(&penalty) => {
+            anyhow::bail!("{key} must be between -2.0 and 2.0, got {penalty}")
+        }
+        Some((penalty, source)) => Ok((Some(penalty), source)),
+        None => Ok((None, ConfigSource::Default)),
+    }
+}
+
+fn first_set<T, const N: usize>(
+    candidates: [(ConfigSource, Option<T>); N],
+) -> Option<(T, ConfigSource)> {
+    candidates
+        .into_iter()
+        .find_map(|(source, value)| value.map(|value| (value, source)))
+}
+
 fn load_file_config(path: Option<&PathBuf>) -> Result<FileConfig> {
     if let Some(path) = path {
         if path.exists() {
@@ -202,10 +1869,10 @@ fn load_file_config(path: Option<&PathBuf>) -> Result<FileConfig> {
         anyhow::bail!("config path {:?} does not exist", path);
     }
 
-    if let Some(default_path) = default_config_path() {
-        if default_path.exists() {
-            return read_config_from_path(&default_path);
-        }
+    if let Some(default_path) = default_config_path()
+        && default_path.exists()
+    {
+        return read_config_from_path(&default_path);
     }
 
     Ok(FileConfig::default())
@@ -218,7 +1885,55 @@ fn read_config_from_path(path: &Path) -> Result<FileConfig> {
         .with_context(|| format!("failed to parse config file at {}", path.display()))
 }
 
-fn default_config_path() -> Option<PathBuf> {
+pub(crate) fn default_config_path() -> Option<PathBuf> {
     ProjectDirs::from("com", "language-cli", "anki-cli")
         .map(|dirs| dirs.config_dir().join("config.toml"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_config;
+
+    #[test]
+    fn concurrent_deck_saves_keep_both_decks() {
+        let config = test_config("http://127.0.0.1:9", "");
+        let path = config.config_path().unwrap().to_path_buf();
+
+        let handles: Vec<_> = (0..8)
+            .map(|idx| {
+                let config = config.clone();
+                thread::spawn(move || {
+                    if idx % 2 == 0 {
+                        config.save_hindi_deck(&format!("Hindi::{idx}"))
+                    } else {
+                        config.save_english_deck(&format!("English::{idx}"))
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+
+        let saved = read_config_from_path(&path).unwrap();
+        assert!(saved.hindi_deck.unwrap().starts_with("Hindi::"));
+        assert!(saved.english_deck.unwrap().starts_with("English::"));
+        assert_eq!(saved.openai_api_key.as_deref(), Some("test-key"));
+        assert!(!sibling_path(&path, ".lock").exists());
+    }
+
+    #[test]
+    fn banned_words_extend_the_built_in_list_unless_disabled() {
+        let config = test_config("http://127.0.0.1:9", r#"banned_words = ["Stupid", "shit"]"#);
+        assert_eq!(config.banned_words.len(), DEFAULT_BANNED_WORDS.len() + 1);
+        assert!(config.banned_words.iter().any(|word| word == "stupid"));
+        assert!(!config.banned_words.iter().any(|word| word == "damn"));
+
+        let config = test_config(
+            "http://127.0.0.1:9",
+            "use_default_banned_words = false\nbanned_words = [\"stupid\"]",
+        );
+        assert_eq!(config.banned_words, ["stupid"]);
+    }
+}
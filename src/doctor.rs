@@ -0,0 +1,152 @@
+use anyhow::Result;
+
+use crate::error::AnkiCliError;
+use crate::workflows::{
+    ENGLISH_FIELDS, ENGLISH_MODEL, HINDI_FIELDS, RunContext, cloze_text_field, ensure_note_fields,
+    hindi_model,
+};
+
+struct Check {
+    name: String,
+    outcome: Result<String, String>,
+}
+
+pub async fn run_doctor(ctx: &RunContext<'_>) -> Result<()> {
+    let mut checks = Vec::new();
+
+    checks.push(Check {
+        name: format!("OpenAI reachable at {}", ctx.config.openai_base_url),
+        outcome: match ctx.llm.list_models().await {
+            Ok(models) if models.iter().any(|id| id == ctx.llm.model()) => {
+                Ok(format!("model '{}' is available", ctx.llm.model()))
+            }
+            Ok(models) => Ok(format!(
+                "{} models listed, but '{}' was not among them",
+                models.len(),
+                ctx.llm.model()
+            )),
+            Err(err) => Err(describe(err)),
+        },
+    });
+
+    let anki_name = format!("AnkiConnect reachable at {}", ctx.config.anki_connect_url);
+    match ctx.anki.version().await {
+        Ok(version) => checks.push(Check {
+            name: anki_name,
+            outcome: Ok(format!("API version {version}")),
+        }),
+        Err(err) => {
+            checks.push(Check {
+                name: anki_name,
+                outcome: Err(describe(err)),
+            });
+            // Every remaining check needs AnkiConnect.
+            return report(checks);
+        }
+    }
+
+    match ctx.anki.deck_names().await {
+        Ok(decks) => {
+            for deck in [&ctx.config.hindi_deck, &ctx.config.english_deck] {
+                checks.push(Check {
+                    name: format!("Deck '{deck}' exists"),
+                    outcome: if decks.contains(deck) {
+                        Ok("found".to_string())
+                    } else {
+                        Err("not found (it will be created on the first run)".to_string())
+                    },
+                });
+            }
+        }
+        Err(err) => checks.push(Check {
+            name: "Decks exist".to_string(),
+            outcome: Err(describe(err)),
+        }),
+    }
+
+    let models = match ctx.anki.model_names().await {
+        Ok(models) => models,
+        Err(err) => {
+            checks.push(Check {
+                name: "Note types exist".to_string(),
+                outcome: Err(describe(err)),
+            });
+            return report(checks);
+        }
+    };
+
+    for (model, built_in) in [
+        (hindi_model(ctx.config), HINDI_FIELDS.as_slice()),
+        // The cloze sentence field is resolved like a run does, below.
+        (ENGLISH_MODEL, &ENGLISH_FIELDS[1..]),
+    ] {
+        let name = format!("Note type '{model}' has the fields the run writes");
+        if !models.iter().any(|existing| existing == model) {
+            checks.push(Check {
+                name,
+                outcome: Err("note type not found".to_string()),
+            });
+            continue;
+        }
+
+        checks.push(Check {
+            name,
+            outcome: note_type_fields(ctx, model, built_in).await,
+        });
+    }
+
+    report(checks)
+}
+
+/// The same field checks a run makes before using `model`, plus the built-in
+/// fields its notes are written with.
+async fn note_type_fields(
+    ctx: &RunContext<'_>,
+    model: &str,
+    built_in: &[&str],
+) -> Result<String, String> {
+    ensure_note_fields(ctx, &[model])
+        .await
+        .map_err(|err| format!("{err:#}"))?;
+    let available = ctx.anki.model_field_names(model).await.map_err(describe)?;
+    let missing: Vec<&str> = built_in
+        .iter()
+        .copied()
+        .filter(|field| !available.iter().any(|name| name == field))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("missing fields: {}", missing.join(", ")));
+    }
+    if model == ENGLISH_MODEL {
+        let field = cloze_text_field(ctx)
+            .await
+            .map_err(|err| format!("{err:#}"))?;
+        return Ok(format!("ok (clozes go to '{field}')"));
+    }
+    Ok("ok".to_string())
+}
+
+/// Render an error with its full source chain.
+fn describe(err: AnkiCliError) -> String {
+    format!("{:#}", anyhow::Error::from(err))
+}
+
+fn report(checks: Vec<Check>) -> Result<()> {
+    let mut failures = 0;
+    for check in &checks {
+        match &check.outcome {
+            Ok(detail) => println!("[PASS] {}: {}", check.name, detail),
+            Err(detail) => {
+                failures += 1;
+                println!("[FAIL] {}: {}", check.name, detail);
+            }
+        }
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{failures} of {} checks failed", checks.len());
+    }
+
+    println!("All {} checks passed.", checks.len());
+    Ok(())
+}
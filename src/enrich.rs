@@ -0,0 +1,124 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::llm::{EnglishClozeCard, EnglishDefinitionCard, HindiCard};
+
+/// A post-processing step run on every generated card before it is turned
+/// into notes, e.g. to add annotations or look the word up in a dictionary.
+/// Each method defaults to leaving the card as it is, so an enricher only
+/// implements the card kinds it cares about.
+///
+/// Built-in enrichers are picked by name with the `enrichers` config key;
+/// programs driving the flows directly can put their own in
+/// [`RunContext::enrichers`](crate::RunContext::enrichers).
+#[async_trait]
+pub trait Enricher: Send + Sync {
+    /// Name used in logs and error messages.
+    fn name(&self) -> &str;
+
+    async fn enrich_hindi(&self, _card: &mut HindiCard) -> Result<()> {
+        Ok(())
+    }
+
+    async fn enrich_cloze(&self, _card: &mut EnglishClozeCard) -> Result<()> {
+        Ok(())
+    }
+
+    async fn enrich_definition(&self, _card: &mut EnglishDefinitionCard) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Leaves every card unchanged; a starting point for custom enrichers.
+pub struct NoopEnricher;
+
+#[async_trait]
+impl Enricher for NoopEnricher {
+    fn name(&self) -> &str {
+        "noop"
+    }
+}
+
+/// Sample enricher that bolds the target word in Hindi sentences and in the
+/// example of English definition cards, wherever it appears as a whole word
+/// and is not bold already.
+pub struct HighlightWordEnricher;
+
+#[async_trait]
+impl Enricher for HighlightWordEnricher {
+    fn name(&self) -> &str {
+        "highlight_word"
+    }
+
+    async fn enrich_hindi(&self, card: &mut HindiCard) -> Result<()> {
+        for example in &mut card.examples {
+            example.hindi_sentence = highlight(&example.hindi_sentence, &card.word);
+        }
+        Ok(())
+    }
+
+    async fn enrich_definition(&self, card: &mut EnglishDefinitionCard) -> Result<()> {
+        if let Some(example) = &card.example {
+            card.example = Some(highlight(example, &card.word));
+        }
+        Ok(())
+    }
+}
+
+fn highlight(sentence: &str, word: &str) -> String {
+    if word.is_empty() {
+        return sentence.to_string();
+    }
+    let mut highlighted = String::with_capacity(sentence.len());
+    let mut copied = 0;
+    for (idx, _) in sentence.match_indices(word) {
+        let before = &sentence[..idx];
+        let after = &sentence[idx + word.len()..];
+        let whole_word = !before
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric)
+            && !after.chars().next().is_some_and(char::is_alphanumeric);
+        let bold = before.matches("<b>").count() > before.matches("</b>").count();
+        if whole_word && !bold {
+            highlighted.push_str(&sentence[copied..idx]);
+            highlighted.push_str(&format!("<b>{word}</b>"));
+            copied = idx + word.len();
+        }
+    }
+    highlighted.push_str(&sentence[copied..]);
+    highlighted
+}
+
+/// Build the enricher chain named by the `enrichers` config key, in order.
+pub fn from_names(names: &[String]) -> Result<Vec<Box<dyn Enricher>>> {
+    names
+        .iter()
+        .map(|name| -> Result<Box<dyn Enricher>> {
+            match name.as_str() {
+                "noop" => Ok(Box::new(NoopEnricher)),
+                "highlight_word" => Ok(Box::new(HighlightWordEnricher)),
+                other => anyhow::bail!(
+                    "unknown enricher '{other}' in enrichers (available: noop, highlight_word)"
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_bolds_whole_words_only_once() {
+        assert_eq!(
+            highlight("A cat sat on the catalog with a cat.", "cat"),
+            "A <b>cat</b> sat on the catalog with a <b>cat</b>."
+        );
+        assert_eq!(
+            highlight("मेरा <b>घर</b> बड़ा है, घरेलू नहीं।", "घर"),
+            "मेरा <b>घर</b> बड़ा है, घरेलू नहीं।"
+        );
+    }
+}
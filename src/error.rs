@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, AnkiCliError>;
+
+/// Failure kinds surfaced by the OpenAI and AnkiConnect clients.
+#[derive(Debug, Error)]
+pub enum AnkiCliError {
+    /// Invalid or unusable client configuration (empty API key, bad proxy URL, ...).
+    #[error("{0}")]
+    Config(String),
+
+    /// The request could not be sent or its response body could not be read.
+    #[error("{context}")]
+    Network {
+        context: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// The server answered with a non-success HTTP status.
+    #[error("{service} HTTP error {status}: {body}")]
+    Http {
+        service: &'static str,
+        status: StatusCode,
+        body: String,
+    },
+
+    /// The server answered with HTTP 429; `retry_after` comes from the `Retry-After` header.
+    #[error("{service} rate limit exceeded{}", format_retry_after(.retry_after))]
+    RateLimited {
+        service: &'static str,
+        retry_after: Option<Duration>,
+    },
+
+    /// The response body was not the JSON envelope the API documents. The
+    /// start of the body and its content type tell an HTML error page from
+    /// a proxy or a wrong URL apart from an unexpected JSON shape.
+    #[error(
+        "failed to parse {service} response body ({}): {snippet}",
+        content_type.as_deref().unwrap_or("no content type")
+    )]
+    InvalidResponse {
+        service: &'static str,
+        content_type: Option<String>,
+        snippet: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// AnkiConnect processed the request but reported an error.
+    #[error("Anki returned error: {0}")]
+    AnkiApi(String),
+
+    /// AnkiConnect declined to add a note; the reason comes from
+    /// `canAddNotesWithErrorDetail` when the add-on supports it.
+    #[error("Anki rejected the note: {0}")]
+    NoteRejected(String),
+
+    /// The deck does not exist, but one whose name differs only in case does.
+    #[error("deck '{requested}' does not exist, but '{existing}' does")]
+    DeckCaseMismatch { requested: String, existing: String },
+
+    /// The model declined the request and returned a `refusal` instead of content.
+    #[error("OpenAI refused the request: {0}")]
+    Refused(String),
+
+    /// The model's completion did not contain a usable card.
+    #[error("{message}")]
+    LlmParse {
+        message: String,
+        #[source]
+        source: Option<serde_json::Error>,
+    },
+}
+
+impl AnkiCliError {
+    pub(crate) fn network(context: impl Into<String>, source: reqwest::Error) -> Self {
+        Self::Network {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn invalid_response(
+        service: &'static str,
+        content_type: Option<String>,
+        body: &str,
+        source: serde_json::Error,
+    ) -> Self {
+        Self::InvalidResponse {
+            service,
+            content_type,
+            snippet: snippet(body),
+            source,
+        }
+    }
+
+    pub(crate) fn llm_parse(message: impl Into<String>) -> Self {
+        Self::LlmParse {
+            message: message.into(),
+            source: None,
+        }
+    }
+}
+
+/// Most characters of a response body quoted in an error.
+const SNIPPET_CHARS: usize = 200;
+
+/// The start of `body` on one line, for error messages.
+fn snippet(body: &str) -> String {
+    let body = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if body.is_empty() {
+        return "<empty body>".to_string();
+    }
+    match body.char_indices().nth(SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body,
+    }
+}
+
+fn format_retry_after(retry_after: &Option<Duration>) -> String {
+    match retry_after {
+        Some(delay) => format!("; retry after {}s", delay.as_secs()),
+        None => String::new(),
+    }
+}
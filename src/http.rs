@@ -0,0 +1,107 @@
+use std::fs;
+use std::time::Duration;
+
+use reqwest::header::{CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Certificate, ClientBuilder, NoProxy, Proxy, Response, StatusCode};
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::error::{AnkiCliError, Result};
+
+/// Hosts that must never be routed through a configured proxy, so a local
+/// AnkiConnect keeps working behind a corporate proxy.
+const LOCAL_HOSTS: &str = "localhost,127.0.0.1,::1";
+
+/// Build an HTTP client builder with the proxy and root-certificate settings
+/// from the config applied. When no proxy is configured, reqwest falls back to
+/// the standard `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY` environment variables.
+pub(crate) fn client_builder(config: &Config) -> Result<ClientBuilder> {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(url) = &config.http_proxy {
+        let proxy = Proxy::http(url)
+            .map_err(|err| AnkiCliError::Config(format!("invalid http_proxy URL {url}: {err}")))?
+            .no_proxy(NoProxy::from_string(LOCAL_HOSTS));
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(url) = &config.https_proxy {
+        let proxy = Proxy::https(url)
+            .map_err(|err| AnkiCliError::Config(format!("invalid https_proxy URL {url}: {err}")))?
+            .no_proxy(NoProxy::from_string(LOCAL_HOSTS));
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = &config.ca_cert_path {
+        let pem = fs::read(path).map_err(|err| {
+            AnkiCliError::Config(format!(
+                "failed to read CA certificate at {}: {err}",
+                path.display()
+            ))
+        })?;
+        let certificates = Certificate::from_pem_bundle(&pem).map_err(|err| {
+            AnkiCliError::Config(format!(
+                "failed to parse CA certificate at {}: {err}",
+                path.display()
+            ))
+        })?;
+        if certificates.is_empty() {
+            return Err(AnkiCliError::Config(format!(
+                "no certificates found in {}",
+                path.display()
+            )));
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(builder)
+}
+
+/// Turn non-success statuses into `Http`/`RateLimited` errors.
+pub(crate) async fn ensure_success(service: &'static str, response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        return Err(AnkiCliError::RateLimited {
+            service,
+            retry_after,
+        });
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(AnkiCliError::Http {
+        service,
+        status,
+        body,
+    })
+}
+
+/// Read the response body and decode it as JSON.
+pub(crate) async fn read_json<T>(service: &'static str, response: Response) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response
+        .text()
+        .await
+        .map_err(|err| AnkiCliError::network(format!("failed to read {service} response"), err))?;
+
+    serde_json::from_str(&body)
+        .map_err(|source| AnkiCliError::invalid_response(service, content_type, &body, source))
+}
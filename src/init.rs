@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use dialoguer::{Input, Password};
+
+use crate::config::{
+    self, DEFAULT_ANKI_CONNECT_URL, DEFAULT_ENGLISH_DECK, DEFAULT_HINDI_DECK, DEFAULT_OPENAI_MODEL,
+    InitialConfig,
+};
+
+pub fn run_init_wizard(config_path: Option<PathBuf>, force: bool) -> Result<()> {
+    let path = match config_path {
+        Some(path) => path,
+        None => config::default_config_path()
+            .context("could not determine config file path to write")?,
+    };
+
+    if path.exists() && !force {
+        anyhow::bail!(
+            "config file already exists at {}; pass --force to overwrite it",
+            path.display()
+        );
+    }
+
+    println!("Creating anki-cli configuration at {}", path.display());
+
+    let api_key = Password::new()
+        .with_prompt("OpenAI API key (leave empty to use OPENAI_API_KEY)")
+        .allow_empty_password(true)
+        .interact()
+        .context("failed to read API key")?;
+
+    let openai_model = prompt_with_default("OpenAI model", DEFAULT_OPENAI_MODEL)?;
+    let anki_connect_url = prompt_with_default("AnkiConnect URL", DEFAULT_ANKI_CONNECT_URL)?;
+    let hindi_deck = prompt_with_default("Hindi deck", DEFAULT_HINDI_DECK)?;
+    let english_deck = prompt_with_default("English deck", DEFAULT_ENGLISH_DECK)?;
+
+    let api_key = api_key.trim();
+    let initial = InitialConfig {
+        openai_api_key: if api_key.is_empty() {
+            None
+        } else {
+            Some(api_key.to_string())
+        },
+        openai_model,
+        anki_connect_url,
+        hindi_deck,
+        english_deck,
+    };
+
+    config::write_initial_config(&path, initial)?;
+    println!("Saved configuration to {}", path.display());
+
+    Ok(())
+}
+
+fn prompt_with_default(prompt: &str, default: &str) -> Result<String> {
+    let value = Input::<String>::new()
+        .with_prompt(prompt)
+        .default(default.to_string())
+        .interact_text()
+        .with_context(|| format!("failed to read {prompt}"))?;
+
+    Ok(value.trim().to_string())
+}
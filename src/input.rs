@@ -1,12 +1,405 @@
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use clap::ValueEnum;
+use unicode_normalization::UnicodeNormalization;
 
-pub fn read_words_from_file(path: &Path) -> Result<Vec<String>> {
+use crate::Language;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PartOfSpeech {
+    #[value(alias = "n")]
+    Noun,
+    #[value(alias = "v")]
+    Verb,
+    #[value(alias = "adj")]
+    Adjective,
+    #[value(alias = "adv")]
+    Adverb,
+    #[value(alias = "pron")]
+    Pronoun,
+    #[value(alias = "prep")]
+    Preposition,
+    #[value(alias = "conj")]
+    Conjunction,
+    #[value(alias = "interj")]
+    Interjection,
+}
+
+impl PartOfSpeech {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PartOfSpeech::Noun => "noun",
+            PartOfSpeech::Verb => "verb",
+            PartOfSpeech::Adjective => "adjective",
+            PartOfSpeech::Adverb => "adverb",
+            PartOfSpeech::Pronoun => "pronoun",
+            PartOfSpeech::Preposition => "preposition",
+            PartOfSpeech::Conjunction => "conjunction",
+            PartOfSpeech::Interjection => "interjection",
+        }
+    }
+}
+
+/// Card style used for English words.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum EnglishMode {
+    /// Cloze deletion in an example sentence
+    #[default]
+    Cloze,
+    /// Basic note with the word on the front and its definition on the back
+    Basic,
+}
+
+/// How `--input` files are parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// Pick the format from the file extension (plain text, CSV or Markdown)
+    #[default]
+    Auto,
+    /// E-reader highlight exports such as Kindle's "My Clippings.txt"
+    Kindle,
+}
+
+/// A single word to generate cards for, with optional per-word hints.
+#[derive(Debug, Clone)]
+pub struct WordEntry {
+    pub word: String,
+    pub pos: Option<PartOfSpeech>,
+    pub tags: Vec<String>,
+    pub english_mode: Option<EnglishMode>,
+    /// Intended meaning supplied alongside the word (e.g. a CSV column),
+    /// passed to the model as a hint.
+    pub definition: Option<String>,
+    /// Sampling temperature for this word only (`word@0.9`), replacing the
+    /// run's temperature.
+    pub temperature: Option<f32>,
+}
+
+impl WordEntry {
+    /// Parse `word`, `word:pos` (e.g. `book:verb`), any number of `#tag`
+    /// tokens (e.g. `meeting:noun #business`), an optional `@cloze` or
+    /// `@basic` English card style, an optional `@0.9` temperature (also
+    /// written `word@0.9`) and an optional `:: meaning` gloss (e.g.
+    /// `bank :: the side of a river`). A suffix that is not a known part of
+    /// speech is kept as part of the word.
+    pub fn parse(raw: &str, default_pos: Option<PartOfSpeech>) -> Self {
+        let (raw, definition) = match split_gloss(raw) {
+            Some((word, meaning)) if !meaning.trim().is_empty() => {
+                (word, Some(meaning.trim().to_string()))
+            }
+            Some((word, _)) => (word, None),
+            None => (raw, None),
+        };
+        let mut tags = Vec::new();
+        let mut english_mode = None;
+        let mut temperature = None;
+        let mut parts = Vec::new();
+        for token in raw.split_whitespace() {
+            if let Some(tag) = token.strip_prefix('#')
+                && !tag.is_empty()
+            {
+                tags.push(tag.to_string());
+            } else if let Some(mode) = token.strip_prefix('@')
+                && let Ok(mode) = EnglishMode::from_str(mode, true)
+            {
+                english_mode = Some(mode);
+            } else if let Some((word, value)) = token.rsplit_once('@')
+                && let Ok(value) = value.parse::<f32>()
+            {
+                temperature = Some(value);
+                if !word.is_empty() {
+                    parts.push(word);
+                }
+            } else {
+                parts.push(token);
+            }
+        }
+        let text = parts.join(" ");
+
+        if let Some((word, suffix)) = text.rsplit_once(':')
+            && let Ok(pos) = PartOfSpeech::from_str(suffix.trim(), true)
+            && !word.trim().is_empty()
+        {
+            return Self {
+                word: word.trim().to_string(),
+                pos: Some(pos),
+                tags,
+                english_mode,
+                definition,
+                temperature,
+            };
+        }
+
+        Self {
+            word: text,
+            pos: default_pos,
+            tags,
+            english_mode,
+            definition,
+            temperature,
+        }
+    }
+
+    /// The entry as one input line that [`WordEntry::parse`] reads back, e.g.
+    /// `bank:noun #finance :: a place that keeps money`.
+    pub fn to_line(&self) -> String {
+        let mut line = self.word.clone();
+        if let Some(pos) = self.pos {
+            line.push(':');
+            line.push_str(pos.as_str());
+        }
+        for tag in &self.tags {
+            line.push_str(" #");
+            line.push_str(tag);
+        }
+        if let Some(mode) = self.english_mode.and_then(|mode| mode.to_possible_value()) {
+            line.push_str(" @");
+            line.push_str(mode.get_name());
+        }
+        if let Some(temperature) = self.temperature {
+            line.push_str(&format!(" @{temperature}"));
+        }
+        if let Some(definition) = &self.definition {
+            line.push_str(" :: ");
+            line.push_str(definition);
+        }
+        line
+    }
+}
+
+/// Split `word :: meaning` at the first `::` that is not part of a
+/// hierarchical `#tag` such as `#home::family`.
+pub(crate) fn split_gloss(raw: &str) -> Option<(&str, &str)> {
+    raw.match_indices("::").map(|(idx, _)| idx).find_map(|idx| {
+        let token_start = raw[..idx]
+            .rfind(char::is_whitespace)
+            .map_or(0, |space| space + 1);
+        let in_tag = raw[token_start..].starts_with('#')
+            && raw[idx + 2..].starts_with(|c: char| !c.is_whitespace());
+        (!in_tag).then(|| (&raw[..idx], &raw[idx + 2..]))
+    })
+}
+
+/// Fail on the first per-word temperature outside 0.0–2.0.
+pub fn check_temperatures(words: &[WordEntry]) -> Result<()> {
+    for entry in words {
+        if let Some(temperature) = entry.temperature
+            && !(0.0..=2.0).contains(&temperature)
+        {
+            anyhow::bail!(
+                "temperature for '{}' must be between 0.0 and 2.0, got {}",
+                entry.word,
+                temperature
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Give each word named in a `--context word=meaning` gloss that meaning,
+/// replacing any definition it came with. Glosses that match no word are
+/// reported.
+pub fn apply_contexts(words: &mut [WordEntry], contexts: &[(String, String)]) {
+    let meanings: HashMap<String, &str> = contexts
+        .iter()
+        .map(|(word, meaning)| (known_word_key(word), meaning.as_str()))
+        .collect();
+    let mut used = HashSet::new();
+    for entry in words.iter_mut() {
+        let key = known_word_key(&entry.word);
+        if let Some(meaning) = meanings.get(&key) {
+            entry.definition = Some(meaning.to_string());
+            used.insert(key);
+        }
+    }
+    for (word, _) in contexts {
+        if !used.contains(&known_word_key(word)) {
+            tracing::warn!("--context for '{}' matches no input word", word);
+        }
+    }
+}
+
+pub fn parse_word_entries(words: Vec<String>, default_pos: Option<PartOfSpeech>) -> Vec<WordEntry> {
+    words
+        .iter()
+        .map(|word| WordEntry::parse(word, default_pos))
+        .collect()
+}
+
+/// Split free-form text (interactive input or the clipboard) on commas,
+/// semicolons and newlines.
+pub fn split_input(input: &str) -> Vec<String> {
+    input
+        .split([',', ';', '\n', '\r'])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Read the clipboard's text and split it with [`split_input`].
+pub fn read_clipboard_words() -> Result<Vec<String>> {
+    let mut clipboard = arboard::Clipboard::new().context("failed to open the clipboard")?;
+    let text = match clipboard.get_text() {
+        Ok(text) => text,
+        Err(arboard::Error::ContentNotAvailable) => {
+            anyhow::bail!("the clipboard is empty or does not hold text")
+        }
+        Err(err) => return Err(err).context("failed to read text from the clipboard"),
+    };
+
+    let words = split_input(&text);
+    if words.is_empty() {
+        anyhow::bail!("the clipboard text contains no words");
+    }
+    Ok(words)
+}
+
+/// Guess a word's language from its script: any Devanagari means Hindi, Latin
+/// letters only mean English. Mixed scripts or no letters at all return `None`.
+pub fn detect_language(word: &str) -> Option<Language> {
+    let devanagari = word
+        .chars()
+        .any(|c| matches!(c, '\u{0900}'..='\u{097F}' | '\u{A8E0}'..='\u{A8FF}'));
+    let latin = word.chars().any(|c| c.is_ascii_alphabetic());
+    match (devanagari, latin) {
+        (true, false) => Some(Language::Hindi),
+        (false, true) => Some(Language::English),
+        _ => None,
+    }
+}
+
+/// Canonical form of text for comparisons: NFC-normalized (which also unifies
+/// precomposed and nukta-combining Devanagari letters) with zero-width
+/// joiners and non-joiners removed.
+pub(crate) fn normalize_unicode(text: &str) -> String {
+    text.nfc()
+        .filter(|c| !matches!(c, '\u{200C}' | '\u{200D}'))
+        .collect()
+}
+
+/// Expand `--input` values into file paths. Values containing `*`, `?` or
+/// `[` are glob patterns and must match at least one file; anything else is
+/// taken as a literal path.
+pub fn expand_input_paths(patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(pattern));
+            continue;
+        }
+
+        let mut matched: Vec<PathBuf> = glob::glob(pattern)
+            .with_context(|| format!("invalid input glob '{pattern}'"))?
+            .collect::<std::result::Result<_, _>>()
+            .with_context(|| format!("failed to expand input glob '{pattern}'"))?;
+        matched.retain(|path| path.is_file());
+        if matched.is_empty() {
+            anyhow::bail!("input glob '{pattern}' did not match any files");
+        }
+        matched.sort();
+        paths.extend(matched);
+    }
+    Ok(paths)
+}
+
+/// Read words from a file, choosing the format by extension: `.csv` and
+/// `.md`/`.markdown` are read as tables with a `word` column and an optional
+/// `definition` (or `meaning`) column; anything else is plain text with one
+/// or more comma/semicolon separated words per line.
+pub fn read_words_from_file(
+    path: &Path,
+    format: InputFormat,
+    default_pos: Option<PartOfSpeech>,
+) -> Result<Vec<WordEntry>> {
     let raw = fs::read_to_string(path)
         .with_context(|| format!("failed to read input file at {}", path.display()))?;
 
+    if format == InputFormat::Kindle {
+        return Ok(parse_word_entries(extract_highlights(&raw), default_pos));
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("csv") => parse_csv_words(&raw, default_pos),
+        Some("md" | "markdown") => parse_markdown_words(&raw, default_pos),
+        _ => Ok(parse_word_entries(split_text_words(&raw), default_pos)),
+    }
+}
+
+/// Keep only the highlighted terms from an e-reader export, dropping
+/// annotation lines such as `Highlight (yellow) - Page 12`,
+/// `- Your Highlight on page 12 | Location 180-181 | Added on ...`, `==========`
+/// separators and the book title that precedes each clipping. Notes and
+/// bookmarks are skipped entirely.
+pub fn extract_highlights(raw: &str) -> Vec<String> {
+    let raw = raw.trim_start_matches('\u{feff}');
+    let mut highlights = Vec::new();
+    let blocks: Vec<&str> = if raw.contains(CLIPPING_SEPARATOR) {
+        raw.split(CLIPPING_SEPARATOR).collect()
+    } else {
+        vec![raw]
+    };
+
+    for block in blocks {
+        let lines: Vec<&str> = block.lines().map(str::trim).collect();
+        // In a clippings file everything up to the "- Your ..." line is the
+        // title and metadata.
+        let body = match lines.iter().position(|line| line.starts_with("- Your ")) {
+            Some(idx) if lines[idx].starts_with("- Your Highlight") => &lines[idx + 1..],
+            Some(_) => continue,
+            None => &lines[..],
+        };
+
+        let mut skip_note = false;
+        for line in body {
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with("Note -") || line.starts_with("Bookmark -") {
+                skip_note = true;
+                continue;
+            }
+            if is_annotation_line(line) {
+                skip_note = false;
+                continue;
+            }
+            if skip_note {
+                continue;
+            }
+
+            let term = line.trim_matches(|c: char| {
+                c.is_ascii_punctuation() || matches!(c, '“' | '”' | '‘' | '’' | '…')
+            });
+            if !term.is_empty() {
+                highlights.push(term.to_string());
+            }
+        }
+    }
+
+    highlights
+}
+
+const CLIPPING_SEPARATOR: &str = "==========";
+
+fn is_annotation_line(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    lower.starts_with("highlight (")
+        || lower.starts_with("highlight -")
+        || lower.starts_with("- your ")
+        || ["page ", "location ", "loc. "].iter().any(|prefix| {
+            lower
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        })
+}
+
+fn split_text_words(raw: &str) -> Vec<String> {
     let mut words = Vec::new();
 
     for line in raw.lines() {
@@ -14,8 +407,13 @@ pub fn read_words_from_file(path: &Path) -> Result<Vec<String>> {
         if trimmed.is_empty() || trimmed.starts_with('#') {
             continue;
         }
+        // A `word :: meaning` line is one entry; the meaning may contain commas.
+        if split_gloss(trimmed).is_some() {
+            words.push(trimmed.to_string());
+            continue;
+        }
 
-        for piece in trimmed.split(|c| c == ',' || c == ';') {
+        for piece in trimmed.split([',', ';']) {
             let candidate = piece.trim();
             if !candidate.is_empty() {
                 words.push(candidate.to_string());
@@ -23,5 +421,289 @@ pub fn read_words_from_file(path: &Path) -> Result<Vec<String>> {
         }
     }
 
-    Ok(words)
+    words
+}
+
+fn parse_csv_words(raw: &str, default_pos: Option<PartOfSpeech>) -> Result<Vec<WordEntry>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(raw.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .context("failed to read CSV header row")?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    let (word_column, definition_column) =
+        table_columns(&headers).context("CSV input needs a header row with a `word` column")?;
+
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record.context("failed to read CSV row")?;
+        if let Some(entry) = table_entry(
+            record.get(word_column),
+            definition_column.and_then(|column| record.get(column)),
+            default_pos,
+        ) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+fn parse_markdown_words(raw: &str, default_pos: Option<PartOfSpeech>) -> Result<Vec<WordEntry>> {
+    let mut rows = raw
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| !line.starts_with('|'))
+        .take_while(|line| line.starts_with('|'))
+        .map(|line| {
+            line.trim_matches('|')
+                .split('|')
+                .map(|cell| cell.trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .filter(|cells| {
+            !cells
+                .iter()
+                .all(|cell| !cell.is_empty() && cell.chars().all(|c| matches!(c, '-' | ':' | ' ')))
+        });
+
+    let headers = rows
+        .next()
+        .context("Markdown input needs a table with a `word` column")?;
+    let (word_column, definition_column) =
+        table_columns(&headers).context("Markdown input needs a table with a `word` column")?;
+
+    Ok(rows
+        .filter_map(|cells| {
+            table_entry(
+                cells.get(word_column).map(String::as_str),
+                definition_column
+                    .and_then(|column| cells.get(column))
+                    .map(String::as_str),
+                default_pos,
+            )
+        })
+        .collect())
+}
+
+/// Locate the word column and the optional definition column in a header row.
+fn table_columns(headers: &[String]) -> Option<(usize, Option<usize>)> {
+    let find = |names: &[&str]| {
+        headers.iter().position(|header| {
+            names
+                .iter()
+                .any(|name| header.trim().eq_ignore_ascii_case(name))
+        })
+    };
+    let word = find(&["word"])?;
+    Some((word, find(&["definition", "meaning"])))
+}
+
+fn table_entry(
+    word: Option<&str>,
+    definition: Option<&str>,
+    default_pos: Option<PartOfSpeech>,
+) -> Option<WordEntry> {
+    let word = word?.trim();
+    if word.is_empty() {
+        return None;
+    }
+
+    let mut entry = WordEntry::parse(word, default_pos);
+    entry.definition = definition
+        .map(str::trim)
+        .filter(|definition| !definition.is_empty())
+        .map(str::to_string);
+    Some(entry)
+}
+
+/// Read a word-frequency list with one word per line, most frequent first.
+/// Anything after the first whitespace-separated token (e.g. a count) is
+/// ignored, as are blank lines and `#` comments. Returns each lowercased word's
+/// rank, keeping the first rank for repeated words.
+pub fn read_frequency_list(path: &Path) -> Result<HashMap<String, usize>> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read frequency list at {}", path.display()))?;
+
+    let mut ranks = HashMap::new();
+    for line in raw.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(word) = trimmed.split_whitespace().next() {
+            let rank = ranks.len();
+            ranks.entry(word.to_lowercase()).or_insert(rank);
+        }
+    }
+
+    Ok(ranks)
+}
+
+/// Read every `--dedupe-against` file with the regular word-file parser and
+/// return the words as [`known_word_key`]s.
+pub fn read_known_words(paths: &[PathBuf]) -> Result<HashSet<String>> {
+    let mut known = HashSet::new();
+    for path in paths {
+        let entries = read_words_from_file(path, InputFormat::Auto, None)
+            .with_context(|| format!("failed to read known words from {}", path.display()))?;
+        tracing::debug!("{} lists {} known word(s)", path.display(), entries.len());
+        known.extend(entries.iter().map(|entry| known_word_key(&entry.word)));
+    }
+    Ok(known)
+}
+
+/// Form in which input words are compared against the known-words list:
+/// Unicode-normalized, whitespace-collapsed and lowercased.
+pub fn known_word_key(word: &str) -> String {
+    normalize_unicode(word)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Order words from most to least frequent according to `ranks`. Words missing
+/// from the list keep their original relative order after all ranked words.
+pub fn sort_by_frequency(
+    mut words: Vec<WordEntry>,
+    ranks: &HashMap<String, usize>,
+) -> Vec<WordEntry> {
+    words.sort_by_key(|entry| {
+        ranks
+            .get(&entry.word.to_lowercase())
+            .copied()
+            .unwrap_or(usize::MAX)
+    });
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_plain_word() {
+        let entry = WordEntry::parse("  ghar ", None);
+        assert_eq!(entry.word, "ghar");
+        assert_eq!(entry.pos, None);
+        assert_eq!(entry.definition, None);
+        assert!(entry.tags.is_empty());
+    }
+
+    #[test]
+    fn parse_part_of_speech_suffix() {
+        let entry = WordEntry::parse("book:verb", None);
+        assert_eq!(entry.word, "book");
+        assert_eq!(entry.pos, Some(PartOfSpeech::Verb));
+
+        let entry = WordEntry::parse("quick:adj", Some(PartOfSpeech::Noun));
+        assert_eq!(entry.word, "quick");
+        assert_eq!(entry.pos, Some(PartOfSpeech::Adjective));
+    }
+
+    #[test]
+    fn parse_unknown_suffix_stays_in_word() {
+        let entry = WordEntry::parse("re:invent", Some(PartOfSpeech::Noun));
+        assert_eq!(entry.word, "re:invent");
+        assert_eq!(entry.pos, Some(PartOfSpeech::Noun));
+    }
+
+    #[test]
+    fn parse_definition_gloss() {
+        let entry = WordEntry::parse("bank:noun :: the side of a river", None);
+        assert_eq!(entry.word, "bank");
+        assert_eq!(entry.pos, Some(PartOfSpeech::Noun));
+        assert_eq!(entry.definition.as_deref(), Some("the side of a river"));
+
+        let entry = WordEntry::parse("bank ::  ", None);
+        assert_eq!(entry.word, "bank");
+        assert_eq!(entry.definition, None);
+    }
+
+    #[test]
+    fn hierarchical_tags_are_not_glosses() {
+        let entry = WordEntry::parse("ghar #home::family", None);
+        assert_eq!(entry.word, "ghar");
+        assert_eq!(entry.tags, ["home::family"]);
+        assert_eq!(entry.definition, None);
+
+        let entry = WordEntry::parse("ghar #home::family :: a house", None);
+        assert_eq!(entry.tags, ["home::family"]);
+        assert_eq!(entry.definition.as_deref(), Some("a house"));
+
+        assert_eq!(
+            split_text_words("ghar #home::family, pani\n"),
+            ["ghar #home::family", "pani"]
+        );
+    }
+
+    #[test]
+    fn parse_tags_mode_and_temperature() {
+        let entry = WordEntry::parse("meeting:noun #business @basic @0.3", None);
+        assert_eq!(entry.word, "meeting");
+        assert_eq!(entry.pos, Some(PartOfSpeech::Noun));
+        assert_eq!(entry.tags, ["business"]);
+        assert_eq!(entry.english_mode, Some(EnglishMode::Basic));
+        assert_eq!(entry.temperature, Some(0.3));
+
+        let entry = WordEntry::parse("ghar@0.9", None);
+        assert_eq!(entry.word, "ghar");
+        assert_eq!(entry.temperature, Some(0.9));
+    }
+
+    #[test]
+    fn to_line_round_trips_through_parse() {
+        let line = "bank:noun #finance @basic @0.3 :: a place that keeps money";
+        let entry = WordEntry::parse(line, None);
+
+        assert_eq!(entry.to_line(), line);
+        let reparsed = WordEntry::parse(&entry.to_line(), None);
+        assert_eq!(reparsed.word, "bank");
+        assert_eq!(reparsed.tags, ["finance"]);
+        assert_eq!(reparsed.temperature, Some(0.3));
+    }
+
+    #[test]
+    fn normalize_unicode_unifies_precomposed_and_combining_devanagari() {
+        // ऩ composes under NFC; क़ is a composition exclusion and stays
+        // decomposed, so both spellings of each end up identical.
+        assert_eq!(normalize_unicode("\u{0928}\u{093C}"), "\u{0929}");
+        assert_eq!(
+            normalize_unicode("\u{0958}"),
+            normalize_unicode("\u{0915}\u{093C}")
+        );
+        assert_eq!(normalize_unicode("क़लम"), normalize_unicode("क\u{093C}लम"));
+    }
+
+    #[test]
+    fn normalize_unicode_strips_zero_width_joiners() {
+        assert_eq!(normalize_unicode("क्\u{200D}ष"), "क्ष");
+        assert_eq!(normalize_unicode("क्\u{200C}ष"), "क्ष");
+        assert_eq!(normalize_unicode("ghar"), "ghar");
+    }
+
+    fn words(entries: &[WordEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.word.as_str()).collect()
+    }
+
+    #[test]
+    fn sort_by_frequency_keeps_ties_and_unranked_in_input_order() {
+        let ranks = HashMap::from([("the".to_string(), 1), ("run".to_string(), 2)]);
+        let entries = ["zebra", "run", "apple", "The", "Run", "quux"]
+            .into_iter()
+            .map(|word| WordEntry::parse(word, None))
+            .collect();
+
+        let sorted = sort_by_frequency(entries, &ranks);
+
+        assert_eq!(
+            words(&sorted),
+            ["The", "run", "Run", "zebra", "apple", "quux"]
+        );
+    }
 }
@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+use crate::input::WordEntry;
+
+const JOURNAL_DIR: &str = "journal";
+const FAILED_EXTENSION: &str = "failed";
+
+/// Record of the words a run has already processed, one `deck<TAB>word` line
+/// per word. The file is keyed by the language and the input word list, so
+/// re-running the same command with `--resume` finds it again, and it is
+/// removed once the run completes.
+///
+/// Words that could not be generated are written next to it to
+/// `<language>-<hash>.failed`, one input line per word, which
+/// `--retry-failed` reads back; that file is kept after the run.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    path: Option<PathBuf>,
+    failed_path: Option<PathBuf>,
+    done: HashSet<(String, String)>,
+    failed: Mutex<Vec<String>>,
+}
+
+impl Journal {
+    /// Open the journal for this run. With `resume`, previously journaled
+    /// words are loaded; otherwise any stale journal is discarded. Dry runs
+    /// and configs without a directory get a journal that records nothing.
+    pub(crate) fn open(
+        config: &Config,
+        language_tag: &str,
+        words: &[WordEntry],
+        resume: bool,
+        dry_run: bool,
+    ) -> Result<Self> {
+        let dir = config
+            .config_path()
+            .and_then(|path| path.parent())
+            .map(|dir| dir.join(JOURNAL_DIR));
+        let Some(dir) = dir.filter(|_| !dry_run) else {
+            return Ok(Self {
+                path: None,
+                failed_path: None,
+                done: HashSet::new(),
+                failed: Mutex::default(),
+            });
+        };
+
+        let path = dir.join(format!(
+            "{language_tag}-{:016x}.log",
+            journal_key(language_tag, words)
+        ));
+        // Failed words are retried by this run, so an earlier list is stale.
+        let failed_path = path.with_extension(FAILED_EXTENSION);
+        if failed_path.exists() {
+            fs::remove_file(&failed_path).with_context(|| {
+                format!("failed to clear failure list {}", failed_path.display())
+            })?;
+        }
+        let mut done = HashSet::new();
+        if resume && path.exists() {
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read journal {}", path.display()))?;
+            done.extend(contents.lines().filter_map(|line| {
+                let (deck, word) = line.split_once('\t')?;
+                Some((deck.to_string(), word.to_string()))
+            }));
+            tracing::info!(
+                "Resuming from {} ({} word(s) already done)",
+                path.display(),
+                done.len()
+            );
+        } else if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to clear journal {}", path.display()))?;
+        }
+
+        Ok(Self {
+            path: Some(path),
+            failed_path: Some(failed_path),
+            done,
+            failed: Mutex::default(),
+        })
+    }
+
+    /// Drop the words already journaled for their deck, logging how many were skipped.
+    pub(crate) fn pending(
+        &self,
+        groups: Vec<(String, Vec<WordEntry>)>,
+    ) -> Vec<(String, Vec<WordEntry>)> {
+        if self.done.is_empty() {
+            return groups;
+        }
+
+        let mut skipped = 0;
+        let groups = groups
+            .into_iter()
+            .map(|(deck, entries)| {
+                let before = entries.len();
+                let entries: Vec<WordEntry> = entries
+                    .into_iter()
+                    .filter(|entry| !self.done.contains(&(deck.clone(), entry.word.clone())))
+                    .collect();
+                skipped += before - entries.len();
+                (deck, entries)
+            })
+            .collect();
+
+        if skipped > 0 {
+            tracing::info!("Skipping {} word(s) added by the interrupted run", skipped);
+        }
+        groups
+    }
+
+    /// Append a word the run is done with, whether its notes were added,
+    /// rejected as duplicates or skipped in review. Failures are logged, not
+    /// returned, so journaling never aborts a run that is adding notes fine.
+    pub(crate) fn record(&self, deck: &str, word: &str) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| OpenOptions::new().create(true).append(true).open(path))
+            .and_then(|mut file| writeln!(file, "{deck}\t{word}"));
+        if let Err(err) = result {
+            tracing::warn!("Failed to update journal {}: {}", path.display(), err);
+        }
+    }
+
+    /// Append a word whose card could not be generated (e.g. it hit
+    /// `--word-timeout`) to the failure list.
+    pub(crate) fn record_failure(&self, entry: &WordEntry) {
+        self.failed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(entry.word.clone());
+        let Some(path) = &self.failed_path else {
+            return;
+        };
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| OpenOptions::new().create(true).append(true).open(path))
+            .and_then(|mut file| writeln!(file, "{}", entry.to_line()));
+        if let Err(err) = result {
+            tracing::warn!("Failed to update failure list {}: {}", path.display(), err);
+        }
+    }
+
+    /// Remove the journal after the run completed and point at the failure
+    /// list, if any word failed.
+    pub(crate) fn finish(self) {
+        if let Some(path) = self.path
+            && path.exists()
+            && let Err(err) = fs::remove_file(&path)
+        {
+            tracing::warn!("Failed to remove journal {}: {}", path.display(), err);
+        }
+
+        let failed = self
+            .failed
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        if failed.is_empty() {
+            return;
+        }
+        match self.failed_path {
+            Some(path) => tracing::warn!(
+                "{} word(s) failed: {}; retry them with --retry-failed {}",
+                failed.len(),
+                failed.join(", "),
+                path.display()
+            ),
+            None => tracing::warn!("{} word(s) failed: {}", failed.len(), failed.join(", ")),
+        }
+    }
+}
+
+/// FNV-1a hash of the language and input words; stable across builds, unlike
+/// `DefaultHasher`.
+fn journal_key(language_tag: &str, words: &[WordEntry]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let parts = std::iter::once(language_tag).chain(words.iter().map(|entry| entry.word.as_str()));
+    for part in parts {
+        for byte in part.bytes().chain(std::iter::once(b'\n')) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
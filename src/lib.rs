@@ -0,0 +1,55 @@
+//! Generate language flashcards with an OpenAI-compatible model and add them
+//! to Anki through AnkiConnect.
+//!
+//! The `anki-cli` binary is a thin wrapper over this library; the same flows
+//! can be driven from another program by resolving a [`Config`], building the
+//! two clients and passing them to [`run_hindi_flow`] or [`run_english_flow`]
+//! via a [`RunContext`].
+
+pub mod anki;
+pub mod bench;
+pub mod config;
+mod doctor;
+pub mod enrich;
+pub mod error;
+mod http;
+mod init;
+pub mod input;
+mod journal;
+pub mod lint;
+pub mod llm;
+pub mod preview;
+mod rate_limit;
+#[cfg(test)]
+mod test_support;
+pub mod workflows;
+
+use clap::ValueEnum;
+
+pub use anki::{
+    AnkiClient, AnkiConnectClient, CardTemplate, DeckStats, MockAnkiClient, MultiAction, Note,
+    NoteInfo, NoteOptions,
+};
+pub use config::{
+    Config, ConfigOverrides, ConfigResolution, ConfigSource, Difficulty, DuplicateScope,
+    HindiDirection, HindiModelMode, HintPolicy, Provider, WordRange,
+};
+pub use doctor::run_doctor;
+pub use error::AnkiCliError;
+pub use init::run_init_wizard;
+pub use input::{EnglishMode, InputFormat, PartOfSpeech, WordEntry};
+pub use llm::{
+    EnglishClozeCard, EnglishDefinitionCard, GenerationOptions, HindiCard, HindiExample,
+    OpenAiClient,
+};
+pub use workflows::{
+    RunContext, run_english_flow, run_hindi_flow, run_interactive_session, run_regenerate,
+    suspend_progress,
+};
+
+/// Target language of a card-generation run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Language {
+    Hindi,
+    English,
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::input::{self, InputFormat, WordEntry};
+
+/// Longest entry, in words, that still looks like a word or phrase rather
+/// than a whole sentence pasted into the list.
+const MAX_ENTRY_WORDS: usize = 4;
+
+enum Severity {
+    Warning,
+    Error,
+}
+
+struct Finding {
+    location: String,
+    severity: Severity,
+    message: String,
+}
+
+/// Check a word file without calling OpenAI or AnkiConnect: blank lines,
+/// repeated words, entries long enough to be sentences and words that mix
+/// scripts. Prints every finding and a summary, and fails if any finding is
+/// an error. With `phrase`, long entries are expected idioms and pass.
+pub fn run_lint(path: &Path, format: InputFormat, phrase: bool) -> Result<()> {
+    let entries = read_entries(path, format)?;
+    let mut findings = Vec::new();
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut words = 0;
+
+    for (location, entry) in entries {
+        let Some(entry) = entry else {
+            findings.push(Finding {
+                location,
+                severity: Severity::Warning,
+                message: "blank line".to_string(),
+            });
+            continue;
+        };
+        words += 1;
+        let word = &entry.word;
+
+        let key = input::normalize_unicode(word).to_lowercase();
+        if let Some(first) = seen.get(&key) {
+            findings.push(Finding {
+                location: location.clone(),
+                severity: Severity::Warning,
+                message: format!("'{word}' repeats {first}"),
+            });
+        } else {
+            seen.insert(key, location.clone());
+        }
+
+        let length = word.split_whitespace().count();
+        if !phrase && length > MAX_ENTRY_WORDS {
+            findings.push(Finding {
+                location: location.clone(),
+                severity: Severity::Error,
+                message: format!("'{word}' has {length} words; it looks like a whole sentence"),
+            });
+        }
+
+        if let Some(temperature) = entry.temperature
+            && !(0.0..=2.0).contains(&temperature)
+        {
+            findings.push(Finding {
+                location: location.clone(),
+                severity: Severity::Error,
+                message: format!("'{word}' has temperature {temperature}; it must be 0.0–2.0"),
+            });
+        }
+
+        if input::detect_language(word).is_none() {
+            let (severity, message) = if word.chars().any(char::is_alphabetic) {
+                (
+                    Severity::Error,
+                    format!("'{word}' mixes scripts or is neither Latin nor Devanagari"),
+                )
+            } else {
+                (Severity::Warning, format!("'{word}' contains no letters"))
+            };
+            findings.push(Finding {
+                location,
+                severity,
+                message,
+            });
+        }
+    }
+
+    report(path, words, findings)
+}
+
+/// Entries of the file with where each came from. Plain text files are read
+/// line by line so blank lines (`None`) can be reported; other formats go
+/// through the regular parser and are located by entry number.
+fn read_entries(path: &Path, format: InputFormat) -> Result<Vec<(String, Option<WordEntry>)>> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let structured = format == InputFormat::Kindle
+        || matches!(extension.as_deref(), Some("csv" | "md" | "markdown"));
+    if structured {
+        let entries = input::read_words_from_file(path, format, None)?;
+        return Ok(entries
+            .into_iter()
+            .enumerate()
+            .map(|(idx, entry)| (format!("entry {}", idx + 1), Some(entry)))
+            .collect());
+    }
+
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read input file at {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (idx, line) in raw.lines().enumerate() {
+        let location = format!("line {}", idx + 1);
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            entries.push((location, None));
+            continue;
+        }
+        if trimmed.starts_with('#') {
+            continue;
+        }
+        if input::split_gloss(trimmed).is_some() {
+            entries.push((location, Some(WordEntry::parse(trimmed, None))));
+            continue;
+        }
+        for piece in trimmed.split([',', ';']).map(str::trim) {
+            if !piece.is_empty() {
+                entries.push((location.clone(), Some(WordEntry::parse(piece, None))));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn report(path: &Path, words: usize, findings: Vec<Finding>) -> Result<()> {
+    let mut errors = 0;
+    for finding in &findings {
+        let label = match finding.severity {
+            Severity::Warning => "WARN",
+            Severity::Error => {
+                errors += 1;
+                "ERROR"
+            }
+        };
+        println!("[{label}] {}: {}", finding.location, finding.message);
+    }
+
+    let warnings = findings.len() - errors;
+    println!(
+        "{}: {words} word(s), {warnings} warning(s), {errors} error(s)",
+        path.display()
+    );
+    if errors > 0 {
+        anyhow::bail!("{errors} error(s) found in {}", path.display());
+    }
+    Ok(())
+}
@@ -1,6 +1,80 @@
-use anyhow::{Context, Result, anyhow};
-use reqwest::Client;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Local;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::Language;
+use crate::config::{
+    Config, DEFAULT_EXPLANATION_LANGUAGE, Difficulty, HintPolicy, Provider, WordRange,
+};
+use crate::error::{AnkiCliError, Result};
+use crate::http;
+use crate::input::{self, PartOfSpeech, WordEntry, normalize_unicode};
+use crate::rate_limit::RateLimiter;
+
+const SERVICE: &str = "OpenAI";
+
+/// How often a 429 is retried when `requests_per_minute` is set.
+const MAX_RATE_LIMIT_RETRIES: usize = 3;
+
+/// Prompt characters per token assumed by the cost estimate.
+const CHARS_PER_TOKEN: usize = 4;
+/// Completion tokens per sentence word assumed by the cost estimate, covering
+/// the sentence and its translation.
+const COMPLETION_TOKENS_PER_WORD: u64 = 4;
+
+/// Appended to every system prompt with `safe_mode`.
+const SAFE_MODE_INSTRUCTION: &str = "Content rules: the cards are used in a school classroom. Never use profanity, slurs, insults, sexual content, graphic violence, drugs, alcohol or other material unsuitable for children; keep every sentence, translation and definition appropriate for all ages.";
+
+/// Inflectional endings stripped from Hindi words before comparing stems:
+/// plural and oblique noun endings, infinitive, participle and absolutive
+/// verb endings, and bare vowel signs.
+const HINDI_SUFFIXES: &[&str] = &[
+    "ियाँ",
+    "ियां",
+    "ियों",
+    "ाओं",
+    "ाएँ",
+    "ाएं",
+    "ों",
+    "ें",
+    "ीं",
+    "ाँ",
+    "ां",
+    "ना",
+    "ने",
+    "नी",
+    "ता",
+    "ती",
+    "ते",
+    "कर",
+    "ा",
+    "ी",
+    "े",
+    "ो",
+    "ि",
+    "ु",
+    "ू",
+    "ं",
+    "ँ",
+];
+
+/// Shortest stem allowed to match the start of a longer inflected token;
+/// shorter stems would match almost any word.
+const MIN_PREFIX_STEM_CHARS: usize = 3;
+
+/// Appended to the prompt when retrying a refused request.
+const SOFTENED_PROMPT_SUFFIX: &str = "\nThis is a vocabulary exercise for language learners. If the word is sensitive, write a neutral, dictionary-style sentence that shows its meaning without graphic or offensive detail.";
 
 #[derive(Debug)]
 pub struct OpenAiClient {
@@ -8,164 +82,1374 @@ pub struct OpenAiClient {
     api_key: String,
     model: String,
     base_url: String,
+    provider: Provider,
+    /// Organization, project and `extra_headers`, sent only to the API itself
+    /// and not to the host serving a generated image.
+    headers: HeaderMap,
+    extra_query: BTreeMap<String, String>,
+    hindi_prompt_template: Option<String>,
+    english_prompt_template: Option<String>,
+    seed: Option<u64>,
+    max_completion_tokens: Option<u32>,
+    top_p: Option<f32>,
+    frequency_penalty: Option<f32>,
+    presence_penalty: Option<f32>,
+    stream: bool,
+    print_prompt: bool,
+    strict: bool,
+    llm_log: Option<PathBuf>,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+    explanation_language: String,
+    style: Option<String>,
+    image_model: String,
+    hint_policy: HintPolicy,
+    safe_mode: bool,
+    structured_output: bool,
+    schema_unsupported: AtomicBool,
+    tool_calling: bool,
+    tools_unsupported: AtomicBool,
+    rate_limiter: Option<RateLimiter>,
+}
+
+/// Settings shared by every generation request in a run.
+#[derive(Debug, Clone)]
+pub struct GenerationOptions {
+    pub temperature: f32,
+    pub difficulty: Option<Difficulty>,
+    pub examples: usize,
+    pub notes_per_word: usize,
+    pub include_ipa: bool,
+    pub enrich: bool,
+    pub hindi_sentence_words: WordRange,
+    pub english_sentence_words: WordRange,
+    /// Earlier sentences the new one must clearly differ from.
+    pub avoid_sentences: Vec<String>,
+    /// The targets are multi-word expressions (`--phrase`).
+    pub phrase: bool,
+}
+
+impl GenerationOptions {
+    /// These options with the entry's own temperature, if it has one.
+    pub fn for_entry(&self, entry: &WordEntry) -> GenerationOptions {
+        GenerationOptions {
+            temperature: entry.temperature.unwrap_or(self.temperature),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct HindiCard {
     pub word: String,
+    pub examples: Vec<HindiExample>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HindiExample {
     pub hindi_sentence: String,
     pub english_sentence: String,
 }
 
+impl HindiCard {
+    /// Hindi sentences, numbered when the card carries more than one example.
+    pub fn hindi_text(&self) -> String {
+        render_examples(self.examples.iter().map(|ex| ex.hindi_sentence.as_str()))
+    }
+
+    /// English translations, numbered when the card carries more than one example.
+    pub fn english_text(&self) -> String {
+        render_examples(self.examples.iter().map(|ex| ex.english_sentence.as_str()))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EnglishClozeCard {
     pub word: String,
     pub cloze_sentence: String,
     pub translation: String,
     pub hint: Option<String>,
+    pub ipa: Option<String>,
+    pub synonyms: Vec<String>,
+    pub antonyms: Vec<String>,
+}
+
+/// Plain definition card used by the Basic English mode.
+#[derive(Debug, Clone)]
+pub struct EnglishDefinitionCard {
+    pub word: String,
+    pub definition: String,
+    pub example: Option<String>,
+    pub ipa: Option<String>,
 }
 
 impl OpenAiClient {
-    pub fn new(api_key: String, model: String, base_url: String) -> Result<Self> {
-        if api_key.trim().is_empty() {
-            anyhow::bail!("OpenAI API key cannot be empty");
+    pub fn new(config: &Config) -> Result<Self> {
+        if config.openai_api_key.trim().is_empty() {
+            return Err(AnkiCliError::Config(
+                "OpenAI API key cannot be empty".to_string(),
+            ));
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("OpenAI-Organization", &config.openai_org),
+            ("OpenAI-Project", &config.openai_project),
+        ] {
+            if let Some(value) = value {
+                let value = HeaderValue::from_str(value).map_err(|err| {
+                    AnkiCliError::Config(format!("invalid {name} header value: {err}"))
+                })?;
+                headers.insert(name, value);
+            }
+        }
+        for (name, value) in &config.extra_headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|err| {
+                AnkiCliError::Config(format!("invalid extra_headers name '{name}': {err}"))
+            })?;
+            let value = HeaderValue::from_str(value).map_err(|err| {
+                AnkiCliError::Config(format!("invalid extra_headers value for '{name}': {err}"))
+            })?;
+            headers.insert(name, value);
         }
 
-        let http = Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
+        let http = http::client_builder(config)?
+            .timeout(Duration::from_secs(config.request_timeout_secs))
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
             .build()
-            .context("failed to build HTTP client for OpenAI")?;
+            .map_err(|err| AnkiCliError::network("failed to build HTTP client for OpenAI", err))?;
 
         Ok(Self {
             http,
-            api_key,
-            model,
-            base_url,
+            api_key: config.openai_api_key.clone(),
+            model: config.openai_model.clone(),
+            base_url: config.openai_base_url.clone(),
+            provider: config.provider,
+            headers,
+            extra_query: config.extra_query.clone(),
+            hindi_prompt_template: config.hindi_prompt_template.clone(),
+            english_prompt_template: config.english_prompt_template.clone(),
+            seed: config.seed,
+            max_completion_tokens: config.max_completion_tokens,
+            top_p: config.top_p,
+            frequency_penalty: config.frequency_penalty,
+            presence_penalty: config.presence_penalty,
+            stream: config.stream,
+            print_prompt: config.print_prompt,
+            strict: config.strict,
+            llm_log: config.llm_log.clone(),
+            prompt_tokens: AtomicU64::new(0),
+            completion_tokens: AtomicU64::new(0),
+            explanation_language: config.explanation_language.clone(),
+            style: config.style.clone(),
+            image_model: config.image_model.clone(),
+            hint_policy: config.hint_policy,
+            safe_mode: config.safe_mode,
+            structured_output: config.structured_output,
+            schema_unsupported: AtomicBool::new(false),
+            tool_calling: config.tool_calling,
+            tools_unsupported: AtomicBool::new(false),
+            rate_limiter: config.requests_per_minute.map(RateLimiter::new),
         })
     }
 
-    pub async fn generate_hindi_card(&self, word: &str, temperature: f32) -> Result<HindiCard> {
+    pub async fn generate_hindi_card(
+        &self,
+        word: &str,
+        pos: Option<PartOfSpeech>,
+        meaning: Option<&str>,
+        options: &GenerationOptions,
+    ) -> Result<HindiCard> {
+        let examples = options.examples.max(1);
+        let (prompt, user) = self.hindi_card_prompt(word, pos, meaning, options);
+        let payload = self
+            .chat_completion(
+                prompt,
+                user,
+                options.temperature,
+                Some(hindi_card_schema(examples)),
+            )
+            .await?;
+
+        let parsed: HindiCardPayload = parse_json(&payload)?;
+        hindi_card_from_payload(word, parsed, examples, self.strict)
+    }
+
+    /// System and user prompt of a single-word Hindi card request.
+    fn hindi_card_prompt(
+        &self,
+        word: &str,
+        pos: Option<PartOfSpeech>,
+        meaning: Option<&str>,
+        options: &GenerationOptions,
+    ) -> (String, String) {
+        let examples = options.examples.max(1);
         let prompt = format!(
             "You are creating language learning flashcards. Generate a natural, short Hindi sentence that uses the target word exactly once and is easy for learners to understand. Provide a natural-sounding English translation. Target word: {word}"
         );
 
+        let shape = if examples == 1 {
+            "Return STRICT JSON with keys word, hindi_sentence, english_sentence.".to_string()
+        } else {
+            format!(
+                "Return STRICT JSON with keys word and examples, where examples is an array of exactly {examples} objects with keys hindi_sentence, english_sentence. Each example must use the word in a different context."
+            )
+        };
+
+        let pos_rule = pos_instruction(pos)
+            + &meaning_instruction(meaning)
+            + &phrase_instruction(options.phrase);
+        let level_rule = difficulty_instruction(options.difficulty);
+        let avoid_rule = avoid_instruction(&options.avoid_sentences);
+        let user = match &self.hindi_prompt_template {
+            Some(template) => format!(
+                "{shape}\n{}{pos_rule}{level_rule}{avoid_rule}",
+                render_prompt_template(template, word, options.hindi_sentence_words)
+            ),
+            None => format!(
+                "{shape} Requirements:\n- sentence length {hindi_range} words\n- include the word exactly once, unmodified unless grammatical inflection is required\n- keep language learner-friendly\n- use Devanagari for Hindi.{pos_rule}{level_rule}{avoid_rule}\nTarget word: {word}",
+                hindi_range = options.hindi_sentence_words,
+            ),
+        };
+        (prompt, user)
+    }
+
+    /// Rough prompt and completion tokens of generating one word, for the
+    /// estimate shown before large batches. The prompt is the one sent for a
+    /// sample word; the completion allows for every requested sentence and
+    /// its translation at the longest configured sentence length, capped at
+    /// `max_completion_tokens`.
+    pub fn estimated_tokens_per_word(
+        &self,
+        language: Language,
+        options: &GenerationOptions,
+    ) -> (u64, u64) {
+        let ((system, user), sentences, sentence_words) = match language {
+            Language::Hindi => (
+                self.hindi_card_prompt("शब्द", None, None, options),
+                options.examples.max(1),
+                options.hindi_sentence_words.max,
+            ),
+            Language::English => (
+                self.english_cloze_prompt("word", None, None, options),
+                options.notes_per_word.max(1),
+                options.english_sentence_words.max,
+            ),
+        };
+        let prompt_chars = system.chars().count() + user.chars().count();
+        let prompt = prompt_chars.div_ceil(CHARS_PER_TOKEN) as u64;
+        let completion = sentences as u64 * u64::from(sentence_words) * COMPLETION_TOKENS_PER_WORD;
+        let completion = match self.max_completion_tokens {
+            Some(limit) => completion.min(u64::from(limit)),
+            None => completion,
+        };
+        (prompt, completion)
+    }
+
+    /// Generate Hindi cards for several words with a single completion. Words
+    /// whose card is missing from the returned array or names a different word
+    /// are generated again one at a time, as is the whole batch if the reply
+    /// cannot be parsed. A custom `hindi_prompt_template`, or any word with its
+    /// own temperature, always generates per word.
+    pub async fn generate_hindi_cards(
+        &self,
+        words: &[WordEntry],
+        options: &GenerationOptions,
+    ) -> Result<Vec<HindiCard>> {
+        let batchable = words.len() > 1
+            && self.hindi_prompt_template.is_none()
+            && words.iter().all(|entry| entry.temperature.is_none());
+        let batch = if batchable {
+            match self.request_hindi_batch(words, options).await {
+                Ok(cards) => {
+                    if cards.len() != words.len() {
+                        tracing::warn!(
+                            "Requested {} cards in one batch but received {}",
+                            words.len(),
+                            cards.len()
+                        );
+                    }
+                    cards
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Batch generation failed, falling back to one request per word: {:#}",
+                        anyhow::Error::from(err)
+                    );
+                    Vec::new()
+                }
+            }
+        } else {
+            Vec::new()
+        };
+        // Match the batch cards to the words by their word rather than their
+        // position, so a reordered or partial batch is still used.
+        let mut batch: HashMap<String, HindiCardPayload> = batch
+            .into_iter()
+            .map(|payload| (input::known_word_key(payload.word()), payload))
+            .collect();
+
+        let examples = options.examples.max(1);
+        let mut cards = Vec::with_capacity(words.len());
+        for entry in words {
+            let card = match batch
+                .remove(&input::known_word_key(&entry.word))
+                .map(|payload| hindi_card_from_payload(&entry.word, payload, examples, self.strict))
+            {
+                Some(Ok(card)) => card,
+                _ => {
+                    tracing::debug!("Generating '{}' individually", entry.word);
+                    self.generate_hindi_card(
+                        &entry.word,
+                        entry.pos,
+                        entry.definition.as_deref(),
+                        &options.for_entry(entry),
+                    )
+                    .await?
+                }
+            };
+            cards.push(card);
+        }
+
+        Ok(cards)
+    }
+
+    async fn request_hindi_batch(
+        &self,
+        words: &[WordEntry],
+        options: &GenerationOptions,
+    ) -> Result<Vec<HindiCardPayload>> {
+        let examples = options.examples.max(1);
+        let prompt = "You are creating language learning flashcards. For each target word, generate natural, short Hindi sentences that use the word exactly once and are easy for learners to understand, each with a natural-sounding English translation.".to_string();
+
+        let targets = words
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                let mut target = format!("{}. {}", idx + 1, entry.word);
+                if let Some(pos) = entry.pos {
+                    target.push_str(&format!(" ({})", pos.as_str()));
+                }
+                if let Some(meaning) = &entry.definition {
+                    target.push_str(&format!(" meaning \"{meaning}\""));
+                }
+                target
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
         let user = format!(
-            "Return STRICT JSON with keys word, hindi_sentence, english_sentence. Requirements:\n- sentence length 5-12 words\n- include the word exactly once, unmodified unless grammatical inflection is required\n- keep language learner-friendly\n- use Devanagari for Hindi.\nTarget word: {word}"
+            "Return STRICT JSON with a single key cards: an array with exactly one object per target word, in the same order. Each object has keys word (the target word, unchanged) and examples, an array of exactly {examples} objects with keys hindi_sentence, english_sentence. Requirements:\n- sentence length {hindi_range} words\n- include the word exactly once, unmodified unless grammatical inflection is required\n- use the word as the part of speech given in parentheses, and in the meaning given, if any\n- keep language learner-friendly\n- use Devanagari for Hindi.{phrase_rule}{level_rule}\nTarget words:\n{targets}",
+            hindi_range = options.hindi_sentence_words,
+            phrase_rule = phrase_instruction(options.phrase),
+            level_rule = difficulty_instruction(options.difficulty)
         );
 
         let payload = self
-            .chat_completion(prompt, user, temperature)
-            .await
-            .context("failed to fetch Hindi card from OpenAI")?;
+            .chat_completion(prompt, user, options.temperature, None)
+            .await?;
 
-        let parsed: HindiCardPayload = parse_json(&payload)?;
+        let parsed: HindiBatchPayload = parse_json(&payload)?;
+        Ok(parsed.cards)
+    }
+
+    pub async fn generate_english_cloze(
+        &self,
+        word: &str,
+        pos: Option<PartOfSpeech>,
+        meaning: Option<&str>,
+        options: &GenerationOptions,
+    ) -> Result<EnglishClozeCard> {
+        let options = GenerationOptions {
+            notes_per_word: 1,
+            ..options.clone()
+        };
+        let mut cards = self
+            .generate_english_clozes(word, pos, meaning, &options)
+            .await?;
+        Ok(cards.remove(0))
+    }
+
+    /// Generate `options.notes_per_word` cloze cards for one word with a
+    /// single completion, each using the word in a different context.
+    /// Repeated sentences are dropped with a warning.
+    pub async fn generate_english_clozes(
+        &self,
+        word: &str,
+        pos: Option<PartOfSpeech>,
+        meaning: Option<&str>,
+        options: &GenerationOptions,
+    ) -> Result<Vec<EnglishClozeCard>> {
+        let count = options.notes_per_word.max(1);
+        let (system, user) = self.english_cloze_prompt(word, pos, meaning, options);
+        let payload = self
+            .chat_completion(
+                system,
+                user,
+                options.temperature,
+                Some(english_cloze_schema(
+                    options.include_ipa,
+                    options.enrich,
+                    count,
+                )),
+            )
+            .await?;
 
-        if !parsed.hindi_sentence.contains(parsed.word.trim()) {
+        let parsed = if count == 1 {
+            vec![parse_json::<EnglishClozePayload>(&payload)?]
+        } else {
+            parse_json::<EnglishClozeBatchPayload>(&payload)?.cards
+        };
+        if parsed.len() != count {
+            return Err(AnkiCliError::llm_parse(format!(
+                "OpenAI returned {} cloze cards for '{word}', expected {count}",
+                parsed.len()
+            )));
+        }
+
+        let mut cards: Vec<EnglishClozeCard> = Vec::with_capacity(parsed.len());
+        for card in parsed {
+            let card = english_cloze_from_payload(
+                word,
+                card,
+                self.hint_policy,
+                options.phrase,
+                self.strict,
+            )?;
+            if cards
+                .iter()
+                .any(|existing| existing.cloze_sentence == card.cloze_sentence)
+            {
+                tracing::warn!("Dropping a repeated cloze sentence for '{}'", word);
+                continue;
+            }
+            cards.push(card);
+        }
+
+        if cards.is_empty() {
+            return Err(AnkiCliError::llm_parse(format!(
+                "Model returned no cloze cards for '{word}'"
+            )));
+        }
+        if cards.len() != count {
             tracing::warn!(
-                "Hindi sentence may not contain original word: {}",
-                parsed.word
+                "Requested {} cloze cards for '{}' but received {}",
+                count,
+                word,
+                cards.len()
             );
         }
-
-        Ok(HindiCard {
-            word: parsed.word.trim().to_string(),
-            hindi_sentence: parsed.hindi_sentence.trim().to_string(),
-            english_sentence: parsed.english_sentence.trim().to_string(),
-        })
+        Ok(cards)
     }
 
-    pub async fn generate_english_cloze(
+    /// System and user prompt of a cloze request for `options.notes_per_word`
+    /// cards.
+    fn english_cloze_prompt(
         &self,
         word: &str,
-        temperature: f32,
-    ) -> Result<EnglishClozeCard> {
+        pos: Option<PartOfSpeech>,
+        meaning: Option<&str>,
+        options: &GenerationOptions,
+    ) -> (String, String) {
+        let count = options.notes_per_word.max(1);
         let system = "You create English cloze deletions for learners who want to improve their English vocabulary.".to_string();
 
+        let ipa_key = if options.include_ipa { ", ipa" } else { "" };
+        let ipa_rule = if options.include_ipa {
+            "\n- For the ipa field, give the IPA transcription of the target word (General American), wrapped in slashes."
+        } else {
+            ""
+        };
+        let enrich_key = if options.enrich {
+            ", synonyms, antonyms"
+        } else {
+            ""
+        };
+        let enrich_rule = if options.enrich {
+            "\n- For the synonyms and antonyms fields, list up to three common single words or short phrases each that match the target word's sense in the sentence; use an empty array when there are none."
+        } else {
+            ""
+        };
+        let language = &self.explanation_language;
+        let explanation_rule = if language.eq_ignore_ascii_case(DEFAULT_EXPLANATION_LANGUAGE) {
+            String::new()
+        } else {
+            format!(" Write the translation field in {language}.")
+        };
+        let keys = format!("word, cloze_sentence, translation, hint{ipa_key}{enrich_key}");
+        let shape = if count == 1 {
+            format!("Return STRICT JSON with keys {keys}.")
+        } else {
+            format!(
+                "Return STRICT JSON with a single key cards: an array of exactly {count} objects with keys {keys}. Each object must use the word in a different context (a different sense where the word has several), and no two cloze_sentence values may be the same."
+            )
+        };
+        let pos_rule = pos_instruction(pos)
+            + &meaning_instruction(meaning)
+            + &phrase_instruction(options.phrase);
+        let level_rule = difficulty_instruction(options.difficulty);
+        let avoid_rule = avoid_instruction(&options.avoid_sentences);
+        let user = match &self.english_prompt_template {
+            Some(template) => format!(
+                "{shape} The cloze_sentence must use Anki cloze syntax {{c1::...}} exactly once around the target word.{explanation_rule}{ipa_rule}{enrich_rule}\n{}{pos_rule}{level_rule}{avoid_rule}",
+                render_prompt_template(template, word, options.english_sentence_words)
+            ),
+            None => format!(
+                "{shape}\nRules:\n- Use Anki cloze syntax {{c1::...}} exactly once around the target word or phrase.\n- If a hint is provided, include it using the built-in format {{c1::answer::hint}} so Anki can show a hint link.\n- Sentence length {english_range} words.\n- For the translation field, provide a concise {language} paraphrase or definition that clarifies the meaning of the sentence.\n- Optional hint should help recall the word and can be null.{ipa_rule}{enrich_rule}{pos_rule}{level_rule}{avoid_rule}\nTarget word: {word}",
+                english_range = options.english_sentence_words,
+            ),
+        };
+        (system, user)
+    }
+
+    pub async fn generate_english_definition(
+        &self,
+        word: &str,
+        pos: Option<PartOfSpeech>,
+        meaning: Option<&str>,
+        options: &GenerationOptions,
+    ) -> Result<EnglishDefinitionCard> {
+        let system = "You write concise English dictionary definitions for learners who want to improve their English vocabulary.".to_string();
+
         let user = format!(
-            "Return STRICT JSON with keys word, cloze_sentence, translation, hint.\nRules:\n- Use Anki cloze syntax {{c1::...}} exactly once around the target word or phrase.\n- If a hint is provided, include it using the built-in format {{c1::answer::hint}} so Anki can show a hint link.\n- Sentence length 8-16 words.\n- For the translation field, provide a concise English paraphrase or definition that clarifies the meaning of the sentence.\n- Optional hint should help recall the word and can be null.\nTarget word: {word}"
+            "Return STRICT JSON with keys word, definition, example{ipa_key}.\nRules:\n- The definition should be a short learner-friendly explanation of the word's meaning and must not contain the word itself.\n- The example is one natural sentence of {english_range} words using the word, or null.{ipa_rule}{pos_rule}{level_rule}{avoid_rule}\nTarget word: {word}",
+            english_range = options.english_sentence_words,
+            ipa_key = if options.include_ipa { ", ipa" } else { "" },
+            ipa_rule = if options.include_ipa {
+                "\n- For the ipa field, give the IPA transcription of the target word (General American), wrapped in slashes."
+            } else {
+                ""
+            },
+            pos_rule = pos_instruction(pos)
+                + &meaning_instruction(meaning)
+                + &phrase_instruction(options.phrase),
+            level_rule = difficulty_instruction(options.difficulty),
+            avoid_rule = avoid_instruction(&options.avoid_sentences),
         );
 
         let payload = self
-            .chat_completion(system, user, temperature)
+            .chat_completion(system, user, options.temperature, None)
+            .await?;
+
+        let parsed: EnglishDefinitionPayload = parse_json(&payload)?;
+
+        Ok(EnglishDefinitionCard {
+            word: required_field(&parsed.word, "word", word)?,
+            definition: required_field(&parsed.definition, "definition", word)?,
+            example: parsed
+                .example
+                .map(|example| example.trim().to_string())
+                .filter(|example| !example.is_empty()),
+            ipa: parsed
+                .ipa
+                .map(|ipa| ipa.trim().to_string())
+                .filter(|ipa| !ipa.is_empty()),
+        })
+    }
+
+    /// Request to `path` under the base URL with the provider's authentication,
+    /// headers and the configured `extra_query` parameters. Azure routes per-model
+    /// endpoints through the deployment named after `model`.
+    fn request(&self, method: Method, path: &str, model: Option<&str>) -> RequestBuilder {
+        let base = self.base_url.trim_end_matches('/');
+        let url = match (self.provider, model) {
+            (Provider::OpenAi, _) => format!("{base}/{path}"),
+            (Provider::Azure, Some(model)) => {
+                format!("{base}/openai/deployments/{model}/{path}")
+            }
+            (Provider::Azure, None) => format!("{base}/openai/{path}"),
+        };
+        let request = self
+            .http
+            .request(method, url)
+            .headers(self.headers.clone())
+            .query(&self.extra_query);
+        match self.provider {
+            Provider::OpenAi => request.bearer_auth(&self.api_key),
+            Provider::Azure => request.header("api-key", &self.api_key),
+        }
+    }
+
+    /// List the model ids exposed by the `/models` endpoint.
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self
+            .request(Method::GET, "models", None)
+            .send()
             .await
-            .context("failed to fetch English cloze from OpenAI")?;
+            .map_err(|err| AnkiCliError::network("failed to call OpenAI models endpoint", err))?;
 
-        let parsed: EnglishClozePayload = parse_json(&payload)?;
+        let response = http::ensure_success(SERVICE, response).await?;
+        let parsed: ModelListResponse = http::read_json(SERVICE, response).await?;
 
-        let word_trimmed = parsed.word.trim().to_string();
-        let hint = parsed
-            .hint
-            .map(|h| h.trim().to_string())
-            .filter(|h| !h.is_empty());
+        Ok(parsed.data.into_iter().map(|model| model.id).collect())
+    }
 
-        let cloze_sentence =
-            build_cloze_sentence(parsed.cloze_sentence.trim(), &word_trimmed, hint.as_deref());
+    /// Generate one image for `prompt` with the configured `image_model` and
+    /// return the encoded image. Models that answer with a URL instead of
+    /// base64 data have the image downloaded from it.
+    pub async fn generate_image(&self, prompt: &str) -> Result<Vec<u8>> {
+        let request = ImageRequest {
+            model: &self.image_model,
+            prompt,
+            n: 1,
+        };
+        let response = self
+            .request(
+                Method::POST,
+                "images/generations",
+                Some(self.image_model.as_str()),
+            )
+            .json(&request)
+            .send()
+            .await
+            .map_err(|err| AnkiCliError::network("failed to call OpenAI images endpoint", err))?;
 
-        Ok(EnglishClozeCard {
-            word: word_trimmed,
-            cloze_sentence,
-            translation: parsed.translation.trim().to_string(),
-            hint,
-        })
+        let response = http::ensure_success(SERVICE, response).await?;
+        let parsed: ImageResponse = http::read_json(SERVICE, response).await?;
+        let image = parsed
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| AnkiCliError::llm_parse("OpenAI returned no image"))?;
+
+        if let Some(data) = image.b64_json {
+            return BASE64.decode(data.trim()).map_err(|err| {
+                AnkiCliError::llm_parse(format!("OpenAI returned invalid image data: {err}"))
+            });
+        }
+        let Some(image_url) = image.url else {
+            return Err(AnkiCliError::llm_parse(
+                "OpenAI returned neither image data nor an image URL",
+            ));
+        };
+        // Outside `request`, so the image host gets neither the API key nor
+        // the provider headers.
+        let response = self
+            .http
+            .get(image_url)
+            .send()
+            .await
+            .map_err(|err| AnkiCliError::network("failed to download generated image", err))?;
+        let response = http::ensure_success(SERVICE, response).await?;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| AnkiCliError::network("failed to read generated image", err))?;
+        Ok(bytes.to_vec())
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
     }
 
+    /// Prompt and completion tokens the endpoint reported for this client's
+    /// chat completions so far. Streamed responses report none.
+    pub fn token_usage(&self) -> (u64, u64) {
+        (
+            self.prompt_tokens.load(Ordering::Relaxed),
+            self.completion_tokens.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Run a chat completion in JSON mode. With `tool_calling` enabled and a
+    /// `schema` given, the schema is offered as a forced `create_card`
+    /// function and its arguments are returned. Otherwise, with
+    /// `structured_output`, the strict `json_schema` response format is tried.
+    /// Whichever of these the endpoint rejects is dropped for this and every
+    /// later request, ending at `json_object`.
+    ///
+    /// A configured `style` and, with `safe_mode`, the content restrictions
+    /// are appended to the system prompt of every request.
+    ///
+    /// A refusal is retried once with a note that the request is a neutral
+    /// vocabulary exercise; a second refusal is returned as
+    /// [`AnkiCliError::Refused`].
     async fn chat_completion(
         &self,
         system: String,
         user: String,
         temperature: f32,
+        schema: Option<JsonSchemaFormat>,
+    ) -> Result<String> {
+        let system = match &self.style {
+            Some(style) => {
+                tracing::debug!("Applying style: {style}");
+                format!("{system} Style: {style}")
+            }
+            None => system,
+        };
+        let system = if self.safe_mode {
+            format!("{system} {SAFE_MODE_INSTRUCTION}")
+        } else {
+            system
+        };
+        match self
+            .chat_completion_once(&system, &user, temperature, schema.clone())
+            .await
+        {
+            Err(AnkiCliError::Refused(reason)) => {
+                tracing::warn!(
+                    "Model refused the request ({reason}); retrying with a softened prompt"
+                );
+                let user = format!("{user}{SOFTENED_PROMPT_SUFFIX}");
+                self.chat_completion_once(&system, &user, temperature, schema)
+                    .await
+            }
+            other => other,
+        }
+    }
+
+    async fn chat_completion_once(
+        &self,
+        system: &str,
+        user: &str,
+        temperature: f32,
+        schema: Option<JsonSchemaFormat>,
     ) -> Result<String> {
         let temperature = temperature.clamp(0.0, 2.0);
+        if self.print_prompt {
+            tracing::info!("System prompt:\n{system}");
+            tracing::info!("User prompt:\n{user}");
+        }
+
+        let schema = match schema {
+            Some(schema)
+                if self.tool_calling && !self.tools_unsupported.load(Ordering::Relaxed) =>
+            {
+                let tool = Tool::create_card(&schema);
+                match self
+                    .send_chat_completion(system, user, temperature, RequestFormat::Tool(tool))
+                    .await
+                {
+                    Err(AnkiCliError::Http { status, body, .. })
+                        if status == StatusCode::BAD_REQUEST
+                            || status == StatusCode::UNPROCESSABLE_ENTITY =>
+                    {
+                        tracing::warn!(
+                            "Endpoint rejected function calling ({status}); falling back to JSON output"
+                        );
+                        tracing::debug!("tool rejection body: {body}");
+                        self.tools_unsupported.store(true, Ordering::Relaxed);
+                        Some(schema)
+                    }
+                    other => return other,
+                }
+            }
+            schema => schema,
+        };
+
+        if let Some(schema) = schema
+            && self.structured_output
+            && !self.schema_unsupported.load(Ordering::Relaxed)
+        {
+            let format = ResponseFormat {
+                kind: "json_schema".to_string(),
+                json_schema: Some(schema),
+            };
+            match self
+                .send_chat_completion(system, user, temperature, RequestFormat::Json(format))
+                .await
+            {
+                Err(AnkiCliError::Http { status, body, .. })
+                    if status == StatusCode::BAD_REQUEST
+                        || status == StatusCode::UNPROCESSABLE_ENTITY =>
+                {
+                    tracing::warn!(
+                        "Endpoint rejected the json_schema response format ({status}); falling back to json_object"
+                    );
+                    tracing::debug!("json_schema rejection body: {body}");
+                    self.schema_unsupported.store(true, Ordering::Relaxed);
+                }
+                other => return other,
+            }
+        }
+
+        let format = ResponseFormat {
+            kind: "json_object".to_string(),
+            json_schema: None,
+        };
+        self.send_chat_completion(system, user, temperature, RequestFormat::Json(format))
+            .await
+    }
 
+    async fn send_chat_completion(
+        &self,
+        system: &str,
+        user: &str,
+        temperature: f32,
+        format: RequestFormat,
+    ) -> Result<String> {
+        let (response_format, tools, tool_choice) = match format {
+            RequestFormat::Json(format) => (Some(format), None, None),
+            RequestFormat::Tool(tool) => {
+                let choice = ToolChoice::function(tool.function.name);
+                (None, Some(vec![tool]), Some(choice))
+            }
+        };
         let request = ChatCompletionRequest {
             model: self.model.clone(),
             messages: vec![
                 Message {
                     role: "system".to_string(),
-                    content: system,
+                    content: system.to_string(),
                 },
                 Message {
                     role: "user".to_string(),
-                    content: user,
+                    content: user.to_string(),
                 },
             ],
             temperature,
-            response_format: Some(ResponseFormat {
-                kind: "json_object".to_string(),
-            }),
+            top_p: self.top_p,
+            seed: self.seed,
+            max_completion_tokens: self.max_completion_tokens,
+            frequency_penalty: self.frequency_penalty,
+            presence_penalty: self.presence_penalty,
+            stream: self.stream,
+            response_format,
+            tools,
+            tool_choice,
         };
 
-        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let mut retries = 0;
+        loop {
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.acquire().await;
+            }
+            match (
+                &self.rate_limiter,
+                self.post_chat_completion(&request).await,
+            ) {
+                (Some(limiter), Err(AnkiCliError::RateLimited { retry_after, .. }))
+                    if retries < MAX_RATE_LIMIT_RETRIES =>
+                {
+                    let delay = retry_after.unwrap_or_else(|| limiter.default_backoff());
+                    tracing::warn!(
+                        "OpenAI rate limit hit; retrying in {:.1}s",
+                        delay.as_secs_f64()
+                    );
+                    limiter.pause(delay).await;
+                    retries += 1;
+                }
+                (_, result) => return result,
+            }
+        }
+    }
+
+    async fn post_chat_completion(&self, request: &ChatCompletionRequest) -> Result<String> {
+        let started = Instant::now();
+        let result = self.fetch_chat_completion(request).await;
+        if let Ok((_, Some(usage))) = &result {
+            self.prompt_tokens
+                .fetch_add(usage.prompt_tokens, Ordering::Relaxed);
+            self.completion_tokens
+                .fetch_add(usage.completion_tokens, Ordering::Relaxed);
+        }
+        if let Some(path) = &self.llm_log {
+            self.log_exchange(path, request, &result, started.elapsed());
+        }
+        result.map(|(content, _)| content)
+    }
+
+    /// Append one request and its outcome to the `llm_log` file. Failures are
+    /// logged, not returned, so auditing never aborts a run.
+    fn log_exchange(
+        &self,
+        path: &Path,
+        request: &ChatCompletionRequest,
+        result: &Result<(String, Option<Usage>)>,
+        latency: Duration,
+    ) {
+        let (completion, usage, error) = match result {
+            Ok((content, usage)) => (Some(content.as_str()), *usage, None),
+            Err(err) => (None, None, Some(err.to_string())),
+        };
+        let entry = json!({
+            "timestamp": Local::now().to_rfc3339(),
+            "model": request.model,
+            "temperature": request.temperature,
+            "messages": request.messages,
+            "completion": completion,
+            "error": error,
+            "latency_ms": u64::try_from(latency.as_millis()).unwrap_or(u64::MAX),
+            "usage": usage,
+        });
+        // The key is never part of a request body, but make sure it cannot
+        // leak through an echoed error either.
+        let line = entry.to_string().replace(&self.api_key, "[redacted]");
+
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| OpenOptions::new().create(true).append(true).open(path))
+            .and_then(|mut file| writeln!(file, "{line}"));
+        if let Err(err) = written {
+            tracing::warn!("Failed to write LLM log {}: {}", path.display(), err);
+        }
+    }
+
+    async fn fetch_chat_completion(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<(String, Option<Usage>)> {
         let response = self
-            .http
-            .post(url)
-            .bearer_auth(&self.api_key)
-            .json(&request)
+            .request(Method::POST, "chat/completions", Some(self.model.as_str()))
+            .json(request)
             .send()
             .await
-            .context("failed to call OpenAI chat completion endpoint")?;
+            .map_err(|err| {
+                AnkiCliError::network("failed to call OpenAI chat completion endpoint", err)
+            })?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("OpenAI HTTP error {status}: {body}");
+        let response = http::ensure_success(SERVICE, response).await?;
+        if self.stream {
+            return Ok((self.read_stream(response).await?, None));
         }
-
-        let parsed: ChatCompletionResponse = response
-            .json()
-            .await
-            .context("failed to parse OpenAI response JSON")?;
+        let parsed: ChatCompletionResponse = http::read_json(SERVICE, response).await?;
+        let usage = parsed.usage;
 
         let choice = parsed
             .choices
             .into_iter()
             .next()
-            .ok_or_else(|| anyhow!("OpenAI returned no choices"))?;
+            .ok_or_else(|| AnkiCliError::llm_parse("OpenAI returned no choices"))?;
 
-        Ok(choice.message.content)
+        self.check_finish_reason(choice.finish_reason.as_deref())?;
+        let message = choice.message;
+        if let Some(refusal) = message.refusal {
+            return Err(AnkiCliError::Refused(refusal));
+        }
+        if let Some(call) = message.tool_calls.into_iter().next() {
+            return Ok((call.function.arguments, usage));
+        }
+        let content = message.content.ok_or_else(|| {
+            AnkiCliError::llm_parse("OpenAI returned neither content nor a tool call")
+        })?;
+        Ok((content, usage))
+    }
+
+    /// Assemble the content of a server-sent-events completion. With debug
+    /// logging enabled (`--verbose`) each token is echoed to stderr as it
+    /// arrives.
+    async fn read_stream(&self, mut response: reqwest::Response) -> Result<String> {
+        let echo = tracing::enabled!(tracing::Level::DEBUG);
+        let mut pending = Vec::new();
+        let mut content = String::new();
+        let mut refusal = String::new();
+        let mut finish_reason = None;
+
+        'stream: loop {
+            let chunk = response
+                .chunk()
+                .await
+                .map_err(|err| AnkiCliError::network("failed to read OpenAI stream", err))?;
+            let Some(chunk) = chunk else {
+                break;
+            };
+            pending.extend_from_slice(&chunk);
+
+            // Events are newline-delimited; keep a trailing partial line
+            // (possibly a split UTF-8 sequence) for the next chunk.
+            while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    break 'stream;
+                }
+
+                let event: StreamChunk = serde_json::from_str(data).map_err(|source| {
+                    AnkiCliError::invalid_response(
+                        SERVICE,
+                        Some("text/event-stream".to_string()),
+                        data,
+                        source,
+                    )
+                })?;
+                if let Some(choice) = event.choices.into_iter().next() {
+                    if let Some(token) = &choice.delta.refusal {
+                        refusal.push_str(token);
+                    }
+                    let arguments = choice
+                        .delta
+                        .tool_calls
+                        .into_iter()
+                        .next()
+                        .and_then(|call| call.function.arguments);
+                    if let Some(token) = choice.delta.content.or(arguments) {
+                        if echo {
+                            eprint!("{token}");
+                            let _ = std::io::stderr().flush();
+                        }
+                        content.push_str(&token);
+                    }
+                    if choice.finish_reason.is_some() {
+                        finish_reason = choice.finish_reason;
+                    }
+                }
+            }
+        }
+        if echo && !content.is_empty() {
+            eprintln!();
+        }
+
+        if !refusal.is_empty() {
+            return Err(AnkiCliError::Refused(refusal));
+        }
+        self.check_finish_reason(finish_reason.as_deref())?;
+        if content.is_empty() {
+            return Err(AnkiCliError::llm_parse(
+                "OpenAI stream contained no content",
+            ));
+        }
+        Ok(content)
+    }
+
+    /// A truncated completion is almost always invalid JSON; say why instead
+    /// of letting the card parser fail on it.
+    fn check_finish_reason(&self, finish_reason: Option<&str>) -> Result<()> {
+        if finish_reason != Some("length") {
+            return Ok(());
+        }
+
+        let limit = match self.max_completion_tokens {
+            Some(tokens) => format!("the max_completion_tokens limit of {tokens}"),
+            None => "the model's default token limit".to_string(),
+        };
+        Err(AnkiCliError::llm_parse(format!(
+            "OpenAI response was cut off at {limit}; raise max_completion_tokens (or --max-completion-tokens) and try again"
+        )))
     }
 }
 
+/// Strict schema matching the `HindiCardPayload` shape the prompt asks for.
+fn hindi_card_schema(examples: usize) -> JsonSchemaFormat {
+    let schema = if examples == 1 {
+        json!({
+            "type": "object",
+            "properties": {
+                "word": { "type": "string" },
+                "hindi_sentence": { "type": "string" },
+                "english_sentence": { "type": "string" }
+            },
+            "required": ["word", "hindi_sentence", "english_sentence"],
+            "additionalProperties": false
+        })
+    } else {
+        json!({
+            "type": "object",
+            "properties": {
+                "word": { "type": "string" },
+                "examples": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "hindi_sentence": { "type": "string" },
+                            "english_sentence": { "type": "string" }
+                        },
+                        "required": ["hindi_sentence", "english_sentence"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["word", "examples"],
+            "additionalProperties": false
+        })
+    };
+
+    JsonSchemaFormat {
+        name: "hindi_card",
+        strict: true,
+        schema,
+    }
+}
+
+/// Strict schema matching `EnglishClozePayload`; `hint`, `ipa` and the
+/// enrichment lists are nullable.
+fn english_cloze_schema(include_ipa: bool, enrich: bool, count: usize) -> JsonSchemaFormat {
+    let mut properties = json!({
+        "word": { "type": "string" },
+        "cloze_sentence": { "type": "string" },
+        "translation": { "type": "string" },
+        "hint": { "type": ["string", "null"] }
+    });
+    let mut required = vec!["word", "cloze_sentence", "translation", "hint"];
+    if include_ipa {
+        properties["ipa"] = json!({ "type": ["string", "null"] });
+        required.push("ipa");
+    }
+    if enrich {
+        for key in ["synonyms", "antonyms"] {
+            properties[key] = json!({
+                "type": ["array", "null"],
+                "items": { "type": "string" }
+            });
+            required.push(key);
+        }
+    }
+
+    let card = json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false
+    });
+    let schema = if count == 1 {
+        card
+    } else {
+        json!({
+            "type": "object",
+            "properties": {
+                "cards": { "type": "array", "items": card }
+            },
+            "required": ["cards"],
+            "additionalProperties": false
+        })
+    };
+
+    JsonSchemaFormat {
+        name: "english_cloze",
+        strict: true,
+        schema,
+    }
+}
+
+/// Turn one parsed cloze object into an `EnglishClozeCard`. Outside the
+/// `model` hint policy, the card's hint is the one built locally.
+fn english_cloze_from_payload(
+    word: &str,
+    parsed: EnglishClozePayload,
+    hint_policy: HintPolicy,
+    phrase: bool,
+    strict: bool,
+) -> Result<EnglishClozeCard> {
+    let word_trimmed = required_field(&parsed.word, "word", word)?;
+    let translation = required_field(&parsed.translation, "translation", word)?;
+    required_field(&parsed.cloze_sentence, "cloze_sentence", word)?;
+    let model_hint = parsed
+        .hint
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty());
+
+    let (cloze_sentence, local_hint) = build_cloze_sentence(
+        parsed.cloze_sentence.trim(),
+        &word_trimmed,
+        hint_policy,
+        model_hint.as_deref(),
+        phrase,
+        strict,
+    )?;
+    let hint = if hint_policy == HintPolicy::Model {
+        model_hint
+    } else {
+        local_hint
+    };
+
+    Ok(EnglishClozeCard {
+        word: word_trimmed,
+        cloze_sentence,
+        translation,
+        hint,
+        ipa: parsed
+            .ipa
+            .map(|ipa| ipa.trim().to_string())
+            .filter(|ipa| !ipa.is_empty()),
+        synonyms: clean_word_list(parsed.synonyms),
+        antonyms: clean_word_list(parsed.antonyms),
+    })
+}
+
+/// The trimmed value of a field the model must fill in, or a parse error
+/// naming the field when it is blank.
+fn required_field(value: &str, field: &str, word: &str) -> Result<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(AnkiCliError::llm_parse(format!(
+            "OpenAI returned an empty {field} for '{word}'"
+        )));
+    }
+    Ok(value.to_string())
+}
+
+/// Turn one parsed Hindi card into a `HindiCard`. Missing sentences or the
+/// wrong number of examples are parse errors; sentences that do not seem to
+/// use the word are warned about.
+fn hindi_card_from_payload(
+    word: &str,
+    parsed: HindiCardPayload,
+    examples: usize,
+    strict: bool,
+) -> Result<HindiCard> {
+    let (parsed_word, parsed_examples) = match parsed {
+        HindiCardPayload::Single {
+            word,
+            hindi_sentence,
+            english_sentence,
+        } => (
+            word,
+            vec![HindiExamplePayload {
+                hindi_sentence,
+                english_sentence,
+            }],
+        ),
+        HindiCardPayload::Multiple { word, examples } => (word, examples),
+    };
+
+    let parsed_word = required_field(&parsed_word, "word", word)?;
+    if parsed_examples.len() != examples {
+        return Err(AnkiCliError::llm_parse(format!(
+            "OpenAI returned {} Hindi examples for '{word}', expected {examples}",
+            parsed_examples.len()
+        )));
+    }
+    let card_examples = parsed_examples
+        .into_iter()
+        .map(|ex| {
+            Ok(HindiExample {
+                hindi_sentence: required_field(&ex.hindi_sentence, "hindi_sentence", word)?,
+                english_sentence: required_field(&ex.english_sentence, "english_sentence", word)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    for example in &card_examples {
+        if !hindi_sentence_contains(&example.hindi_sentence, &parsed_word) {
+            if strict {
+                return Err(AnkiCliError::llm_parse(format!(
+                    "Hindi sentence for '{parsed_word}' does not contain the word: {}",
+                    example.hindi_sentence
+                )));
+            }
+            tracing::warn!(
+                "Hindi sentence may not contain original word: {}",
+                parsed_word
+            );
+        }
+    }
+
+    Ok(HindiCard {
+        word: parsed_word,
+        examples: card_examples,
+    })
+}
+
+/// Whether a Hindi sentence uses the word, allowing for inflection: every
+/// token of the word must appear in the sentence either verbatim or as a
+/// token sharing its stem (`लड़का` matches `लड़के`, `खाना` matches `खाता`).
+fn hindi_sentence_contains(sentence: &str, word: &str) -> bool {
+    let sentence = normalize_unicode(sentence);
+    let word = normalize_unicode(word);
+    if sentence.contains(&word) {
+        return true;
+    }
+
+    let tokens: Vec<&str> = sentence
+        .split(|c: char| c.is_whitespace() || matches!(c, '।' | '॥') || c.is_ascii_punctuation())
+        .filter(|token| !token.is_empty())
+        .collect();
+    word.split_whitespace().all(|part| {
+        let stem = hindi_stem(part);
+        tokens.iter().any(|token| {
+            hindi_stem(token) == stem
+                || (stem.chars().count() >= MIN_PREFIX_STEM_CHARS && token.starts_with(stem))
+        })
+    })
+}
+
+/// The word with its longest matching entry from [`HINDI_SUFFIXES`] removed,
+/// as long as at least two characters remain.
+fn hindi_stem(word: &str) -> &str {
+    HINDI_SUFFIXES
+        .iter()
+        .filter_map(|suffix| word.strip_suffix(suffix))
+        .filter(|stem| stem.chars().count() >= 2)
+        .min_by_key(|stem| stem.len())
+        .unwrap_or(word)
+}
+
+fn render_examples<'a>(sentences: impl Iterator<Item = &'a str>) -> String {
+    let sentences: Vec<&str> = sentences.collect();
+    if sentences.len() == 1 {
+        return sentences[0].to_string();
+    }
+
+    sentences
+        .iter()
+        .enumerate()
+        .map(|(idx, sentence)| format!("{}. {}", idx + 1, sentence))
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
+
+/// Trimmed, non-empty entries of a synonym/antonym list; `null` becomes empty.
+fn clean_word_list(words: Option<Vec<String>>) -> Vec<String> {
+    words
+        .unwrap_or_default()
+        .into_iter()
+        .map(|word| word.trim().to_string())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Fill the `{word}`, `{min_words}` and `{max_words}` placeholders of a
+/// user-supplied prompt template.
+fn render_prompt_template(template: &str, word: &str, range: WordRange) -> String {
+    template
+        .replace("{word}", word)
+        .replace("{min_words}", &range.min.to_string())
+        .replace("{max_words}", &range.max.to_string())
+}
+
+fn pos_instruction(pos: Option<PartOfSpeech>) -> String {
+    match pos {
+        Some(pos) => format!(
+            "\n- Use the target word as a {} in the sentence.",
+            pos.as_str()
+        ),
+        None => String::new(),
+    }
+}
+
+fn phrase_instruction(phrase: bool) -> String {
+    if phrase {
+        "\n- The target is a multi-word expression (a phrase or idiom): use it whole, in its idiomatic sense, and treat all of its words as one unit, e.g. inside a single cloze.".to_string()
+    } else {
+        String::new()
+    }
+}
+
+fn meaning_instruction(meaning: Option<&str>) -> String {
+    match meaning {
+        Some(meaning) => format!("\n- Use the target word in this sense: {meaning}"),
+        None => String::new(),
+    }
+}
+
+fn difficulty_instruction(difficulty: Option<Difficulty>) -> String {
+    match difficulty {
+        Some(level) => format!(
+            "\n- Target CEFR level {} ({}); keep vocabulary and grammar within that level.",
+            level.as_str().to_uppercase(),
+            level.description()
+        ),
+        None => String::new(),
+    }
+}
+
+fn avoid_instruction(sentences: &[String]) -> String {
+    sentences
+        .iter()
+        .map(|sentence| {
+            format!(
+                "\n- Make the sentence clearly different in topic and structure from this earlier one: {sentence}"
+            )
+        })
+        .collect()
+}
+
 fn parse_json<T>(raw: &str) -> Result<T>
 where
     T: for<'de> Deserialize<'de>,
@@ -177,7 +1461,10 @@ where
         trimmed.to_string()
     };
 
-    serde_json::from_str(&json).with_context(|| format!("failed to parse JSON payload: {json}"))
+    serde_json::from_str(&json).map_err(|source| AnkiCliError::LlmParse {
+        message: format!("failed to parse JSON payload: {json}"),
+        source: Some(source),
+    })
 }
 
 fn extract_json_block(raw: &str) -> Option<String> {
@@ -192,24 +1479,54 @@ fn extract_json_block(raw: &str) -> Option<String> {
         return None;
     }
 
-    if let Some(last) = content.last() {
-        if last.trim().starts_with("```") {
-            content.pop();
-        }
+    if let Some(last) = content.last()
+        && last.trim().starts_with("```")
+    {
+        content.pop();
     }
 
     Some(content.join("\n"))
 }
 
-fn build_cloze_sentence(raw_sentence: &str, word: &str, hint: Option<&str>) -> String {
+/// Wrap `word` in `{{c1::...}}` and add the hint chosen by `hint_policy`,
+/// returning the sentence and the hint it got.
+fn build_cloze_sentence(
+    raw_sentence: &str,
+    word: &str,
+    hint_policy: HintPolicy,
+    model_hint: Option<&str>,
+    phrase: bool,
+    strict: bool,
+) -> Result<(String, Option<String>)> {
     let trimmed = raw_sentence.trim();
     let original = trimmed.to_string();
 
-    let base_sentence =
-        strip_existing_cloze_markup(trimmed, word).unwrap_or_else(|| original.clone());
+    let wrapped = if phrase {
+        // The model may have wrapped only part of the phrase; drop its markup
+        // and wrap the whole expression as one cloze group
+        let plain = uncloze(trimmed);
+        match phrase_span(&plain, word) {
+            Some((start, end)) => Some(format!(
+                "{}{{{{c1::{}}}}}{}",
+                &plain[..start],
+                &plain[start..end],
+                &plain[end..]
+            )),
+            None => wrap_with_cloze(&plain, word),
+        }
+    } else {
+        let base_sentence =
+            strip_existing_cloze_markup(trimmed, word).unwrap_or_else(|| original.clone());
+        wrap_with_cloze(&base_sentence, word)
+    };
 
-    let mut cloze_sentence = match wrap_with_cloze(&base_sentence, word) {
+    let cloze_sentence = match wrapped {
         Some(wrapped) => wrapped,
+        None if strict => {
+            return Err(AnkiCliError::llm_parse(format!(
+                "Could not put cloze markup around '{word}' in: {original}"
+            )));
+        }
         None => {
             tracing::warn!(
                 "Failed to insert cloze markup for '{}' - reverting to model output",
@@ -219,11 +1536,7 @@ fn build_cloze_sentence(raw_sentence: &str, word: &str, hint: Option<&str>) -> S
         }
     };
 
-    if let Some(hint_value) = hint {
-        cloze_sentence = inject_anki_hint(&cloze_sentence, hint_value);
-    }
-
-    cloze_sentence
+    Ok(inject_anki_hint(&cloze_sentence, hint_policy, model_hint))
 }
 
 fn strip_existing_cloze_markup(sentence: &str, replacement: &str) -> Option<String> {
@@ -288,6 +1601,84 @@ fn strip_existing_cloze_markup(sentence: &str, replacement: &str) -> Option<Stri
     if replaced { Some(result) } else { None }
 }
 
+/// `sentence` with its cloze markup replaced by the answers, e.g.
+/// `I {{c1::ran::verb}} home` becomes `I ran home`.
+pub(crate) fn uncloze(sentence: &str) -> String {
+    let mut out = String::with_capacity(sentence.len());
+    let mut rest = sentence;
+    while let Some(start) = rest.find("{{c") {
+        let after = &rest[start + 3..];
+        let digits = after.chars().take_while(char::is_ascii_digit).count();
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        if digits == 0 || !after[digits..].starts_with("::") || end < digits + 2 {
+            out.push_str(&rest[start..start + 3]);
+            rest = after;
+            continue;
+        }
+        let body = &after[digits + 2..end];
+        out.push_str(body.split("::").next().unwrap_or_default());
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Byte range of a multi-word phrase in the sentence, from its first word to
+/// its last. Words are matched in order and case-insensitively, and a
+/// sentence word only has to start with the phrase word's stem so inflected
+/// forms (`kicked the bucket`, `taking it easy`) are found too.
+fn phrase_span(sentence: &str, phrase: &str) -> Option<(usize, usize)> {
+    let stems: Vec<String> = phrase
+        .split_whitespace()
+        .map(|part| {
+            let part = part.to_lowercase();
+            match part.strip_suffix(['e', 'y']) {
+                Some(stem) if stem.chars().count() >= 3 => stem.to_string(),
+                _ => part,
+            }
+        })
+        .collect();
+    if stems.len() < 2 {
+        return None;
+    }
+
+    // Whitespace-separated tokens with their byte offsets, trimmed of
+    // surrounding punctuation.
+    let mut words: Vec<(usize, &str)> = Vec::new();
+    let mut token_start = None;
+    let boundaries = sentence
+        .char_indices()
+        .chain(std::iter::once((sentence.len(), ' ')));
+    for (idx, c) in boundaries {
+        match (token_start, c.is_whitespace()) {
+            (None, false) => token_start = Some(idx),
+            (Some(start), true) => {
+                let token = &sentence[start..idx];
+                let leading = token.len()
+                    - token
+                        .trim_start_matches(|c: char| !c.is_alphanumeric())
+                        .len();
+                let word = token.trim_matches(|c: char| !c.is_alphanumeric());
+                words.push((start + leading, word));
+                token_start = None;
+            }
+            _ => {}
+        }
+    }
+    words.windows(stems.len()).find_map(|window| {
+        let matches = window
+            .iter()
+            .zip(&stems)
+            .all(|((_, word), stem)| !word.is_empty() && word.to_lowercase().starts_with(stem));
+        let (first, _) = window.first()?;
+        let (last, last_word) = window.last()?;
+        matches.then_some((*first, last + last_word.len()))
+    })
+}
+
 fn wrap_with_cloze(sentence: &str, word: &str) -> Option<String> {
     if sentence.contains("{{c1::") {
         return Some(sentence.to_string());
@@ -338,26 +1729,48 @@ fn advance_by_chars(text: &str, start: usize, char_count: usize) -> usize {
     text.len()
 }
 
-fn inject_anki_hint(cloze_sentence: &str, hint: &str) -> String {
-    let hint = hint.trim();
-    if hint.is_empty() {
-        return cloze_sentence.to_string();
-    }
-
-    if let Some(start) = cloze_sentence.find("{{c1::") {
-        let prefix = &cloze_sentence[..start + 6];
-        let rest = &cloze_sentence[start + 6..];
-        if let Some(end_rel) = rest.find("}}") {
-            let inside = &rest[..end_rel];
-            if inside.contains("::") {
-                return cloze_sentence.to_string();
-            }
-            let suffix = &rest[end_rel..];
-            return format!("{}{}::{}{}", prefix, inside, hint, suffix);
-        }
+/// Add a hint to the `c1` cloze: the model's hint, or one derived from the
+/// answer (`w...` for `first_letter`, one dash per letter for `length`). A
+/// cloze that already has a hint is left as it is.
+fn inject_anki_hint(
+    cloze_sentence: &str,
+    policy: HintPolicy,
+    model_hint: Option<&str>,
+) -> (String, Option<String>) {
+    let unchanged = || (cloze_sentence.to_string(), None);
+    let Some(start) = cloze_sentence.find("{{c1::") else {
+        return unchanged();
+    };
+    let prefix = &cloze_sentence[..start + 6];
+    let rest = &cloze_sentence[start + 6..];
+    let Some(end_rel) = rest.find("}}") else {
+        return unchanged();
+    };
+    let inside = &rest[..end_rel];
+    if inside.contains("::") {
+        return unchanged();
     }
 
-    cloze_sentence.to_string()
+    let hint = match policy {
+        HintPolicy::Model => model_hint
+            .map(str::trim)
+            .filter(|hint| !hint.is_empty())
+            .map(str::to_string),
+        HintPolicy::FirstLetter => inside.trim().chars().next().map(|c| format!("{c}...")),
+        HintPolicy::Length => Some(
+            inside
+                .trim()
+                .chars()
+                .map(|c| if c.is_alphanumeric() { '-' } else { c })
+                .collect(),
+        ),
+        HintPolicy::None => None,
+    };
+    let Some(hint) = hint else {
+        return unchanged();
+    };
+    let suffix = &rest[end_rel..];
+    (format!("{prefix}{inside}::{hint}{suffix}"), Some(hint))
 }
 
 #[derive(Debug, Serialize)]
@@ -366,7 +1779,82 @@ struct ChatCompletionRequest {
     messages: Vec<Message>,
     temperature: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
     response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ToolChoice>,
+}
+
+/// How a completion is asked to return its card: a JSON response format, or
+/// the arguments of a forced function call.
+enum RequestFormat {
+    Json(ResponseFormat),
+    Tool(Tool),
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: ToolFunction,
+}
+
+impl Tool {
+    /// The `create_card` function whose parameters are the card schema.
+    fn create_card(schema: &JsonSchemaFormat) -> Self {
+        Self {
+            kind: "function",
+            function: ToolFunction {
+                name: "create_card",
+                description: format!("Create the {} flashcard", schema.name),
+                parameters: schema.schema.clone(),
+                strict: schema.strict,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ToolFunction {
+    name: &'static str,
+    description: String,
+    parameters: serde_json::Value,
+    strict: bool,
+}
+
+/// `tool_choice` forcing a call to one named function.
+#[derive(Debug, Serialize)]
+struct ToolChoice {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: ToolChoiceFunction,
+}
+
+impl ToolChoice {
+    fn function(name: &'static str) -> Self {
+        Self {
+            kind: "function",
+            function: ToolChoiceFunction { name },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ToolChoiceFunction {
+    name: &'static str,
 }
 
 #[derive(Debug, Serialize)]
@@ -379,26 +1867,159 @@ struct Message {
 struct ResponseFormat {
     #[serde(rename = "type")]
     kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_schema: Option<JsonSchemaFormat>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct JsonSchemaFormat {
+    name: &'static str,
+    strict: bool,
+    schema: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct ImageRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+    n: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageResponse {
+    data: Vec<ImageData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageData {
+    #[serde(default)]
+    b64_json: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelListResponse {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct ChatCompletionResponse {
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+/// Token counts reported with a completion.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+struct Usage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+    #[serde(default)]
+    total_tokens: u64,
 }
 
 #[derive(Debug, Deserialize)]
 struct Choice {
     message: ChoiceMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ChoiceMessage {
-    content: String,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    refusal: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
 }
 
 #[derive(Debug, Deserialize)]
-struct HindiCardPayload {
-    word: String,
+struct ToolCall {
+    function: ToolCallFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCallFunction {
+    arguments: String,
+}
+
+/// One `data:` event of a streamed chat completion.
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: StreamDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StreamDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    refusal: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<StreamToolCall>,
+}
+
+/// Fragment of a streamed tool call; the arguments arrive piecewise.
+#[derive(Debug, Deserialize)]
+struct StreamToolCall {
+    #[serde(default)]
+    function: StreamFunction,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StreamFunction {
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum HindiCardPayload {
+    Multiple {
+        word: String,
+        examples: Vec<HindiExamplePayload>,
+    },
+    Single {
+        word: String,
+        hindi_sentence: String,
+        english_sentence: String,
+    },
+}
+
+impl HindiCardPayload {
+    fn word(&self) -> &str {
+        match self {
+            HindiCardPayload::Multiple { word, .. } | HindiCardPayload::Single { word, .. } => word,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HindiBatchPayload {
+    cards: Vec<HindiCardPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HindiExamplePayload {
     hindi_sentence: String,
     english_sentence: String,
 }
@@ -410,4 +2031,186 @@ struct EnglishClozePayload {
     translation: String,
     #[serde(default)]
     hint: Option<String>,
+    #[serde(default)]
+    ipa: Option<String>,
+    #[serde(default)]
+    synonyms: Option<Vec<String>>,
+    #[serde(default)]
+    antonyms: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnglishClozeBatchPayload {
+    cards: Vec<EnglishClozePayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnglishDefinitionPayload {
+    word: String,
+    definition: String,
+    #[serde(default)]
+    example: Option<String>,
+    #[serde(default)]
+    ipa: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockOpenAi, completion, refusal, test_config};
+
+    fn options(config: &Config) -> GenerationOptions {
+        GenerationOptions {
+            temperature: config.temperature,
+            difficulty: None,
+            examples: 1,
+            notes_per_word: 1,
+            include_ipa: false,
+            enrich: false,
+            hindi_sentence_words: config.hindi_sentence_words,
+            english_sentence_words: config.english_sentence_words,
+            avoid_sentences: Vec::new(),
+            phrase: false,
+        }
+    }
+
+    fn hindi_card_json(word: &str, sentence: &str) -> serde_json::Value {
+        json!({
+            "word": word,
+            "examples": [{"hindi_sentence": sentence, "english_sentence": "An example."}]
+        })
+    }
+
+    #[tokio::test]
+    async fn partial_misordered_batch_falls_back_for_missing_words() {
+        let batch = json!({
+            "cards": [
+                hindi_card_json("किताब", "यह किताब नई है।"),
+                hindi_card_json("घर", "मेरा घर बड़ा है।"),
+            ]
+        });
+        let single = hindi_card_json("पानी", "मुझे पानी चाहिए।");
+        let server = MockOpenAi::start(vec![
+            completion(&batch.to_string()),
+            completion(&single.to_string()),
+        ]);
+        let config = test_config(&server.url, "");
+        let client = OpenAiClient::new(&config).unwrap();
+        let words: Vec<WordEntry> = ["घर", "पानी", "किताब"]
+            .into_iter()
+            .map(|word| WordEntry::parse(word, None))
+            .collect();
+
+        let cards = client
+            .generate_hindi_cards(&words, &options(&config))
+            .await
+            .unwrap();
+
+        let generated: Vec<&str> = cards.iter().map(|card| card.word.as_str()).collect();
+        assert_eq!(generated, ["घर", "पानी", "किताब"]);
+        assert_eq!(cards[0].examples[0].hindi_sentence, "मेरा घर बड़ा है।");
+        assert_eq!(cards[2].examples[0].hindi_sentence, "यह किताब नई है।");
+        let prompts = server.prompts();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[1].ends_with("Target word: पानी"), "{}", prompts[1]);
+    }
+
+    #[tokio::test]
+    async fn refusal_is_retried_once_with_a_softened_prompt() {
+        let server = MockOpenAi::start(vec![
+            refusal("I can't help with that."),
+            refusal("Still no."),
+            completion("{}"),
+        ]);
+        let config = test_config(&server.url, "");
+        let client = OpenAiClient::new(&config).unwrap();
+
+        let err = client
+            .generate_hindi_card("घर", None, None, &options(&config))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(&err, AnkiCliError::Refused(reason) if reason == "Still no."),
+            "{err}"
+        );
+        let prompts = server.prompts();
+        assert_eq!(prompts.len(), 2);
+        assert!(!prompts[0].ends_with(SOFTENED_PROMPT_SUFFIX));
+        assert_eq!(
+            prompts[1],
+            format!("{}{SOFTENED_PROMPT_SUFFIX}", prompts[0])
+        );
+    }
+
+    #[test]
+    fn hindi_payload_with_wrong_example_count_is_a_parse_error() {
+        let payload: HindiCardPayload =
+            serde_json::from_value(hindi_card_json("घर", "मेरा घर बड़ा है।")).unwrap();
+
+        let err = hindi_card_from_payload("घर", payload, 2, false).unwrap_err();
+
+        assert!(matches!(err, AnkiCliError::LlmParse { .. }), "{err}");
+    }
+
+    #[test]
+    fn cloze_payload_with_blank_translation_is_a_parse_error() {
+        let payload: EnglishClozePayload = serde_json::from_value(json!({
+            "word": "serendipity",
+            "cloze_sentence": "Pure {{c1::serendipity}}.",
+            "translation": "  "
+        }))
+        .unwrap();
+
+        let err =
+            english_cloze_from_payload("serendipity", payload, HintPolicy::Model, false, false)
+                .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "OpenAI returned an empty translation for 'serendipity'"
+        );
+    }
+
+    #[test]
+    fn token_estimate_scales_with_sentences_and_respects_the_completion_cap() {
+        let config = test_config("http://127.0.0.1:9", "");
+        let client = OpenAiClient::new(&config).unwrap();
+        let one = options(&config);
+        let three = GenerationOptions {
+            examples: 3,
+            ..options(&config)
+        };
+
+        let (prompt, completion) = client.estimated_tokens_per_word(Language::Hindi, &one);
+        let (_, completion_three) = client.estimated_tokens_per_word(Language::Hindi, &three);
+        assert!(prompt > 0);
+        assert_eq!(completion_three, completion * 3);
+
+        let capped = test_config("http://127.0.0.1:9", "max_completion_tokens = 50");
+        let client = OpenAiClient::new(&capped).unwrap();
+        let (_, completion) = client.estimated_tokens_per_word(Language::Hindi, &three);
+        assert_eq!(completion, 50);
+    }
+
+    #[test]
+    fn hindi_inflections_match_but_short_stems_do_not_prefix_match() {
+        assert!(hindi_sentence_contains("वे लड़के खेल रहे हैं।", "लड़का"));
+        assert!(hindi_sentence_contains("वह खाना खाता है।", "खाना"));
+        assert!(!hindi_sentence_contains("यह खास दिन है।", "खाना"));
+        assert_eq!(hindi_stem("लड़कियाँ"), "लड़क");
+    }
+
+    #[test]
+    fn phrase_span_finds_inflected_three_and_four_word_idioms() {
+        let sentence = "After years of work, he finally kicked the bucket.";
+        let (start, end) = phrase_span(sentence, "kick the bucket").unwrap();
+        assert_eq!(&sentence[start..end], "kicked the bucket");
+
+        let sentence = "Don't worry — it's \"a piece of cake\" for her.";
+        let (start, end) = phrase_span(sentence, "a piece of cake").unwrap();
+        assert_eq!(&sentence[start..end], "a piece of cake");
+
+        assert_eq!(phrase_span("She is taking a nap.", "take it easy"), None);
+    }
 }
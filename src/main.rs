@@ -1,19 +1,21 @@
-mod anki;
-mod config;
-mod input;
-mod llm;
-mod workflows;
-
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand};
+use tokio::sync::OnceCell;
 use tracing::Level;
+use tracing_subscriber::filter::LevelFilter;
 
-use crate::anki::AnkiConnectClient;
-use crate::config::{Config, ConfigOverrides};
-use crate::llm::OpenAiClient;
-use crate::workflows::{RunContext, run_english_flow, run_hindi_flow};
+use anki_cli::bench::BenchConfig;
+use anki_cli::preview::HtmlPreview;
+use anki_cli::{
+    AnkiCliError, AnkiConnectClient, Config, ConfigOverrides, ConfigResolution, Difficulty,
+    DuplicateScope, EnglishMode, HindiDirection, InputFormat, Language, OpenAiClient, PartOfSpeech,
+    RunContext, bench, enrich, input, lint, run_doctor, run_english_flow, run_hindi_flow,
+    run_init_wizard, run_interactive_session, run_regenerate, suspend_progress,
+};
 
 #[derive(Debug, Parser)]
 #[command(
@@ -29,6 +31,10 @@ struct Cli {
     #[arg(long)]
     config: Option<PathBuf>,
 
+    /// Load KEY=VALUE environment variables from this dotenv file (variables already set win)
+    #[arg(long = "env-file", value_name = "PATH")]
+    env_file: Option<PathBuf>,
+
     /// Override the OpenAI model used for generation
     #[arg(long)]
     model: Option<String>,
@@ -45,25 +51,233 @@ struct Cli {
     #[arg(long = "english-deck")]
     english_deck: Option<String>,
 
+    /// Use the Hindi/English decks from a named `[presets.<name>]` config table
+    #[arg(long)]
+    preset: Option<String>,
+
     /// Optional temperature override for the language model
     #[arg(long)]
     temperature: Option<f32>,
 
+    /// Nucleus sampling probability mass (0.0 exclusive to 1.0); best not combined with --temperature
+    #[arg(long = "top-p")]
+    top_p: Option<f32>,
+
+    /// OpenAI frequency_penalty (-2.0 to 2.0); positive values discourage repeated tokens
+    #[arg(long = "frequency-penalty", allow_negative_numbers = true)]
+    frequency_penalty: Option<f32>,
+
+    /// OpenAI presence_penalty (-2.0 to 2.0); positive values encourage new topics
+    #[arg(long = "presence-penalty", allow_negative_numbers = true)]
+    presence_penalty: Option<f32>,
+
+    /// CEFR level used to constrain vocabulary and sentence complexity
+    #[arg(long, value_enum)]
+    difficulty: Option<Difficulty>,
+
+    /// Seed passed to OpenAI for best-effort reproducible generations
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Language for the explanation on English cloze cards (default English)
+    #[arg(long = "explanation-language")]
+    explanation_language: Option<String>,
+
+    /// Tone every generated sentence should take, e.g. "Write in a formal business register."
+    #[arg(long)]
+    style: Option<String>,
+
+    /// Prompt version recorded as a `prompt_<version>` tag (default: the crate version)
+    #[arg(long = "prompt-version")]
+    prompt_version: Option<String>,
+
+    /// Timeout in seconds for each OpenAI request
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    /// Timeout in seconds for establishing the OpenAI connection
+    #[arg(long = "connect-timeout")]
+    connect_timeout: Option<u64>,
+
+    /// Maximum OpenAI requests per minute; 429 responses are retried after a back-off
+    #[arg(long)]
+    rpm: Option<u32>,
+
+    /// Stream OpenAI responses; with --verbose the tokens are echoed as they arrive
+    #[arg(long)]
+    stream: bool,
+
+    /// Log the full system and user prompt of every OpenAI request
+    #[arg(long = "print-prompt")]
+    print_prompt: bool,
+
+    /// Fail a word whose generated sentence lacks the word or the cloze markup instead of warning
+    #[arg(long, visible_alias = "strict-json")]
+    strict: bool,
+
+    /// Append every OpenAI request and raw response to this JSONL file
+    #[arg(long = "log-llm", value_name = "PATH")]
+    log_llm: Option<PathBuf>,
+
+    /// Maximum tokens OpenAI may generate per response
+    #[arg(long = "max-completion-tokens")]
+    max_completion_tokens: Option<u32>,
+
     /// Additional tags to attach to generated notes
     #[arg(long, value_delimiter = ',')]
     tags: Vec<String>,
 
+    /// Prefix for the per-word tag added to every note (default `word_`)
+    #[arg(long = "tag-prefix")]
+    tag_prefix: Option<String>,
+
+    /// Number of example sentences to put on each Hindi card
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=5))]
+    examples: u8,
+
+    /// Number of cloze notes to create per English word, each using it in a different context
+    #[arg(long = "notes-per-word", default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=5))]
+    notes_per_word: u8,
+
+    /// Regenerate a sentence once when its word overlap with an earlier one in the run reaches this fraction (0-1)
+    #[arg(long = "uniqueness-threshold", value_parser = parse_fraction)]
+    uniqueness_threshold: Option<f32>,
+
+    /// Card style for English words without an explicit `@cloze`/`@basic` marker
+    #[arg(long = "english-mode", value_enum, default_value_t = EnglishMode::Cloze)]
+    english_mode: EnglishMode,
+
+    /// Add synonyms and antonyms to the back of English cloze cards
+    #[arg(long)]
+    enrich: bool,
+
+    /// Illustrate English cloze cards with an image from the OpenAI images endpoint
+    #[arg(long)]
+    image: bool,
+
+    /// Also add a Basic note per English cloze: word and meaning on the front, the full sentence on the back
+    #[arg(long = "with-production-card")]
+    with_production_card: bool,
+
+    /// Treat the words as multi-word phrases or idioms: one cloze spans the whole expression
+    #[arg(long)]
+    phrase: bool,
+
+    /// Number of Hindi words to generate per OpenAI request
+    #[arg(long = "batch-size", default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=20))]
+    batch_size: u8,
+
+    /// Let Anki add notes whose first field duplicates an existing note
+    #[arg(long)]
+    allow_duplicate: bool,
+
+    /// Where Anki looks for duplicate notes: the target deck or the whole collection
+    #[arg(long = "duplicate-scope", value_enum)]
+    duplicate_scope: Option<DuplicateScope>,
+
+    /// Skip a word whose generation takes longer than this many seconds
+    #[arg(long = "word-timeout", value_parser = clap::value_parser!(u64).range(1..))]
+    word_timeout: Option<u64>,
+
+    /// Allow runs with more words than `max_words_per_run`
+    #[arg(long = "yes-large")]
+    yes_large: bool,
+
+    /// Fail instead of reusing an existing deck whose name differs only in case
+    #[arg(long = "strict-deck")]
+    strict_deck: bool,
+
+    /// Only create the Hindi→English note, skipping the reverse direction
+    #[arg(long)]
+    no_reverse: bool,
+
+    /// Which Hindi notes to create: both directions, Hindi front only, or English front only
+    #[arg(long = "hindi-direction", value_enum, conflicts_with = "no_reverse")]
+    hindi_direction: Option<HindiDirection>,
+
+    /// Sync the Anki collection with AnkiWeb after notes are added
+    #[arg(long)]
+    sync: bool,
+
+    /// Don't remember the deck used by this run in the config file
+    #[arg(long = "no-save-deck")]
+    no_save_deck: bool,
+
+    /// Route words to decks by their `#tag` using the config's tag_deck_map
+    #[arg(long)]
+    deck_from_tag: bool,
+
+    /// Open the Anki browser on the newly added notes after a successful run
+    #[arg(long)]
+    browse: bool,
+
+    /// Process common words first, using the config's frequency_list
+    #[arg(long)]
+    sort_by_frequency: bool,
+
+    /// Skip words listed in this file of already-known words (repeatable)
+    #[arg(long = "dedupe-against", value_name = "FILE")]
+    dedupe_against: Vec<PathBuf>,
+
+    /// Only process the first N words (after dedup and sorting)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    limit: Option<u32>,
+
+    /// Re-read added notes from Anki and warn about empty fields or clozes without cards
+    #[arg(long)]
+    verify: bool,
+
+    /// Generate cards even for words that already have notes anywhere in the collection
+    #[arg(long)]
+    allow_existing: bool,
+
+    /// Skip words already in Anki and report on stderr how many of the input are new
+    #[arg(long = "only-new", conflicts_with = "allow_existing")]
+    only_new: bool,
+
+    /// For words that already have notes, append a new example to this field of those notes
+    #[arg(
+        long = "append-to-field",
+        value_name = "FIELD",
+        conflicts_with = "allow_existing"
+    )]
+    append_to_field: Option<String>,
+
+    /// Print the IDs of the created notes to stdout as a JSON array
+    #[arg(long)]
+    output_note_ids: bool,
+
+    /// Skip words that an interrupted run with the same input already added
+    #[arg(long)]
+    resume: bool,
+
     /// Preview the generated notes without sending them to Anki
     #[arg(long)]
     dry_run: bool,
 
+    /// With --dry-run, also write the cards as Anki would render them to this HTML file
+    #[arg(long = "preview-html", value_name = "FILE", requires = "dry_run")]
+    preview_html: Option<PathBuf>,
+
     /// Automatically send generated notes to Anki without confirmation
-    #[arg(long)]
+    #[arg(long, visible_alias = "yes")]
     auto_approve: bool,
 
+    /// Generate every card first, then review them all before anything is sent
+    #[arg(long, conflicts_with = "auto_approve")]
+    review_all_first: bool,
+
     /// Enable verbose logging
     #[arg(long)]
     verbose: bool,
+
+    /// Only log errors and skip dry-run card previews (e.g. for cron jobs)
+    #[arg(long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log one JSON object per line instead of human-readable text (e.g. for CI or log collectors)
+    #[arg(long = "json-logs")]
+    json_logs: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -74,21 +288,60 @@ enum Command {
     English(LanguageArgs),
     /// Run an interactive session for adding cards
     Interactive(InteractiveArgs),
+    /// Interactively create a config file with API key, model, decks and AnkiConnect URL
+    Init(InitArgs),
+    /// Inspect the effective configuration
+    Config(ConfigArgs),
+    /// Check connectivity to OpenAI and AnkiConnect and verify decks and note types
+    Doctor,
+    /// List the model ids offered by the OpenAI-compatible endpoint
+    ListModels,
+    /// Regenerate every note carrying a tag (e.g. `prompt_v1`) with the current prompt and retag it
+    #[command(visible_alias = "regenerate")]
+    UpdatePromptAndRegenerate(RegenerateArgs),
+    /// Check a word file for blank lines, duplicates, sentences and mixed scripts, offline
+    Lint(LintArgs),
+    /// Compare models and temperatures on a word list: latency, token usage and the cards, without Anki
+    Bench(BenchArgs),
 }
 
 #[derive(Debug, Args)]
 struct LanguageArgs {
-    /// Optional path to a file containing words (one per line)
-    #[arg(short, long)]
-    input: Option<PathBuf>,
+    /// File of words (one per line), or a glob such as `words/*.txt`; repeatable
+    #[arg(short, long, visible_alias = "input-glob")]
+    input: Vec<String>,
+
+    /// How to parse the --input file
+    #[arg(long = "input-format", value_enum, default_value_t = InputFormat::Auto)]
+    input_format: InputFormat,
 
     /// Optional override for the deck name
     #[arg(long)]
     deck: Option<String>,
 
-    /// Words supplied directly via CLI arguments
+    /// Part of speech applied to words without an explicit `word:pos` suffix
+    #[arg(long, value_enum)]
+    pos: Option<PartOfSpeech>,
+
+    /// Words supplied directly via CLI arguments (optionally as `word:pos #tag`)
     #[arg(name = "WORD", required = false)]
     words: Vec<String>,
+
+    /// Read words from the clipboard, split on commas, semicolons and newlines
+    #[arg(long = "from-clipboard", conflicts_with_all = ["input", "WORD"])]
+    from_clipboard: bool,
+
+    /// Rerun only the words in a failure list from an earlier run (same format as --input)
+    #[arg(
+        long = "retry-failed",
+        value_name = "FILE",
+        conflicts_with_all = ["input", "WORD", "from_clipboard"]
+    )]
+    retry_failed: Option<PathBuf>,
+
+    /// Intended sense of a word, as `word=meaning` (repeatable)
+    #[arg(long, value_name = "WORD=MEANING", value_parser = parse_context)]
+    context: Vec<(String, String)>,
 }
 
 #[derive(Debug, Args)]
@@ -96,43 +349,162 @@ struct InteractiveArgs {
     /// Optional default language to preselect in the interactive prompt
     #[arg(long)]
     language: Option<Language>,
+
+    /// Route each word to Hindi or English by its script instead of asking
+    #[arg(long, conflicts_with = "language")]
+    auto_detect: bool,
+}
+
+#[derive(Debug, Args)]
+struct RegenerateArgs {
+    /// Tag of the notes to regenerate, replaced by the current `prompt_<version>` tag
+    tag: String,
+}
+
+#[derive(Debug, Args)]
+struct LintArgs {
+    /// Word file to check
+    file: PathBuf,
+
+    /// How to parse the file
+    #[arg(long = "input-format", value_enum, default_value_t = InputFormat::Auto)]
+    input_format: InputFormat,
+}
+
+#[derive(Debug, Args)]
+struct BenchArgs {
+    /// Language of the words
+    #[arg(value_enum)]
+    language: Language,
+
+    /// Word file to generate cards for
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Models to compare, comma-separated (default: the configured model)
+    #[arg(long, value_delimiter = ',')]
+    models: Vec<String>,
+
+    /// Temperatures to compare, comma-separated (default: the configured temperature)
+    #[arg(long, value_delimiter = ',')]
+    temperatures: Vec<f32>,
+
+    /// Directory that receives one file of generated cards per model and temperature
+    #[arg(long = "out-dir", default_value = "bench")]
+    out_dir: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct InitArgs {
+    /// Overwrite an existing config file
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommand,
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum Language {
-    Hindi,
-    English,
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print the resolved configuration and where each value came from
+    Show,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // Neither file overrides variables that are already set, so the
+    // explicit --env-file takes precedence over a .env in the cwd.
+    if let Some(path) = &cli.env_file {
+        dotenvy::from_path(path)
+            .with_context(|| format!("failed to load env file {}", path.display()))?;
+    }
     // Load .env file if it exists (ignore errors if it doesn't)
     let _ = dotenvy::dotenv();
 
-    let cli = Cli::parse();
+    init_tracing(cli.verbose, cli.quiet, cli.json_logs)?;
 
-    init_tracing(cli.verbose)?;
+    if let Command::Init(args) = &cli.command {
+        return run_init_wizard(cli.config.clone(), args.force);
+    }
+    if let Command::Lint(args) = &cli.command {
+        return lint::run_lint(&args.file, args.input_format, cli.phrase);
+    }
 
     let overrides = ConfigOverrides {
         model: cli.model.clone(),
         anki_url: cli.anki_url.clone(),
         hindi_deck: cli.hindi_deck.clone(),
         english_deck: cli.english_deck.clone(),
+        preset: cli.preset.clone(),
         temperature: cli.temperature,
+        top_p: cli.top_p,
+        frequency_penalty: cli.frequency_penalty,
+        presence_penalty: cli.presence_penalty,
         extra_tags: if cli.tags.is_empty() {
             None
         } else {
             Some(cli.tags.clone())
         },
+        word_tag_prefix: cli.tag_prefix.clone(),
+        difficulty: cli.difficulty,
+        request_timeout_secs: cli.timeout,
+        connect_timeout_secs: cli.connect_timeout,
+        requests_per_minute: cli.rpm,
+        max_completion_tokens: cli.max_completion_tokens,
+        hindi_direction: cli
+            .hindi_direction
+            .or(cli.no_reverse.then_some(HindiDirection::Forward)),
+        allow_duplicate: cli.allow_duplicate.then_some(true),
+        duplicate_scope: cli.duplicate_scope,
+        auto_sync: cli.sync.then_some(true),
+        auto_save_deck: cli.no_save_deck.then_some(false),
+        stream: cli.stream.then_some(true),
+        print_prompt: cli.print_prompt.then_some(true),
+        strict: cli.strict.then_some(true),
+        llm_log: cli.log_llm.clone(),
+        seed: cli.seed,
+        explanation_language: cli.explanation_language.clone(),
+        style: cli.style.clone(),
+        prompt_version: cli.prompt_version.clone(),
     };
 
-    let config = Config::load(cli.config.clone(), overrides)?;
-    let anki_client = AnkiConnectClient::new(config.anki_connect_url.clone());
-    let llm_client = OpenAiClient::new(
-        config.openai_api_key.clone(),
-        config.openai_model.clone(),
-        config.openai_base_url.clone(),
-    )?;
+    if let Command::Config(args) = &cli.command {
+        let resolution = Config::resolve_for_display(cli.config.clone(), overrides)?;
+        match args.command {
+            ConfigCommand::Show => print_config(&resolution),
+        }
+        return Ok(());
+    }
+
+    let resolution = Config::resolve(cli.config.clone(), overrides)?;
+
+    let config = resolution.config;
+    let frequency_ranks = if cli.sort_by_frequency {
+        let path = config
+            .frequency_list
+            .as_deref()
+            .context("--sort-by-frequency requires frequency_list to be set in the config file")?;
+        Some(input::read_frequency_list(path)?)
+    } else {
+        None
+    };
+    let known_words = if cli.dedupe_against.is_empty() {
+        None
+    } else {
+        Some(input::read_known_words(&cli.dedupe_against)?)
+    };
+    let html_preview = cli.preview_html.clone().map(HtmlPreview::new);
+    let enrichers = enrich::from_names(&config.enrichers)?;
+    let anki_client = AnkiConnectClient::new(&config)?;
+    let llm_client = OpenAiClient::new(&config)?;
+    if let Some(style) = &config.style {
+        tracing::info!("Generating with style: {style}");
+    }
 
     let run_ctx = RunContext {
         anki: &anki_client,
@@ -140,27 +512,206 @@ async fn main() -> Result<()> {
         config: &config,
         dry_run: cli.dry_run,
         auto_approve: cli.auto_approve,
+        examples: usize::from(cli.examples),
+        notes_per_word: usize::from(cli.notes_per_word),
+        uniqueness_threshold: cli.uniqueness_threshold,
+        strict_deck: cli.strict_deck,
+        yes_large: cli.yes_large,
+        word_timeout: cli.word_timeout.map(Duration::from_secs),
+        show_progress: !cli.verbose
+            && !cli.quiet
+            && !cli.json_logs
+            && std::io::stdout().is_terminal(),
+        deck_from_tag: cli.deck_from_tag,
+        browse: cli.browse,
+        english_mode: cli.english_mode,
+        frequency_ranks: frequency_ranks.as_ref(),
+        known_words: known_words.as_ref(),
+        html_preview: html_preview.as_ref(),
+        enrichers: &enrichers,
+        cloze_text_field: OnceCell::new(),
+        limit: cli.limit.map(|limit| limit as usize),
+        batch_size: usize::from(cli.batch_size),
+        verify: cli.verify,
+        allow_existing: cli.allow_existing,
+        only_new: cli.only_new,
+        append_to_field: cli.append_to_field.as_deref(),
+        output_note_ids: cli.output_note_ids,
+        quiet: cli.quiet,
+        enrich: cli.enrich,
+        image: cli.image,
+        production_card: cli.with_production_card,
+        phrase: cli.phrase,
+        resume: cli.resume,
+        review_all_first: cli.review_all_first,
     };
 
     match cli.command {
         Command::Hindi(args) => run_language(Language::Hindi, args, &run_ctx).await?,
         Command::English(args) => run_language(Language::English, args, &run_ctx).await?,
         Command::Interactive(args) => run_interactive(args, &run_ctx).await?,
+        Command::Doctor => run_doctor(&run_ctx).await?,
+        Command::ListModels => list_models(&llm_client, &config).await?,
+        Command::UpdatePromptAndRegenerate(args) => run_regenerate(&args.tag, &run_ctx).await?,
+        Command::Bench(args) => run_bench(args, &run_ctx).await?,
+        Command::Init(_) | Command::Config(_) | Command::Lint(_) => {
+            unreachable!("handled before constructing clients")
+        }
     }
 
     Ok(())
 }
 
-fn init_tracing(verbose: bool) -> Result<()> {
-    let level = if verbose { Level::DEBUG } else { Level::INFO };
-    let subscriber = tracing_subscriber::fmt()
-        .with_max_level(level)
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .with_target(false)
-        .finish();
+fn parse_context(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((word, meaning)) if !word.trim().is_empty() && !meaning.trim().is_empty() => {
+            Ok((word.trim().to_string(), meaning.trim().to_string()))
+        }
+        _ => Err(format!("'{value}' is not in the form word=meaning")),
+    }
+}
 
-    tracing::subscriber::set_global_default(subscriber)
-        .map_err(|err| anyhow::anyhow!("Failed to set tracing subscriber: {err}"))
+fn parse_fraction(value: &str) -> Result<f32, String> {
+    let fraction: f32 = value
+        .parse()
+        .map_err(|_| format!("'{value}' is not a number"))?;
+    if fraction > 0.0 && fraction <= 1.0 {
+        Ok(fraction)
+    } else {
+        Err(format!("{value} must be greater than 0 and at most 1"))
+    }
+}
+
+/// Log writer for the tracing subscriber: writes to stderr, so stdout
+/// carries only results such as `--output-note-ids`, and hides the progress
+/// bar while each line is printed.
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        suspend_progress(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+fn init_tracing(verbose: bool, quiet: bool, json: bool) -> Result<()> {
+    let level = if verbose {
+        Level::DEBUG
+    } else if quiet {
+        Level::ERROR
+    } else {
+        Level::INFO
+    };
+    // RUST_LOG still takes precedence; without it the flags pick the level.
+    let filter = tracing_subscriber::EnvFilter::builder()
+        .with_default_directive(LevelFilter::from_level(level).into())
+        .from_env_lossy();
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(|| LogWriter)
+        .with_target(false);
+    // JSON lines keep event fields at the top level and the word/deck fields
+    // of the enclosing spans under `span` and `spans`.
+    let result = if json {
+        tracing::subscriber::set_global_default(
+            builder
+                .json()
+                .flatten_event(true)
+                .with_current_span(true)
+                .with_span_list(true)
+                .finish(),
+        )
+    } else {
+        tracing::subscriber::set_global_default(builder.finish())
+    };
+    result.map_err(|err| anyhow::anyhow!("Failed to set tracing subscriber: {err}"))
+}
+
+fn print_config(resolution: &ConfigResolution) {
+    match resolution.config.config_path() {
+        Some(path) if path.exists() => println!("Config file: {}", path.display()),
+        Some(path) => println!("Config file: {} (not created yet)", path.display()),
+        None => println!("Config file: <none>"),
+    }
+
+    for (key, value, source) in resolution.entries() {
+        println!("  {key:<24} = {value}  [{}]", source.as_str());
+    }
+}
+
+async fn run_bench(args: BenchArgs, run_ctx: &RunContext<'_>) -> Result<()> {
+    let words = input::read_words_from_file(&args.input, InputFormat::Auto, None)
+        .with_context(|| format!("failed to read words from file {:?}", args.input))?;
+    if words.is_empty() {
+        anyhow::bail!("{} contains no words", args.input.display());
+    }
+    let with_temperature: Vec<&str> = words
+        .iter()
+        .filter(|entry| entry.temperature.is_some())
+        .map(|entry| entry.word.as_str())
+        .collect();
+    if !with_temperature.is_empty() {
+        tracing::warn!(
+            "Ignoring the per-word temperature of {}; bench compares the --temperatures values",
+            with_temperature.join(", ")
+        );
+    }
+
+    let models = if args.models.is_empty() {
+        vec![run_ctx.config.openai_model.clone()]
+    } else {
+        args.models
+    };
+    let temperatures = if args.temperatures.is_empty() {
+        vec![run_ctx.config.temperature]
+    } else {
+        args.temperatures
+    };
+    if let Some(temperature) = temperatures
+        .iter()
+        .find(|temperature| !(0.0..=2.0).contains(*temperature))
+    {
+        anyhow::bail!("--temperatures must be between 0.0 and 2.0, got {temperature}");
+    }
+
+    let configs: Vec<BenchConfig> = models
+        .iter()
+        .flat_map(|model| {
+            temperatures.iter().map(|&temperature| BenchConfig {
+                model: model.clone(),
+                temperature,
+            })
+        })
+        .collect();
+    bench::run_bench(args.language, &words, &configs, &args.out_dir, run_ctx).await
+}
+
+async fn list_models(llm: &OpenAiClient, config: &Config) -> Result<()> {
+    let mut models = match llm.list_models().await {
+        Ok(models) => models,
+        Err(AnkiCliError::Http { status, .. }) if matches!(status.as_u16(), 404 | 405 | 501) => {
+            anyhow::bail!(
+                "{} does not implement GET /models (HTTP {status}); check the server's documentation for model names",
+                config.openai_base_url
+            );
+        }
+        Err(err) => return Err(err).context("failed to list models"),
+    };
+
+    if models.is_empty() {
+        println!("The endpoint listed no models.");
+        return Ok(());
+    }
+
+    models.sort();
+    for model in models {
+        let marker = if model == llm.model() { "*" } else { " " };
+        println!("{marker} {model}");
+    }
+    Ok(())
 }
 
 async fn run_language(
@@ -168,17 +719,38 @@ async fn run_language(
     args: LanguageArgs,
     run_ctx: &RunContext<'_>,
 ) -> Result<()> {
-    let mut words = args.words;
+    let mut words = input::parse_word_entries(args.words, args.pos);
+    if args.from_clipboard {
+        words = input::parse_word_entries(input::read_clipboard_words()?, args.pos);
+        tracing::debug!("Read {} word(s) from the clipboard", words.len());
+    }
+    if let Some(path) = &args.retry_failed {
+        words = read_failed_words(language, path, args.input_format, args.pos)?;
+    }
 
-    if let Some(path) = args.input {
-        let mut from_file = input::read_words_from_file(&path)
+    for path in input::expand_input_paths(&args.input)? {
+        let mut from_file = input::read_words_from_file(&path, args.input_format, args.pos)
             .with_context(|| format!("failed to read words from file {path:?}"))?;
+        tracing::debug!(
+            "{} contributed {} word(s): {}",
+            path.display(),
+            from_file.len(),
+            from_file
+                .iter()
+                .map(|entry| entry.word.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
         words.append(&mut from_file);
     }
 
     if words.is_empty() {
-        anyhow::bail!("no words provided; specify words via CLI arguments or --input file");
+        anyhow::bail!(
+            "no words provided; specify words via CLI arguments, --input file or --from-clipboard"
+        );
     }
+    input::apply_contexts(&mut words, &args.context);
+    input::check_temperatures(&words)?;
 
     let deck_override = args.deck;
 
@@ -190,6 +762,36 @@ async fn run_language(
     Ok(())
 }
 
+/// Words of `language` listed in a failure file. Words in the other
+/// language's script are left out with a warning, since they belong to the
+/// other subcommand.
+fn read_failed_words(
+    language: Language,
+    path: &Path,
+    format: InputFormat,
+    pos: Option<PartOfSpeech>,
+) -> Result<Vec<input::WordEntry>> {
+    let entries = input::read_words_from_file(path, format, pos)
+        .with_context(|| format!("failed to read failed words from {}", path.display()))?;
+    let (words, other): (Vec<_>, Vec<_>) = entries.into_iter().partition(|entry| {
+        input::detect_language(&entry.word).is_none_or(|detected| detected == language)
+    });
+    if !other.is_empty() {
+        let listed: Vec<&str> = other.iter().map(|entry| entry.word.as_str()).collect();
+        tracing::warn!(
+            "Skipping {} word(s) in another language: {}",
+            other.len(),
+            listed.join(", ")
+        );
+    }
+    tracing::info!(
+        "Retrying {} failed word(s) from {}",
+        words.len(),
+        path.display()
+    );
+    Ok(words)
+}
+
 async fn run_interactive(args: InteractiveArgs, run_ctx: &RunContext<'_>) -> Result<()> {
-    workflows::run_interactive_session(args.language, run_ctx).await
+    run_interactive_session(args.language, args.auto_detect, run_ctx).await
 }
@@ -0,0 +1,302 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::{Context, Result};
+
+use crate::anki::{AnkiClient, CardTemplate, Note};
+
+/// Card templates and CSS of one note type.
+struct ModelLayout {
+    templates: BTreeMap<String, CardTemplate>,
+    css: String,
+}
+
+/// Dry-run notes rendered through their note type's own card templates and
+/// styling, written to a standalone HTML page for `--preview-html`.
+pub struct HtmlPreview {
+    path: PathBuf,
+    models: Mutex<BTreeMap<String, ModelLayout>>,
+    sections: Mutex<Vec<String>>,
+}
+
+impl HtmlPreview {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            models: Mutex::new(BTreeMap::new()),
+            sections: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Render every card the notes for `word` would produce and add them to
+    /// the page. Templates and styling are fetched once per note type.
+    pub async fn add_notes(&self, anki: &dyn AnkiClient, word: &str, notes: &[Note]) -> Result<()> {
+        for note in notes {
+            self.load_model(anki, &note.model_name).await?;
+            let models = lock(&self.models);
+            let layout = &models[&note.model_name];
+
+            let mut section = format!(
+                "<section>\n<h2>{} <small>{} &middot; {}</small></h2>\n",
+                escape(word),
+                escape(&note.deck_name),
+                escape(&note.model_name)
+            );
+            let mut cards = 0;
+            for (name, template) in &layout.templates {
+                let ordinals = if template.front.contains("{{cloze:") {
+                    cloze_ordinals(note).into_iter().map(Some).collect()
+                } else {
+                    vec![None]
+                };
+                for ordinal in ordinals {
+                    let front = render(&template.front, note, ordinal, None);
+                    // Anki generates no card when the question side is empty.
+                    if strip_html(&front).trim().is_empty() {
+                        continue;
+                    }
+                    let back = render(&template.back, note, ordinal, Some(&front));
+                    let title = match ordinal {
+                        Some(ordinal) => format!("{name} {ordinal}"),
+                        None => name.clone(),
+                    };
+                    section.push_str(&format!(
+                        "<h3>{}</h3>\n<div class=\"sides\">\n{}\n{}\n</div>\n",
+                        escape(&title),
+                        card_frame(&front, &layout.css),
+                        card_frame(&back, &layout.css)
+                    ));
+                    cards += 1;
+                }
+            }
+            if cards == 0 {
+                section.push_str("<p>This note would not produce any cards.</p>\n");
+            }
+            section.push_str("</section>\n");
+            lock(&self.sections).push(section);
+        }
+        Ok(())
+    }
+
+    /// Write the page with every note added so far.
+    pub fn write(&self) -> Result<()> {
+        let sections = lock(&self.sections);
+        let body = if sections.is_empty() {
+            "<p>No cards were generated.</p>\n".to_string()
+        } else {
+            sections.concat()
+        };
+        let page = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>anki-cli card preview</title>\n<style>\nbody {{ font-family: sans-serif; margin: 2em; }}\n.sides {{ display: flex; gap: 1em; }}\niframe {{ flex: 1; height: 240px; border: 1px solid #ccc; }}\n</style>\n</head>\n<body>\n<h1>Card preview</h1>\n{body}</body>\n</html>\n"
+        );
+        fs::write(&self.path, page)
+            .with_context(|| format!("failed to write card preview to {}", self.path.display()))?;
+        tracing::info!(
+            "Wrote a preview of {} note(s) to {}",
+            sections.len(),
+            self.path.display()
+        );
+        Ok(())
+    }
+
+    async fn load_model(&self, anki: &dyn AnkiClient, model: &str) -> Result<()> {
+        if lock(&self.models).contains_key(model) {
+            return Ok(());
+        }
+        let templates = anki
+            .model_templates(model)
+            .await
+            .with_context(|| format!("failed to read the card templates of note type '{model}'"))?;
+        let css = anki
+            .model_styling(model)
+            .await
+            .with_context(|| format!("failed to read the styling of note type '{model}'"))?;
+        lock(&self.models).insert(model.to_string(), ModelLayout { templates, css });
+        Ok(())
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// One side of a card in its own frame, so each note type's CSS applies
+/// only to its own cards.
+fn card_frame(html: &str, css: &str) -> String {
+    let document = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><style>{css}</style></head><body class=\"card\">{html}</body></html>"
+    );
+    format!("<iframe srcdoc=\"{}\"></iframe>", escape(&document))
+}
+
+/// Fill a card template the way Anki does for the common cases: `{{Field}}`,
+/// `{{FrontSide}}`, `{{Tags}}`, `{{Deck}}`, `{{#Field}}`/`{{^Field}}`
+/// sections and the `cloze`, `text`, `hint` and `type` filters. `front_side`
+/// is the rendered question when rendering the answer.
+fn render(template: &str, note: &Note, ordinal: Option<u32>, front_side: Option<&str>) -> String {
+    let template = render_sections(template, &note.fields);
+    let mut out = String::new();
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        out.push_str(&render_token(
+            after[..end].trim(),
+            note,
+            ordinal,
+            front_side,
+        ));
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn render_token(
+    token: &str,
+    note: &Note,
+    ordinal: Option<u32>,
+    front_side: Option<&str>,
+) -> String {
+    match token {
+        "FrontSide" => return front_side.unwrap_or_default().to_string(),
+        "Tags" => return note.tags.join(" "),
+        "Deck" => return note.deck_name.clone(),
+        "Type" => return note.model_name.clone(),
+        _ => {}
+    }
+
+    let mut parts: Vec<&str> = token.split(':').collect();
+    let name = parts.pop().unwrap_or_default().trim();
+    let mut value = note.fields.get(name).cloned().unwrap_or_default();
+    // Filters apply from the one nearest the field name outwards.
+    for filter in parts.iter().rev() {
+        value = match filter.trim() {
+            "cloze" => render_cloze(&value, ordinal.unwrap_or(1), front_side.is_some()),
+            "text" => strip_html(&value),
+            "hint" if !value.is_empty() => {
+                format!("<details><summary>Hint</summary>{value}</details>")
+            }
+            "type" => String::new(),
+            _ => value,
+        };
+    }
+    value
+}
+
+/// Resolve `{{#Field}}...{{/Field}}` (kept when the field has content) and
+/// `{{^Field}}...{{/Field}}` (kept when it is empty).
+fn render_sections(template: &str, fields: &BTreeMap<String, String>) -> String {
+    let mut out = template.to_string();
+    loop {
+        let Some(start) = out.find("{{#").or_else(|| out.find("{{^")) else {
+            return out;
+        };
+        let inverted = out[start..].starts_with("{{^");
+        let Some(name_end) = out[start..].find("}}").map(|end| start + end) else {
+            return out;
+        };
+        let name = out[start + 3..name_end].trim().to_string();
+        let close = format!("{{{{/{name}}}}}");
+        let Some(close_start) = out[name_end..].find(&close).map(|idx| name_end + idx) else {
+            return out;
+        };
+
+        let filled = fields
+            .get(&name)
+            .is_some_and(|value| !strip_html(value).trim().is_empty());
+        let inner = if filled != inverted {
+            out[name_end + 2..close_start].to_string()
+        } else {
+            String::new()
+        };
+        out.replace_range(start..close_start + close.len(), &inner);
+    }
+}
+
+/// Show cloze `ordinal` as `[...]` (or `[hint]`) on the question side and
+/// highlighted on the answer side; every other cloze shows its answer.
+fn render_cloze(text: &str, ordinal: u32, answer: bool) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{c") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 3..];
+        let digits = after.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 || !after[digits..].starts_with("::") {
+            out.push_str("{{c");
+            rest = after;
+            continue;
+        }
+        let body_start = digits + 2;
+        let Some(end) = after[body_start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let body = &after[body_start..body_start + end];
+        let (text, hint) = match body.split_once("::") {
+            Some((text, hint)) => (text, Some(hint)),
+            None => (body, None),
+        };
+        if after[..digits].parse() == Ok(ordinal) {
+            let shown = if answer {
+                text.to_string()
+            } else {
+                format!("[{}]", hint.unwrap_or("..."))
+            };
+            out.push_str(&format!("<span class=\"cloze\">{shown}</span>"));
+        } else {
+            out.push_str(text);
+        }
+        rest = &after[body_start + end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Cloze numbers used anywhere in the note; each becomes one card.
+fn cloze_ordinals(note: &Note) -> BTreeSet<u32> {
+    let mut ordinals = BTreeSet::new();
+    for value in note.fields.values() {
+        for (idx, _) in value.match_indices("{{c") {
+            let after = &value[idx + 3..];
+            let digits = after.chars().take_while(char::is_ascii_digit).count();
+            if digits > 0
+                && after[digits..].starts_with("::")
+                && let Ok(ordinal) = after[..digits].parse()
+            {
+                ordinals.insert(ordinal);
+            }
+        }
+    }
+    ordinals
+}
+
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
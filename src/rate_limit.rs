@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+/// Token bucket allowing `requests_per_minute` requests, refilled continuously
+/// and holding at most one minute's worth of tokens.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    capacity: f64,
+    per_second: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    updated: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(requests_per_minute: u32) -> Self {
+        let capacity = f64::from(requests_per_minute.max(1));
+        Self {
+            capacity,
+            per_second: capacity / 60.0,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                updated: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Wait until a request may be sent and take a token for it.
+    pub(crate) async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                let now = Instant::now();
+                match state.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        let elapsed = now.duration_since(state.updated).as_secs_f64();
+                        state.tokens =
+                            (state.tokens + elapsed * self.per_second).min(self.capacity);
+                        state.updated = now;
+                        if state.tokens >= 1.0 {
+                            state.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::from_secs_f64((1.0 - state.tokens) / self.per_second)
+                    }
+                }
+            };
+            tracing::debug!("Rate limit reached; waiting {:.1}s", wait.as_secs_f64());
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Hold every request back for `delay` and empty the bucket, after the
+    /// server answered 429.
+    pub(crate) async fn pause(&self, delay: Duration) {
+        let mut state = self.state.lock().await;
+        let now = Instant::now();
+        state.tokens = 0.0;
+        state.updated = now + delay;
+        state.paused_until = Some(now + delay);
+    }
+
+    /// Back-off used when a 429 carries no `Retry-After` header: the time one
+    /// token takes to refill.
+    pub(crate) fn default_backoff(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.per_second)
+    }
+}
@@ -0,0 +1,139 @@
+//! Helpers shared by the unit tests: a throwaway config directory and a
+//! minimal OpenAI-compatible server that answers with canned responses.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::{fs, thread};
+
+use serde_json::{Value, json};
+
+use crate::config::{Config, ConfigOverrides};
+
+/// A fresh, empty directory under the system temp directory.
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "anki-cli-test-{}-{}-{name}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create temp dir");
+    dir
+}
+
+/// Resolve a config pointing at `openai_base_url`, written to a fresh temp
+/// directory together with `extra` TOML lines.
+pub(crate) fn test_config(openai_base_url: &str, extra: &str) -> Config {
+    let dir = temp_dir("config");
+    let path = dir.join("config.toml");
+    fs::write(
+        &path,
+        format!(
+            "openai_api_key = \"test-key\"\nopenai_base_url = \"{openai_base_url}\"\nopenai_model = \"test-model\"\nauto_save_deck = false\n{extra}\n"
+        ),
+    )
+    .expect("write test config");
+    Config::resolve(Some(path), ConfigOverrides::default())
+        .expect("resolve test config")
+        .config
+}
+
+/// A chat completion whose message content is `content`.
+pub(crate) fn completion(content: &str) -> Value {
+    json!({
+        "choices": [{"message": {"role": "assistant", "content": content}}],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5}
+    })
+}
+
+/// A chat completion in which the model refused the request.
+pub(crate) fn refusal(reason: &str) -> Value {
+    json!({
+        "choices": [{"message": {"role": "assistant", "content": null, "refusal": reason}}]
+    })
+}
+
+/// OpenAI stand-in that answers each request with the next canned body and
+/// records the request bodies it received. Requests beyond the canned
+/// responses get an HTTP 500.
+pub(crate) struct MockOpenAi {
+    pub(crate) url: String,
+    requests: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockOpenAi {
+    pub(crate) fn start(responses: Vec<Value>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let url = format!("http://{}", listener.local_addr().expect("local addr"));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        thread::spawn(move || {
+            let mut responses = responses.into_iter();
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let Some(body) = read_request(&stream) else {
+                    continue;
+                };
+                recorded.lock().unwrap().push(body);
+                let (status, reply) = match responses.next() {
+                    Some(reply) => ("200 OK", reply.to_string()),
+                    None => ("500 Internal Server Error", "{}".to_string()),
+                };
+                let _ = write!(
+                    &stream,
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{reply}",
+                    reply.len()
+                );
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// Bodies of the requests received so far.
+    pub(crate) fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The user message of each chat completion request received so far.
+    pub(crate) fn prompts(&self) -> Vec<String> {
+        self.requests()
+            .iter()
+            .filter_map(|body| {
+                body["messages"]
+                    .as_array()?
+                    .iter()
+                    .rev()
+                    .find(|message| message["role"] == "user")?["content"]
+                    .as_str()
+                    .map(str::to_string)
+            })
+            .collect()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Option<Value> {
+    let mut reader = BufReader::new(stream);
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().ok()?;
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+    Some(serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
@@ -1,354 +1,2985 @@
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
+use chrono::Local;
 use dialoguer::{Confirm, Input, Select};
+use indicatif::{ProgressBar, ProgressStyle, WeakProgressBar};
+use tokio::sync::OnceCell;
 
 use crate::Language;
-use crate::anki::{AnkiConnectClient, Note, NoteOptions};
-use crate::config::Config;
-use crate::llm::{EnglishClozeCard, HindiCard, OpenAiClient};
+use crate::anki::{AnkiClient, Note, NoteInfo, NoteOptions};
+use crate::config::{
+    Config, DEFAULT_EXPLANATION_LANGUAGE, Difficulty, HindiDirection, HindiModelMode,
+};
+use crate::enrich::Enricher;
+use crate::error::AnkiCliError;
+use crate::input::{self, EnglishMode, WordEntry};
+use crate::journal::Journal;
+use crate::llm::{
+    EnglishClozeCard, EnglishDefinitionCard, GenerationOptions, HindiCard, OpenAiClient, uncloze,
+};
+use crate::preview::HtmlPreview;
+
+/// Separator between the examples `--append-to-field` adds to a field.
+const APPEND_SEPARATOR: &str = "<br>";
+
+/// Tag added to every note generated with `--phrase`.
+const PHRASE_TAG: &str = "phrase";
+
+/// Tag marking the Basic notes `--with-production-card` adds, so regenerate
+/// can tell them apart from definition cards.
+const PRODUCTION_TAG: &str = "production";
+
+/// Most word tags OR-ed together in one existing-note search.
+const EXISTING_LOOKUP_CHUNK: usize = 100;
+
+/// Note type and fields required for Hindi sentence notes.
+pub const HINDI_MODEL: &str = "Basic";
+pub const HINDI_FIELDS: [&str; 2] = ["Front", "Back"];
+
+/// Built-in note type used by `hindi_model_mode = "reversed_model"`; it has the
+/// same fields as `Basic` but generates both directions from one note.
+pub const HINDI_REVERSED_MODEL: &str = "Basic (and reversed card)";
+
+/// Note type and fields required for English cloze notes.
+pub const ENGLISH_MODEL: &str = "Cloze";
+pub const ENGLISH_FIELDS: [&str; 2] = ["Text", "Back Extra"];
+
+/// Note type used for English words in Basic mode.
+pub const ENGLISH_BASIC_MODEL: &str = "Basic";
 
 pub struct RunContext<'a> {
-    pub anki: &'a AnkiConnectClient,
+    pub anki: &'a dyn AnkiClient,
     pub llm: &'a OpenAiClient,
     pub config: &'a Config,
     pub dry_run: bool,
     pub auto_approve: bool,
+    pub examples: usize,
+    pub notes_per_word: usize,
+    pub uniqueness_threshold: Option<f32>,
+    pub strict_deck: bool,
+    /// Skip the `max_words_per_run` guard (`--yes-large`).
+    pub yes_large: bool,
+    pub word_timeout: Option<Duration>,
+    pub show_progress: bool,
+    pub deck_from_tag: bool,
+    pub browse: bool,
+    pub english_mode: EnglishMode,
+    pub frequency_ranks: Option<&'a HashMap<String, usize>>,
+    pub known_words: Option<&'a HashSet<String>>,
+    pub html_preview: Option<&'a HtmlPreview>,
+    /// Run in order on every generated card before it becomes notes.
+    pub enrichers: &'a [Box<dyn Enricher>],
+    pub limit: Option<usize>,
+    pub batch_size: usize,
+    pub verify: bool,
+    pub allow_existing: bool,
+    /// Report how many of the words are already in Anki before generating.
+    pub only_new: bool,
+    /// Field that words with existing notes get a new example appended to.
+    pub append_to_field: Option<&'a str>,
+    pub output_note_ids: bool,
+    pub quiet: bool,
+    pub enrich: bool,
+    /// Illustrate English cloze notes with a generated image (`--image`).
+    pub image: bool,
+    /// Add a Basic word→sentence note next to each English cloze note.
+    pub production_card: bool,
+    /// Treat every word as a multi-word expression (`--phrase`).
+    pub phrase: bool,
+    pub resume: bool,
+    pub review_all_first: bool,
+    /// Cloze sentence field, looked up on first use; see [`cloze_text_field`].
+    pub cloze_text_field: OnceCell<String>,
+}
+
+impl RunContext<'_> {
+    pub(crate) fn generation_options(&self) -> GenerationOptions {
+        GenerationOptions {
+            temperature: self.config.temperature,
+            difficulty: self.config.difficulty,
+            examples: self.examples,
+            notes_per_word: self.notes_per_word,
+            include_ipa: self.config.include_ipa,
+            enrich: self.enrich,
+            hindi_sentence_words: self.config.hindi_sentence_words,
+            english_sentence_words: self.config.english_sentence_words,
+            avoid_sentences: Vec::new(),
+            phrase: self.phrase,
+        }
+    }
+
+    /// Whether cards are collected for one review after generation instead
+    /// of being confirmed one at a time.
+    fn reviews_all_first(&self) -> bool {
+        self.review_all_first && !self.dry_run && !self.auto_approve
+    }
 }
 
 pub async fn run_hindi_flow(
-    words: Vec<String>,
+    words: Vec<WordEntry>,
     deck_override: Option<String>,
     ctx: &RunContext<'_>,
 ) -> Result<()> {
     let deck = deck_override.unwrap_or_else(|| ctx.config.hindi_deck.clone());
-    ctx.anki
-        .ensure_deck_exists(&deck)
-        .await
-        .with_context(|| format!("failed to ensure Hindi deck {deck} exists"))?;
+    let journal = Journal::open(ctx.config, "hindi", &words, ctx.resume, ctx.dry_run)?;
+    let PreparedWords {
+        words,
+        duplicates,
+        existing,
+    } = prepare_words(words, "hindi", ctx).await?;
+    let routes = ctx.deck_from_tag.then_some(&ctx.config.tag_deck_map.hindi);
+    let groups = journal.pending(group_by_deck(words, &deck, routes));
+    ensure_note_fields(ctx, &[hindi_model(ctx.config)]).await?;
+    let mut decks = Vec::new();
+    let progress = progress_bar(ctx, groups.iter().map(|(_, entries)| entries.len()).sum());
+    let mut history = SentenceHistory::new(ctx.uniqueness_threshold);
+    let mut added = Vec::new();
+    let mut pending = Vec::new();
+    for (group_deck, entries) in groups {
+        let group_deck = ensure_deck(ctx, &group_deck, "Hindi", &progress).await?;
+        decks.push(group_deck.clone());
 
-    let mut seen = HashSet::new();
-    for word in normalize_words(words) {
-        let key = word.to_lowercase();
-        if !seen.insert(key) {
-            tracing::debug!("Skipping duplicate word: {}", word);
-            continue;
-        }
-
-        tracing::info!("Generating Hindi card for word: {}", word);
-        let card = ctx
-            .llm
-            .generate_hindi_card(&word, ctx.config.temperature)
-            .await
-            .with_context(|| format!("failed to generate Hindi card for '{word}'"))?;
-
-        if ctx.dry_run {
-            print_hindi_card(&card, &deck, "DRY RUN");
-            continue;
-        }
-
-        if !ctx.auto_approve {
-            print_hindi_card(&card, &deck, "REVIEW");
-            let approved = prompt_send_confirmation("Send these Hindi notes to Anki?")?;
-            if !approved {
-                tracing::info!("Skipping Hindi notes for '{}'", card.word);
+        for chunk in entries.chunks(ctx.batch_size.max(1)) {
+            let cards: Vec<GeneratedCard> = if let [entry] = chunk {
+                progress.set_message(entry.word.clone());
+                let entries = std::slice::from_ref(entry);
+                match generate_or_skip(ctx, &journal, entries, generate_card(entry, None, ctx))
+                    .await?
+                {
+                    Some(card) => vec![card],
+                    None => Vec::new(),
+                }
+            } else {
+                tracing::info!("Generating Hindi cards for {} words", chunk.len());
+                let generation = async {
+                    ctx.llm
+                        .generate_hindi_cards(chunk, &ctx.generation_options())
+                        .await
+                        .context("failed to generate Hindi cards")
+                };
+                match generate_or_skip(ctx, &journal, chunk, generation).await? {
+                    Some(cards) => cards.into_iter().map(GeneratedCard::Hindi).collect(),
+                    None => Vec::new(),
+                }
+            };
+            if cards.is_empty() {
+                progress.inc(chunk.len() as u64);
                 continue;
             }
-        }
 
-        let notes = build_hindi_notes(&card, &deck, &ctx.config.tags);
-        let results = ctx
-            .anki
-            .add_notes(&notes)
-            .await
-            .with_context(|| format!("failed to add Hindi notes for '{word}'"))?;
+            for (entry, card) in chunk.iter().zip(cards) {
+                progress.set_message(entry.word.clone());
+                let card = ensure_unique(entry, None, card, &mut history, ctx).await?;
+                let options = ctx.generation_options();
+                let Some(mut card) = ensure_safe(entry, None, card, &options, ctx).await? else {
+                    progress.inc(1);
+                    continue;
+                };
+                enrich_card(&mut card, ctx).await?;
+                if ctx.reviews_all_first() {
+                    pending.push(PendingCard {
+                        deck: group_deck.clone(),
+                        entry: entry.clone(),
+                        card,
+                    });
+                } else {
+                    let ids = send_card(entry, &card, &group_deck, ctx, &progress).await?;
+                    journal.record(&group_deck, &entry.word);
+                    added.extend(ids);
+                }
+                progress.inc(1);
+            }
+        }
+    }
+    progress.finish_and_clear();
+    added.extend(review_all_first(pending, ctx, &journal).await?);
+    let appended = append_examples(existing, "hindi", ctx).await?;
+    journal.finish();
+    ctx.anki
+        .create_pending_decks()
+        .await
+        .context("failed to create the new decks")?;
+    report_duplicates(&duplicates);
 
-        report_add_note_results(&card.word, &deck, results);
+    if (!added.is_empty() || appended > 0) && ctx.config.auto_sync {
+        sync_collection(ctx).await;
+    }
+    if !added.is_empty() {
+        report_deck_stats(ctx, &decks).await;
+    }
+    if !added.is_empty() && ctx.browse && !ctx.dry_run {
+        browse_added_notes(&added, ctx).await;
+    }
+    if ctx.output_note_ids && !ctx.dry_run {
+        print_note_ids(&added)?;
+    }
+    if let Some(preview) = ctx.html_preview {
+        preview.write()?;
     }
 
     // Save the deck name for future use (skip in dry run)
-    if !ctx.dry_run {
-        if let Err(e) = ctx.config.save_hindi_deck(&deck) {
-            tracing::warn!("Failed to save Hindi deck to config: {}", e);
-        }
+    if !ctx.dry_run
+        && ctx.config.auto_save_deck
+        && let Err(e) = save_deck(ctx.config, Language::Hindi, &deck).await
+    {
+        tracing::warn!("Failed to save Hindi deck to config: {}", e);
     }
 
     Ok(())
 }
 
 pub async fn run_english_flow(
-    words: Vec<String>,
+    words: Vec<WordEntry>,
     deck_override: Option<String>,
     ctx: &RunContext<'_>,
 ) -> Result<()> {
     let deck = deck_override.unwrap_or_else(|| ctx.config.english_deck.clone());
-    ctx.anki
-        .ensure_deck_exists(&deck)
-        .await
-        .with_context(|| format!("failed to ensure English deck {deck} exists"))?;
-
-    let mut seen = HashSet::new();
-    for word in normalize_words(words) {
-        let key = word.to_lowercase();
-        if !seen.insert(key.clone()) {
-            tracing::debug!("Skipping duplicate word: {}", word);
-            continue;
-        }
-
-        tracing::info!("Generating English cloze for word: {}", word);
-        let card = ctx
-            .llm
-            .generate_english_cloze(&word, ctx.config.temperature)
-            .await
-            .with_context(|| format!("failed to generate English cloze for '{word}'"))?;
-
-        if ctx.dry_run {
-            print_english_card(&card, &deck, "DRY RUN");
-            continue;
+    let journal = Journal::open(ctx.config, "english", &words, ctx.resume, ctx.dry_run)?;
+    let PreparedWords {
+        words,
+        duplicates,
+        existing,
+    } = prepare_words(words, "english", ctx).await?;
+    let routes = ctx
+        .deck_from_tag
+        .then_some(&ctx.config.tag_deck_map.english);
+    let groups = journal.pending(group_by_deck(words, &deck, routes));
+    let mut models = Vec::new();
+    for (_, entries) in &groups {
+        for entry in entries {
+            let mode = entry.english_mode.unwrap_or(ctx.english_mode);
+            let mut needed = match mode {
+                EnglishMode::Cloze => vec![ENGLISH_MODEL],
+                EnglishMode::Basic => vec![ENGLISH_BASIC_MODEL],
+            };
+            if matches!(mode, EnglishMode::Cloze) && ctx.production_card {
+                needed.push(ENGLISH_BASIC_MODEL);
+            }
+            for model in needed {
+                if !models.contains(&model) {
+                    models.push(model);
+                }
+            }
         }
+    }
+    ensure_note_fields(ctx, &models).await?;
+    let mut decks = Vec::new();
+    let progress = progress_bar(ctx, groups.iter().map(|(_, entries)| entries.len()).sum());
+    let mut history = SentenceHistory::new(ctx.uniqueness_threshold);
+    let mut added = Vec::new();
+    let mut pending = Vec::new();
+    for (group_deck, entries) in groups {
+        let group_deck = ensure_deck(ctx, &group_deck, "English", &progress).await?;
+        decks.push(group_deck.clone());
 
-        if !ctx.auto_approve {
-            print_english_card(&card, &deck, "REVIEW");
-            let approved = prompt_send_confirmation("Send this English cloze to Anki?")?;
-            if !approved {
-                tracing::info!("Skipping English note for '{}'", card.word);
+        for entry in entries {
+            progress.set_message(entry.word.clone());
+            let mode = entry.english_mode.unwrap_or(ctx.english_mode);
+            let generation = generate_card(&entry, Some(mode), ctx);
+            let entries = std::slice::from_ref(&entry);
+            let Some(card) = generate_or_skip(ctx, &journal, entries, generation).await? else {
+                progress.inc(1);
                 continue;
+            };
+            let card = ensure_unique(&entry, Some(mode), card, &mut history, ctx).await?;
+            let options = ctx.generation_options();
+            let Some(mut card) = ensure_safe(&entry, Some(mode), card, &options, ctx).await? else {
+                progress.inc(1);
+                continue;
+            };
+            enrich_card(&mut card, ctx).await?;
+            if ctx.reviews_all_first() {
+                pending.push(PendingCard {
+                    deck: group_deck.clone(),
+                    entry,
+                    card,
+                });
+            } else {
+                let ids = send_card(&entry, &card, &group_deck, ctx, &progress).await?;
+                journal.record(&group_deck, &entry.word);
+                added.extend(ids);
             }
+            progress.inc(1);
         }
+    }
+    progress.finish_and_clear();
+    added.extend(review_all_first(pending, ctx, &journal).await?);
+    let appended = append_examples(existing, "english", ctx).await?;
+    journal.finish();
+    ctx.anki
+        .create_pending_decks()
+        .await
+        .context("failed to create the new decks")?;
+    report_duplicates(&duplicates);
 
-        let note = build_english_note(&card, &deck, &ctx.config.tags);
-        let results = ctx
-            .anki
-            .add_notes(&[note])
-            .await
-            .with_context(|| format!("failed to add English note for '{word}'"))?;
-
-        report_add_note_results(&card.word, &deck, results);
+    if (!added.is_empty() || appended > 0) && ctx.config.auto_sync {
+        sync_collection(ctx).await;
+    }
+    if !added.is_empty() {
+        report_deck_stats(ctx, &decks).await;
+    }
+    if !added.is_empty() && ctx.browse && !ctx.dry_run {
+        browse_added_notes(&added, ctx).await;
+    }
+    if ctx.output_note_ids && !ctx.dry_run {
+        print_note_ids(&added)?;
+    }
+    if let Some(preview) = ctx.html_preview {
+        preview.write()?;
     }
 
     // Save the deck name for future use (skip in dry run)
-    if !ctx.dry_run {
-        if let Err(e) = ctx.config.save_english_deck(&deck) {
-            tracing::warn!("Failed to save English deck to config: {}", e);
-        }
+    if !ctx.dry_run
+        && ctx.config.auto_save_deck
+        && let Err(e) = save_deck(ctx.config, Language::English, &deck).await
+    {
+        tracing::warn!("Failed to save English deck to config: {}", e);
     }
 
     Ok(())
 }
 
-pub async fn run_interactive_session(
-    default_language: Option<Language>,
-    ctx: &RunContext<'_>,
-) -> Result<()> {
-    let mut keep_running = true;
-    let mut preset_language = default_language;
+/// A generated card of any of the supported kinds.
+enum GeneratedCard {
+    Hindi(HindiCard),
+    /// One or more cloze cards for the same word (`--notes-per-word`).
+    Cloze(Vec<EnglishClozeCard>),
+    Definition(EnglishDefinitionCard),
+}
 
-    while keep_running {
-        let language = match preset_language.take() {
-            Some(lang) => lang,
-            None => match prompt_language()? {
-                Some(lang) => lang,
-                None => {
-                    tracing::info!("Exiting interactive session.");
-                    break;
-                }
-            },
-        };
+impl GeneratedCard {
+    fn word(&self) -> &str {
+        match self {
+            GeneratedCard::Hindi(card) => &card.word,
+            GeneratedCard::Cloze(cards) => cards.first().map_or("", |card| &card.word),
+            GeneratedCard::Definition(card) => &card.word,
+        }
+    }
 
-        let input = Input::<String>::new()
-            .with_prompt("Enter words (comma or newline separated). Leave empty to exit")
-            .allow_empty(true)
-            .interact_text()?;
+    fn print(&self, deck: &str, label: &str) {
+        match self {
+            GeneratedCard::Hindi(card) => print_hindi_card(card, deck, label),
+            GeneratedCard::Cloze(cards) => print_english_cards(cards, deck, label),
+            GeneratedCard::Definition(card) => print_english_definition(card, deck, label),
+        }
+    }
 
-        if input.trim().is_empty() {
-            tracing::info!("No words provided. Exiting interactive mode.");
-            break;
+    /// The example sentences on the card, for similarity checks.
+    fn sentences(&self) -> Vec<&str> {
+        match self {
+            GeneratedCard::Hindi(card) => card
+                .examples
+                .iter()
+                .map(|example| example.hindi_sentence.as_str())
+                .collect(),
+            GeneratedCard::Cloze(cards) => cards
+                .iter()
+                .map(|card| card.cloze_sentence.as_str())
+                .collect(),
+            GeneratedCard::Definition(card) => card.example.as_deref().into_iter().collect(),
         }
+    }
 
-        let words = split_input(&input);
-        if words.is_empty() {
-            tracing::warn!("No valid words parsed from input.");
-        } else {
-            match language {
-                Language::Hindi => {
-                    run_hindi_flow(words, None, ctx).await?;
+    /// Every piece of generated text on the card, for the banned-word check.
+    fn texts(&self) -> Vec<&str> {
+        match self {
+            GeneratedCard::Hindi(card) => card
+                .examples
+                .iter()
+                .flat_map(|example| [example.hindi_sentence.as_str(), &example.english_sentence])
+                .collect(),
+            GeneratedCard::Cloze(cards) => cards
+                .iter()
+                .flat_map(|card| {
+                    [card.cloze_sentence.as_str(), &card.translation]
+                        .into_iter()
+                        .chain(card.hint.as_deref())
+                })
+                .collect(),
+            GeneratedCard::Definition(card) => [card.definition.as_str()]
+                .into_iter()
+                .chain(card.example.as_deref())
+                .collect(),
+        }
+    }
+
+    fn confirmation_prompt(&self) -> &'static str {
+        match self {
+            GeneratedCard::Hindi(_) => "Send these Hindi notes to Anki?",
+            GeneratedCard::Cloze(cards) if cards.len() > 1 => "Send these English clozes to Anki?",
+            GeneratedCard::Cloze(_) => "Send this English cloze to Anki?",
+            GeneratedCard::Definition(_) => "Send this English definition to Anki?",
+        }
+    }
+
+    /// Let the user rewrite the generated text, field by field.
+    fn edit(&mut self) -> Result<()> {
+        match self {
+            GeneratedCard::Hindi(card) => {
+                let numbered = card.examples.len() > 1;
+                for (idx, example) in card.examples.iter_mut().enumerate() {
+                    let label = if numbered {
+                        format!(" {}", idx + 1)
+                    } else {
+                        String::new()
+                    };
+                    example.hindi_sentence =
+                        edit_text(&format!("Hindi{label}"), &example.hindi_sentence)?;
+                    example.english_sentence =
+                        edit_text(&format!("English{label}"), &example.english_sentence)?;
+                }
+            }
+            GeneratedCard::Cloze(cards) => {
+                let numbered = cards.len() > 1;
+                for (idx, card) in cards.iter_mut().enumerate() {
+                    let label = if numbered {
+                        format!(" {}", idx + 1)
+                    } else {
+                        String::new()
+                    };
+                    card.cloze_sentence =
+                        edit_text(&format!("Cloze{label}"), &card.cloze_sentence)?;
+                    card.translation =
+                        edit_text(&format!("Explanation{label}"), &card.translation)?;
                 }
-                Language::English => {
-                    run_english_flow(words, None, ctx).await?;
+            }
+            GeneratedCard::Definition(card) => {
+                card.definition = edit_text("Definition", &card.definition)?;
+                if let Some(example) = &card.example {
+                    card.example = Some(edit_text("Example", example)?);
                 }
             }
         }
+        Ok(())
+    }
+}
 
-        keep_running = Confirm::new()
-            .with_prompt("Add more cards?")
-            .default(true)
-            .interact()?;
+/// A card generated under `--review-all-first`, waiting for the batch review.
+struct PendingCard {
+    deck: String,
+    entry: WordEntry,
+    card: GeneratedCard,
+}
+
+/// Save the deck name to the config file on a blocking thread, since the
+/// save may wait up to a few seconds for another run's config lock.
+async fn save_deck(config: &Config, language: Language, deck: &str) -> Result<()> {
+    let config = config.clone();
+    let deck = deck.to_string();
+    tokio::task::spawn_blocking(move || match language {
+        Language::Hindi => config.save_hindi_deck(&deck),
+        Language::English => config.save_english_deck(&deck),
+    })
+    .await
+    .context("config save task failed")?
+}
+
+/// Make sure `deck` exists and return the deck name to add notes to. When
+/// only a differently-cased deck exists, that deck is used after a warning
+/// (and a confirmation unless `--auto-approve`); `--strict-deck` makes it an
+/// error instead.
+async fn ensure_deck(
+    ctx: &RunContext<'_>,
+    deck: &str,
+    language: &str,
+    progress: &ProgressBar,
+) -> Result<String> {
+    match ctx.anki.ensure_deck_exists(deck).await {
+        Ok(()) => Ok(deck.to_string()),
+        Err(AnkiCliError::DeckCaseMismatch {
+            requested,
+            existing,
+        }) => {
+            if ctx.strict_deck {
+                anyhow::bail!(
+                    "{language} deck '{requested}' does not exist, but '{existing}' does; fix the casing or drop --strict-deck"
+                );
+            }
+            tracing::warn!(
+                "{} deck '{}' differs only in case from the existing deck '{}'",
+                language,
+                requested,
+                existing
+            );
+            let use_existing = ctx.auto_approve
+                || progress.suspend(|| {
+                    prompt_send_confirmation(&format!(
+                        "Use the existing deck '{existing}' instead?"
+                    ))
+                })?;
+            if !use_existing {
+                anyhow::bail!("not creating {language} deck '{requested}' next to '{existing}'");
+            }
+            Ok(existing)
+        }
+        Err(err) => Err(anyhow::Error::from(err))
+            .with_context(|| format!("failed to ensure {language} deck {deck} exists")),
     }
+}
+
+/// Generate the card for one word: Hindi when `mode` is `None`, otherwise
+/// the given English card style.
+async fn generate_card(
+    entry: &WordEntry,
+    mode: Option<EnglishMode>,
+    ctx: &RunContext<'_>,
+) -> Result<GeneratedCard> {
+    generate_card_with(entry, mode, ctx, &ctx.generation_options()).await
+}
+
+#[tracing::instrument(name = "generate", skip_all, fields(word = %entry.word))]
+async fn generate_card_with(
+    entry: &WordEntry,
+    mode: Option<EnglishMode>,
+    ctx: &RunContext<'_>,
+    options: &GenerationOptions,
+) -> Result<GeneratedCard> {
+    let options = &options.for_entry(entry);
+    let word = &entry.word;
+    let meaning = entry.definition.as_deref();
+    let card = match mode {
+        None => {
+            tracing::info!("Generating Hindi card for word: {}", word);
+            let card = ctx
+                .llm
+                .generate_hindi_card(word, entry.pos, meaning, options)
+                .await
+                .with_context(|| format!("failed to generate Hindi card for '{word}'"))?;
+            GeneratedCard::Hindi(card)
+        }
+        Some(EnglishMode::Cloze) => {
+            tracing::info!("Generating English cloze for word: {}", word);
+            let cards = ctx
+                .llm
+                .generate_english_clozes(word, entry.pos, meaning, options)
+                .await
+                .with_context(|| format!("failed to generate English cloze for '{word}'"))?;
+            GeneratedCard::Cloze(cards)
+        }
+        Some(EnglishMode::Basic) => {
+            tracing::info!("Generating English definition for word: {}", word);
+            let card = ctx
+                .llm
+                .generate_english_definition(word, entry.pos, meaning, options)
+                .await
+                .with_context(|| format!("failed to generate English definition for '{word}'"))?;
+            GeneratedCard::Definition(card)
+        }
+    };
+    Ok(card)
+}
 
+/// Pass a card through the configured enrichers, in order.
+async fn enrich_card(card: &mut GeneratedCard, ctx: &RunContext<'_>) -> Result<()> {
+    for enricher in ctx.enrichers {
+        let word = card.word().to_string();
+        apply_enricher(enricher.as_ref(), card)
+            .await
+            .with_context(|| {
+                format!(
+                    "enricher '{}' failed on the card for '{}'",
+                    enricher.name(),
+                    word
+                )
+            })?;
+    }
     Ok(())
 }
 
-fn build_hindi_notes(card: &HindiCard, deck: &str, base_tags: &[String]) -> Vec<Note> {
-    let tags = collect_tags(base_tags, &card.word, "hindi");
+async fn apply_enricher(enricher: &dyn Enricher, card: &mut GeneratedCard) -> Result<()> {
+    match card {
+        GeneratedCard::Hindi(card) => enricher.enrich_hindi(card).await,
+        GeneratedCard::Cloze(cards) => {
+            for card in cards {
+                enricher.enrich_cloze(card).await?;
+            }
+            Ok(())
+        }
+        GeneratedCard::Definition(card) => enricher.enrich_definition(card).await,
+    }
+}
 
-    let mut forward_fields = BTreeMap::new();
-    forward_fields.insert("Front".to_string(), card.hindi_sentence.clone());
-    forward_fields.insert("Back".to_string(), card.english_sentence.clone());
+/// Generate the cards for `entries` under `--word-timeout`. When that times
+/// out, the model refuses or its reply is unusable, the words are recorded as
+/// failed and `None` is returned so the run moves on without them. Any other
+/// error ends the run, after recording the words too.
+async fn generate_or_skip<T>(
+    ctx: &RunContext<'_>,
+    journal: &Journal,
+    entries: &[WordEntry],
+    generation: impl Future<Output = Result<T>>,
+) -> Result<Option<T>> {
+    let words: Vec<&str> = entries.iter().map(|entry| entry.word.as_str()).collect();
+    let words = words.join(", ");
+    let result = within_word_timeout(ctx, &words, entries.len(), generation).await;
+    if !matches!(result, Ok(Some(_))) {
+        entries
+            .iter()
+            .for_each(|entry| journal.record_failure(entry));
+    }
+    match result {
+        Err(err) if is_word_failure(&err) => {
+            tracing::warn!("Skipping '{}': {:#}", words, err);
+            Ok(None)
+        }
+        result => result,
+    }
+}
 
-    let mut reverse_fields = BTreeMap::new();
-    reverse_fields.insert("Front".to_string(), card.english_sentence.clone());
-    reverse_fields.insert("Back".to_string(), card.hindi_sentence.clone());
+/// Whether a generation error concerns only the words being generated: the
+/// model refused them or returned a card that could not be used.
+fn is_word_failure(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<AnkiCliError>(),
+            Some(AnkiCliError::Refused(_) | AnkiCliError::LlmParse { .. })
+        )
+    })
+}
 
-    let note_options = NoteOptions {
-        allow_duplicate: Some(false),
-        duplicate_scope: Some("deck".to_string()),
+/// Run a generation under `--word-timeout`, scaled by the number of words it
+/// covers. On timeout the words are logged and `None` is returned so the run
+/// moves on without them.
+async fn within_word_timeout<T>(
+    ctx: &RunContext<'_>,
+    words: &str,
+    count: usize,
+    generation: impl Future<Output = Result<T>>,
+) -> Result<Option<T>> {
+    let Some(limit) = ctx.word_timeout else {
+        return generation.await.map(Some);
     };
 
-    vec![
-        Note {
-            deck_name: deck.to_string(),
-            model_name: "Basic".to_string(),
-            fields: forward_fields,
-            tags: tags.clone(),
-            options: Some(note_options.clone()),
-        },
-        Note {
-            deck_name: deck.to_string(),
-            model_name: "Basic".to_string(),
-            fields: reverse_fields,
-            tags,
-            options: Some(note_options),
-        },
-    ]
+    let limit = u32::try_from(count)
+        .ok()
+        .and_then(|count| limit.checked_mul(count))
+        .unwrap_or(Duration::MAX);
+    match tokio::time::timeout(limit, generation).await {
+        Ok(result) => result.map(Some),
+        Err(_) => {
+            tracing::warn!(
+                "Generation for '{}' took longer than {}s; skipping",
+                words,
+                limit.as_secs()
+            );
+            Ok(None)
+        }
+    }
 }
 
-fn build_english_note(card: &EnglishClozeCard, deck: &str, base_tags: &[String]) -> Note {
-    let mut fields = BTreeMap::new();
-    fields.insert("Text".to_string(), card.cloze_sentence.clone());
+/// With `--uniqueness-threshold`, regenerate a card once when one of its
+/// sentences is too similar to a sentence generated earlier in the run, asking
+/// the model to make it different from that sentence.
+async fn ensure_unique(
+    entry: &WordEntry,
+    mode: Option<EnglishMode>,
+    card: GeneratedCard,
+    history: &mut SentenceHistory,
+    ctx: &RunContext<'_>,
+) -> Result<GeneratedCard> {
+    if history.threshold.is_none() {
+        return Ok(card);
+    }
 
-    let mut back_extra = format!("Explanation: {}", card.translation.trim());
-    if let Some(hint) = &card.hint {
-        if !hint.trim().is_empty() {
-            back_extra.push_str("\nHint: ");
-            back_extra.push_str(hint.trim());
+    let similar = history
+        .most_similar(&card.sentences())
+        .map(|(earlier, score)| (earlier.to_string(), score));
+    let card = match similar {
+        Some((earlier, score)) => {
+            tracing::info!(
+                "Sentence for '{}' is {:.0}% similar to an earlier one; regenerating",
+                entry.word,
+                score * 100.0
+            );
+            let options = GenerationOptions {
+                avoid_sentences: vec![earlier],
+                ..ctx.generation_options()
+            };
+            let generation = generate_card_with(entry, mode, ctx, &options);
+            within_word_timeout(ctx, &entry.word, 1, generation)
+                .await?
+                .unwrap_or(card)
         }
+        None => card,
+    };
+    history.record(&card.sentences());
+    Ok(card)
+}
+
+/// In safe mode with `banned_word_check`, regenerate a card once when its text
+/// contains a banned term. A card that still contains one is dropped and
+/// `None` returned, so the word is skipped.
+async fn ensure_safe(
+    entry: &WordEntry,
+    mode: Option<EnglishMode>,
+    card: GeneratedCard,
+    options: &GenerationOptions,
+    ctx: &RunContext<'_>,
+) -> Result<Option<GeneratedCard>> {
+    if !ctx.config.safe_mode || !ctx.config.banned_word_check {
+        return Ok(Some(card));
     }
+    let Some(term) = banned_term(&card, ctx.config) else {
+        return Ok(Some(card));
+    };
 
-    fields.insert("Back Extra".to_string(), back_extra);
+    tracing::warn!(
+        "Card for '{}' contains the banned term '{}'; regenerating",
+        entry.word,
+        term
+    );
+    let options = GenerationOptions {
+        avoid_sentences: card.sentences().iter().map(|s| s.to_string()).collect(),
+        ..options.clone()
+    };
+    let generation = generate_card_with(entry, mode, ctx, &options);
+    let Some(card) = within_word_timeout(ctx, &entry.word, 1, generation).await? else {
+        return Ok(None);
+    };
+    if let Some(term) = banned_term(&card, ctx.config) {
+        tracing::warn!(
+            "Regenerated card for '{}' still contains the banned term '{}'; skipping the word",
+            entry.word,
+            term
+        );
+        return Ok(None);
+    }
+    Ok(Some(card))
+}
 
-    let tags = collect_tags(base_tags, &card.word, "english");
+/// The first banned term in the card's text, matched case-insensitively as a
+/// whole word or phrase.
+fn banned_term<'c>(card: &GeneratedCard, config: &'c Config) -> Option<&'c str> {
+    let text = card.texts().join("\n").to_lowercase();
+    config.banned_words.iter().map(String::as_str).find(|term| {
+        text.match_indices(term).any(|(idx, _)| {
+            let before = text[..idx].chars().next_back();
+            let after = text[idx + term.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+    })
+}
 
-    Note {
-        deck_name: deck.to_string(),
-        model_name: "Cloze".to_string(),
-        fields,
-        tags,
-        options: Some(NoteOptions {
-            allow_duplicate: Some(false),
-            duplicate_scope: Some("deck".to_string()),
-        }),
-    }
+/// Sentences generated so far in a run, compared by the Jaccard similarity
+/// of their lowercased word sets.
+struct SentenceHistory {
+    threshold: Option<f32>,
+    seen: Vec<(String, HashSet<String>)>,
 }
 
-fn collect_tags(base: &[String], word: &str, language_tag: &str) -> Vec<String> {
-    let mut tags = base.to_vec();
-    if !tags
-        .iter()
-        .any(|existing| existing.eq_ignore_ascii_case(language_tag))
-    {
-        tags.push(language_tag.to_string());
+impl SentenceHistory {
+    fn new(threshold: Option<f32>) -> Self {
+        Self {
+            threshold,
+            seen: Vec::new(),
+        }
     }
 
-    let word_tag = format!("word_{}", sanitize_tag(word));
-    if !tags
-        .iter()
-        .any(|existing| existing.eq_ignore_ascii_case(&word_tag))
-    {
-        tags.push(word_tag);
+    /// The earlier sentence closest to any of `sentences`, when it reaches the threshold.
+    fn most_similar(&self, sentences: &[&str]) -> Option<(&str, f32)> {
+        let threshold = self.threshold?;
+        sentences
+            .iter()
+            .map(|sentence| sentence_tokens(sentence))
+            .flat_map(|tokens| {
+                self.seen
+                    .iter()
+                    .map(move |(earlier, seen)| (earlier.as_str(), jaccard(&tokens, seen)))
+            })
+            .filter(|(_, score)| *score >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
     }
 
-    tags
+    fn record(&mut self, sentences: &[&str]) {
+        if self.threshold.is_some() {
+            self.seen.extend(
+                sentences
+                    .iter()
+                    .map(|sentence| (sentence.to_string(), sentence_tokens(sentence))),
+            );
+        }
+    }
 }
 
-fn sanitize_tag(input: &str) -> String {
-    input
-        .trim()
-        .chars()
-        .map(|c| match c {
-            c if c.is_whitespace() => '_',
-            ':' | ';' | ',' => '_',
-            _ => c,
+/// Lowercased words of a sentence, ignoring punctuation and cloze markers
+/// such as `c1`.
+fn sentence_tokens(sentence: &str) -> HashSet<String> {
+    sentence
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .filter(|token| {
+            !token.strip_prefix('c').is_some_and(|number| {
+                !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())
+            })
         })
+        .map(str::to_lowercase)
         .collect()
 }
 
-fn normalize_words(words: Vec<String>) -> Vec<String> {
-    words
-        .into_iter()
-        .map(|w| w.trim().to_string())
-        .filter(|w| !w.is_empty())
-        .collect()
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
 }
 
-fn report_add_note_results(word: &str, deck: &str, results: Vec<Option<i64>>) {
-    for (idx, outcome) in results.into_iter().enumerate() {
-        match outcome {
-            Some(note_id) => {
-                tracing::info!("Added note {} for '{}' to deck '{}'", note_id, word, deck)
+/// Preview, review and add the notes for a generated card.
+async fn send_card(
+    entry: &WordEntry,
+    card: &GeneratedCard,
+    deck: &str,
+    ctx: &RunContext<'_>,
+    progress: &ProgressBar,
+) -> Result<Vec<i64>> {
+    if ctx.dry_run {
+        show_preview(ctx, progress, || card.print(deck, "DRY RUN"));
+        if let Some(preview) = ctx.html_preview {
+            let notes = card_notes(entry, card, deck, ctx).await?;
+            preview.add_notes(ctx.anki, card.word(), &notes).await?;
+        }
+        return Ok(Vec::new());
+    }
+
+    if !ctx.auto_approve {
+        let approved = progress.suspend(|| {
+            card.print(deck, "REVIEW");
+            prompt_send_confirmation(card.confirmation_prompt())
+        })?;
+        if !approved {
+            tracing::info!("Skipping notes for '{}'", card.word());
+            return Ok(Vec::new());
+        }
+    }
+
+    add_card(entry, card, deck, ctx).await
+}
+
+/// Page through every generated card, letting the user approve, skip or edit
+/// each one, then add the approved cards.
+async fn review_all_first(
+    pending: Vec<PendingCard>,
+    ctx: &RunContext<'_>,
+    journal: &Journal,
+) -> Result<Vec<i64>> {
+    if pending.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let total = pending.len();
+    let mut approved = Vec::new();
+    let mut remaining = pending.into_iter().enumerate();
+    while let Some((idx, mut item)) = remaining.next() {
+        loop {
+            item.card
+                .print(&item.deck, &format!("REVIEW {}/{}", idx + 1, total));
+            let choice = Select::new()
+                .with_prompt("Approve this card?")
+                .items(&[
+                    "Approve",
+                    "Skip",
+                    "Edit",
+                    "Approve all remaining",
+                    "Skip all remaining",
+                ])
+                .default(0)
+                .interact()
+                .context("failed to read review choice")?;
+            match choice {
+                0 => approved.push(item),
+                1 => {
+                    tracing::info!("Skipping notes for '{}'", item.card.word());
+                    journal.record(&item.deck, &item.entry.word);
+                }
+                2 => {
+                    item.card.edit()?;
+                    continue;
+                }
+                3 => {
+                    approved.push(item);
+                    approved.extend(remaining.by_ref().map(|(_, item)| item));
+                }
+                _ => {
+                    journal.record(&item.deck, &item.entry.word);
+                    let mut skipped = 1;
+                    for (_, rest) in remaining.by_ref() {
+                        journal.record(&rest.deck, &rest.entry.word);
+                        skipped += 1;
+                    }
+                    tracing::info!("Skipping the remaining {} card(s)", skipped);
+                }
             }
-            None => tracing::warn!(
-                "Anki reported a duplicate for '{}' (card #{}).",
-                word,
-                idx + 1
-            ),
+            break;
         }
     }
+
+    tracing::info!("Sending {} of {} reviewed card(s)", approved.len(), total);
+    let mut added = Vec::new();
+    for item in approved {
+        let ids = add_card(&item.entry, &item.card, &item.deck, ctx).await?;
+        journal.record(&item.deck, &item.entry.word);
+        added.extend(ids);
+    }
+    Ok(added)
 }
 
-fn print_hindi_card(card: &HindiCard, deck: &str, label: &str) {
-    println!("[{}][{}] {}", label, deck, card.word);
-    println!("  Hindi : {}", card.hindi_sentence);
-    println!("  English: {}", card.english_sentence);
+/// Build and add the notes for an approved card.
+#[tracing::instrument(name = "add", skip_all, fields(word = %entry.word, deck = %deck))]
+async fn add_card(
+    entry: &WordEntry,
+    card: &GeneratedCard,
+    deck: &str,
+    ctx: &RunContext<'_>,
+) -> Result<Vec<i64>> {
+    let notes = card_notes(entry, card, deck, ctx).await?;
+    if let GeneratedCard::Hindi(card) = card {
+        return add_hindi_notes(&card.word, &notes, deck, ctx).await;
+    }
+
+    let mut added = Vec::new();
+    for note in &notes {
+        added.extend(add_english_note(note, card.word(), deck, ctx).await?);
+    }
+    Ok(added)
 }
 
-fn print_english_card(card: &EnglishClozeCard, deck: &str, label: &str) {
-    println!("[{}][{}] {}", label, deck, card.word);
-    println!("  Cloze       : {}", card.cloze_sentence);
-    println!("  Explanation : {}", card.translation);
-    if let Some(hint) = &card.hint {
-        if !hint.trim().is_empty() {
-            println!("  Hint        : {}", hint);
+/// The notes a card becomes.
+async fn card_notes(
+    entry: &WordEntry,
+    card: &GeneratedCard,
+    deck: &str,
+    ctx: &RunContext<'_>,
+) -> Result<Vec<Note>> {
+    let mut tags = entry.tags.clone();
+    if ctx.phrase {
+        tags.push(PHRASE_TAG.to_string());
+    }
+    let notes = match card {
+        GeneratedCard::Hindi(card) => build_hindi_notes(card, deck, &tags, ctx.config),
+        GeneratedCard::Cloze(cards) => {
+            let text_field = cloze_text_field(ctx).await?;
+            let image = match cards.first() {
+                Some(first) if ctx.image && !ctx.dry_run => word_image(&first.word, ctx).await,
+                _ => None,
+            };
+            let mut notes = Vec::new();
+            for card in cards {
+                notes.push(build_english_note(
+                    card,
+                    deck,
+                    &tags,
+                    ctx.config,
+                    text_field,
+                    image.as_deref(),
+                ));
+                if ctx.production_card {
+                    notes.push(build_production_note(card, deck, &tags, ctx.config));
+                }
+            }
+            notes
+        }
+        GeneratedCard::Definition(card) => {
+            vec![build_english_basic_note(card, deck, &tags, ctx.config)]
+        }
+    };
+    Ok(notes)
+}
+
+/// Generate an illustration for `word` and store it in Anki's media folder,
+/// returning the stored file name. A failure is logged and yields `None`, so
+/// the card is still added without the image.
+async fn word_image(word: &str, ctx: &RunContext<'_>) -> Option<String> {
+    let prompt = format!(
+        "A simple, clear illustration of the meaning of the English word \"{word}\", to be used as a visual mnemonic on a flashcard. Do not include any text or letters."
+    );
+    let stored = async {
+        let image = ctx.llm.generate_image(&prompt).await?;
+        ctx.anki
+            .store_media_file(&image_file_name(word), &image)
+            .await
+    }
+    .await;
+    match stored {
+        Ok(filename) => {
+            tracing::debug!("Stored image for '{word}' as {filename}");
+            Some(filename)
+        }
+        Err(err) => {
+            tracing::warn!(
+                "Failed to add an image for '{word}', adding the card without it: {err}"
+            );
+            None
         }
     }
 }
 
-fn prompt_send_confirmation(prompt: &str) -> Result<bool> {
-    Confirm::new()
-        .with_prompt(prompt)
-        .default(true)
-        .interact()
-        .context("failed to read approval input")
+/// Media file name for a word's image; the timestamp keeps a regenerated
+/// image from replacing the one on an existing note.
+fn image_file_name(word: &str) -> String {
+    let stem: String = word
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    format!(
+        "anki-cli_{stem}_{}.png",
+        Local::now().format("%Y%m%d%H%M%S")
+    )
 }
 
-fn prompt_language() -> Result<Option<Language>> {
-    let selections = vec!["Hindi sentence cards", "English cloze cards", "Exit"];
-    let choice = Select::new()
-        .with_prompt("Choose a language workflow")
-        .items(&selections)
-        .default(0)
-        .interact()?;
+async fn add_hindi_notes(
+    word: &str,
+    notes: &[Note],
+    deck: &str,
+    ctx: &RunContext<'_>,
+) -> Result<Vec<i64>> {
+    let results = ctx
+        .anki
+        .add_notes(notes)
+        .await
+        .with_context(|| format!("failed to add Hindi notes for '{word}'"))?;
 
-    match choice {
-        0 => Ok(Some(Language::Hindi)),
-        1 => Ok(Some(Language::English)),
-        _ => Ok(None),
+    if ctx.verify {
+        verify_added_notes(ctx, word, &results).await;
+    }
+    let mut outcomes = Vec::with_capacity(results.len());
+    for (note, result) in notes.iter().zip(results) {
+        outcomes.push(match result {
+            Some(note_id) => Ok(note_id),
+            None => Err(ctx.anki.rejection_reason(note).await),
+        });
     }
+    Ok(report_add_note_results(word, deck, outcomes))
 }
 
-fn split_input(input: &str) -> Vec<String> {
-    input
-        .split(|c| c == ',' || c == ';' || c == '\n' || c == '\r')
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .collect()
+/// Add one English note. A rejected note is logged with Anki's reason and
+/// skipped; any other failure aborts the run.
+async fn add_english_note(
+    note: &Note,
+    word: &str,
+    deck: &str,
+    ctx: &RunContext<'_>,
+) -> Result<Vec<i64>> {
+    let note_id = match ctx.anki.add_note(note).await {
+        Ok(note_id) => note_id,
+        Err(AnkiCliError::NoteRejected(reason)) => {
+            tracing::warn!("Anki did not add the note for '{}': {}", word, reason);
+            return Ok(Vec::new());
+        }
+        Err(err) => {
+            return Err(anyhow::Error::from(err))
+                .with_context(|| format!("failed to add English note for '{word}'"));
+        }
+    };
+
+    if ctx.verify {
+        verify_added_notes(ctx, word, &[Some(note_id)]).await;
+    }
+    Ok(report_add_note_results(word, deck, vec![Ok(note_id)]))
+}
+
+/// The progress bar currently on screen, if any, so log lines can be
+/// printed around it instead of through it.
+static ACTIVE_PROGRESS: Mutex<Option<WeakProgressBar>> = Mutex::new(None);
+
+/// Progress bar for a batch of words; hidden unless the run asked for one.
+fn progress_bar(ctx: &RunContext<'_>, len: usize) -> ProgressBar {
+    if !ctx.show_progress {
+        return ProgressBar::hidden();
+    }
+
+    let bar = ProgressBar::new(len as u64);
+    if let Ok(style) =
+        ProgressStyle::with_template("[{bar:30}] {pos}/{len} words (ETA {eta}) {msg}")
+    {
+        bar.set_style(style.progress_chars("=> "));
+    }
+    *ACTIVE_PROGRESS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = Some(bar.downgrade());
+    bar
+}
+
+/// Run `f` with the active progress bar hidden, so a log line printed from
+/// it does not interleave with the bar.
+pub fn suspend_progress<R>(f: impl FnOnce() -> R) -> R {
+    let active = ACTIVE_PROGRESS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .and_then(WeakProgressBar::upgrade);
+    match active {
+        Some(bar) if !bar.is_finished() => bar.suspend(f),
+        _ => f(),
+    }
+}
+
+/// Prompt for words in a loop. With `auto_detect`, the language prompt is
+/// skipped and each word is routed by its script (see
+/// [`input::detect_language`]); only ambiguous words are asked about.
+pub async fn run_interactive_session(
+    default_language: Option<Language>,
+    auto_detect: bool,
+    ctx: &RunContext<'_>,
+) -> Result<()> {
+    let mut keep_running = true;
+    let mut preset_language = default_language;
+
+    while keep_running {
+        let language = if auto_detect {
+            None
+        } else {
+            match preset_language.take() {
+                Some(lang) => Some(lang),
+                None => match prompt_language()? {
+                    Some(lang) => Some(lang),
+                    None => {
+                        tracing::info!("Exiting interactive session.");
+                        break;
+                    }
+                },
+            }
+        };
+
+        let input = Input::<String>::new()
+            .with_prompt("Enter words (comma or newline separated). Leave empty to exit")
+            .allow_empty(true)
+            .interact_text()?;
+
+        if input.trim().is_empty() {
+            tracing::info!("No words provided. Exiting interactive mode.");
+            break;
+        }
+
+        let words = input::parse_word_entries(input::split_input(&input), None);
+        if words.is_empty() {
+            tracing::warn!("No valid words parsed from input.");
+        } else if let Err(err) = input::check_temperatures(&words) {
+            tracing::warn!("{}", err);
+        } else {
+            let batches = match language {
+                Some(language) => vec![(language, words)],
+                None => partition_by_language(words)?,
+            };
+            for (language, words) in batches {
+                let deck = prompt_deck(language, ctx).await?;
+                match language {
+                    Language::Hindi => {
+                        run_hindi_flow(words, Some(deck), ctx).await?;
+                    }
+                    Language::English => {
+                        run_english_flow(words, Some(deck), ctx).await?;
+                    }
+                }
+            }
+        }
+
+        keep_running = Confirm::new()
+            .with_prompt("Add more cards?")
+            .default(true)
+            .interact()?;
+    }
+
+    Ok(())
+}
+
+/// Regenerate every note tagged `tag` (e.g. `prompt_v1`) with the current
+/// prompt. Each note's word is read back from its word tag, a fresh card is
+/// generated and the note's fields are updated in place; updated notes then
+/// have `tag` replaced by the current `prompt_<version>` tag.
+pub async fn run_regenerate(tag: &str, ctx: &RunContext<'_>) -> Result<()> {
+    let new_tag = format!("prompt_{}", sanitize_tag(&ctx.config.prompt_version));
+    if new_tag.eq_ignore_ascii_case(tag) {
+        anyhow::bail!(
+            "'{tag}' is already the current prompt tag; set --prompt-version to the new version"
+        );
+    }
+
+    let note_ids = ctx
+        .anki
+        .find_notes(&format!("tag:{}", escape_search_term(tag)))
+        .await
+        .with_context(|| format!("failed to find notes tagged '{tag}'"))?;
+    if note_ids.is_empty() {
+        tracing::info!("No notes tagged '{}'", tag);
+        return Ok(());
+    }
+    let notes = ctx
+        .anki
+        .notes_info(&note_ids)
+        .await
+        .with_context(|| format!("failed to read the notes tagged '{tag}'"))?;
+    tracing::info!("Regenerating {} note(s) tagged '{}'", notes.len(), tag);
+
+    let options = GenerationOptions {
+        notes_per_word: 1,
+        ..ctx.generation_options()
+    };
+    // Both notes of a two-note Hindi pair share one regenerated card.
+    let mut hindi_cards: HashMap<String, HindiCard> = HashMap::new();
+    let mut updated = Vec::new();
+    let progress = progress_bar(ctx, notes.len());
+    for note in notes {
+        progress.inc(1);
+        let Some(note_id) = note.note_id else {
+            continue;
+        };
+        let Some((language, word)) = note_word(ctx.config, &note.tags) else {
+            tracing::warn!("Note {} has no language or word tag; skipping", note_id);
+            continue;
+        };
+        progress.set_message(word.clone());
+        let entry = WordEntry::parse(&word, None);
+
+        let mut fields = match language {
+            Language::Hindi => {
+                let card = match hindi_cards.get(&word) {
+                    Some(card) => card.clone(),
+                    None => {
+                        let generated = generate_card_with(&entry, None, ctx, &options).await?;
+                        let Some(mut generated) =
+                            ensure_safe(&entry, None, generated, &options, ctx).await?
+                        else {
+                            continue;
+                        };
+                        enrich_card(&mut generated, ctx).await?;
+                        let GeneratedCard::Hindi(card) = generated else {
+                            anyhow::bail!(
+                                "expected a Hindi card for '{word}' (note {note_id}), got English"
+                            );
+                        };
+                        hindi_cards.insert(word.clone(), card.clone());
+                        card
+                    }
+                };
+                let (hindi_text, english_text) = (card.hindi_text(), card.english_text());
+                let english_front = note.fields.get("Front").is_some_and(|front| {
+                    matches!(
+                        input::detect_language(&front.value),
+                        Some(Language::English)
+                    )
+                });
+                let (front, back) = if english_front {
+                    (english_text, hindi_text)
+                } else {
+                    (hindi_text, english_text)
+                };
+                let mut fields =
+                    BTreeMap::from([("Front".to_string(), front), ("Back".to_string(), back)]);
+                add_configured_fields(&mut fields, ctx.config, &card.word);
+                fields
+            }
+            Language::English => {
+                let production = note.model_name != ENGLISH_MODEL
+                    && note
+                        .tags
+                        .iter()
+                        .any(|tag| tag.eq_ignore_ascii_case(PRODUCTION_TAG));
+                let mode = if note.model_name == ENGLISH_MODEL || production {
+                    EnglishMode::Cloze
+                } else {
+                    EnglishMode::Basic
+                };
+                let generated = generate_card_with(&entry, Some(mode), ctx, &options).await?;
+                let Some(mut generated) =
+                    ensure_safe(&entry, Some(mode), generated, &options, ctx).await?
+                else {
+                    continue;
+                };
+                enrich_card(&mut generated, ctx).await?;
+                match generated {
+                    GeneratedCard::Cloze(cards) => {
+                        let card = cards.first().with_context(|| {
+                            format!("no cloze generated for '{word}' (note {note_id})")
+                        })?;
+                        if production {
+                            build_production_note(card, "", &[], ctx.config).fields
+                        } else {
+                            let mut fields = build_english_note(
+                                card,
+                                "",
+                                &[],
+                                ctx.config,
+                                cloze_text_field(ctx).await?,
+                                None,
+                            )
+                            .fields;
+                            // Clear explanations left over from the old prompt.
+                            fields.entry("Back Extra".to_string()).or_default();
+                            fields
+                        }
+                    }
+                    GeneratedCard::Definition(card) => {
+                        build_english_basic_note(&card, "", &[], ctx.config).fields
+                    }
+                    GeneratedCard::Hindi(_) => anyhow::bail!(
+                        "expected an English card for '{word}' (note {note_id}), got Hindi"
+                    ),
+                }
+            }
+        };
+        fields.retain(|name, _| note.fields.contains_key(name));
+
+        if ctx.dry_run {
+            show_preview(ctx, &progress, || {
+                print_note_update(note_id, &word, &fields, "DRY RUN")
+            });
+            continue;
+        }
+        if !ctx.auto_approve {
+            let approved = progress.suspend(|| {
+                print_note_update(note_id, &word, &fields, "REVIEW");
+                prompt_send_confirmation("Update this note in Anki?")
+            })?;
+            if !approved {
+                tracing::info!("Skipping note {} for '{}'", note_id, word);
+                continue;
+            }
+        }
+
+        ctx.anki
+            .update_note_fields(note_id, &fields)
+            .await
+            .with_context(|| format!("failed to update note {note_id} for '{word}'"))?;
+        updated.push(note_id);
+    }
+    progress.finish_and_clear();
+
+    if updated.is_empty() {
+        return Ok(());
+    }
+    ctx.anki
+        .replace_tags(&updated, tag, &new_tag)
+        .await
+        .with_context(|| format!("failed to retag updated notes as '{new_tag}'"))?;
+    tracing::info!(
+        "Regenerated {} note(s) and retagged them from '{}' to '{}'",
+        updated.len(),
+        tag,
+        new_tag
+    );
+    Ok(())
+}
+
+/// Language and word of a stored note, read from its language tag and its
+/// word tag (`word_chai` or `hindi::word_chai`). Spaces in multi-word
+/// entries were stored as `_`.
+fn note_word(config: &Config, tags: &[String]) -> Option<(Language, String)> {
+    let language = if tags.iter().any(|tag| tag.eq_ignore_ascii_case("hindi")) {
+        Language::Hindi
+    } else if tags.iter().any(|tag| tag.eq_ignore_ascii_case("english")) {
+        Language::English
+    } else {
+        return None;
+    };
+    let word = tags.iter().find_map(|tag| {
+        let tag = tag.rsplit("::").next().unwrap_or(tag);
+        tag.strip_prefix(config.word_tag_prefix.as_str())
+            .filter(|word| !word.is_empty())
+    })?;
+    Some((language, word.replace('_', " ")))
+}
+
+fn print_note_update(note_id: i64, word: &str, fields: &BTreeMap<String, String>, label: &str) {
+    println!("[{}][note {}] {}", label, note_id, word);
+    for (name, value) in fields {
+        println!("  {name}: {value}");
+    }
+}
+
+fn build_hindi_notes(
+    card: &HindiCard,
+    deck: &str,
+    word_tags: &[String],
+    config: &Config,
+) -> Vec<Note> {
+    let tags = collect_tags(
+        &config.tags,
+        word_tags,
+        &word_tag(config, "hindi", &card.word),
+        "hindi",
+        config.difficulty,
+        &run_tags(config),
+    );
+
+    let hindi_text = card.hindi_text();
+    let english_text = card.english_text();
+    // With the reversed model a single Hindi-front note yields both cards.
+    let sides: Vec<(&str, &str)> = match config.hindi_direction {
+        HindiDirection::Both if config.hindi_model_mode == HindiModelMode::TwoNotes => {
+            vec![(&hindi_text, &english_text), (&english_text, &hindi_text)]
+        }
+        HindiDirection::Both | HindiDirection::Forward => vec![(&hindi_text, &english_text)],
+        HindiDirection::Reverse => vec![(&english_text, &hindi_text)],
+    };
+
+    let note_options = note_options(config);
+    sides
+        .into_iter()
+        .map(|(front, back)| {
+            let mut fields = BTreeMap::new();
+            fields.insert("Front".to_string(), front.to_string());
+            fields.insert("Back".to_string(), back.to_string());
+            add_configured_fields(&mut fields, config, &card.word);
+
+            Note {
+                deck_name: deck.to_string(),
+                model_name: hindi_model(config).to_string(),
+                fields,
+                tags: tags.clone(),
+                options: Some(note_options.clone()),
+            }
+        })
+        .collect()
+}
+
+/// Note type for Hindi notes: the reversed model only matters when both
+/// directions are wanted.
+pub(crate) fn hindi_model(config: &Config) -> &'static str {
+    if config.hindi_direction == HindiDirection::Both
+        && config.hindi_model_mode == HindiModelMode::ReversedModel
+    {
+        HINDI_REVERSED_MODEL
+    } else {
+        HINDI_MODEL
+    }
+}
+
+/// Field of the Cloze note type that receives the cloze sentence: the
+/// configured `cloze_field`, else `Text`, else the note type's first field.
+/// Looked up once per run.
+pub(crate) async fn cloze_text_field<'c>(ctx: &'c RunContext<'_>) -> Result<&'c str> {
+    let field = ctx
+        .cloze_text_field
+        .get_or_try_init(|| async {
+            let fields = ctx
+                .anki
+                .model_field_names(ENGLISH_MODEL)
+                .await
+                .with_context(|| {
+                    format!("failed to read the fields of note type '{ENGLISH_MODEL}'")
+                })?;
+            if let Some(field) = &ctx.config.cloze_field {
+                if !fields.contains(field) {
+                    anyhow::bail!(
+                        "cloze_field '{field}' is not a field of note type '{ENGLISH_MODEL}' (fields: {})",
+                        fields.join(", ")
+                    );
+                }
+                return Ok(field.clone());
+            }
+            if fields.iter().any(|field| field == ENGLISH_FIELDS[0]) {
+                return Ok(ENGLISH_FIELDS[0].to_string());
+            }
+            let first = fields.first().with_context(|| {
+                format!("note type '{ENGLISH_MODEL}' has no fields")
+            })?;
+            tracing::warn!(
+                "Note type '{}' has no '{}' field; writing clozes to its first field '{}' (set cloze_field to choose another)",
+                ENGLISH_MODEL,
+                ENGLISH_FIELDS[0],
+                first
+            );
+            Ok(first.clone())
+        })
+        .await?;
+    Ok(field)
+}
+
+fn build_english_note(
+    card: &EnglishClozeCard,
+    deck: &str,
+    word_tags: &[String],
+    config: &Config,
+    text_field: &str,
+    image: Option<&str>,
+) -> Note {
+    let mut fields = BTreeMap::new();
+    fields.insert(text_field.to_string(), card.cloze_sentence.clone());
+
+    // Each part goes to its mapped field when `cloze_fields` names one, and
+    // into `Back Extra` with a label otherwise. Parts the model left out are
+    // omitted entirely.
+    let map = &config.cloze_fields;
+    let hint = card
+        .hint
+        .as_deref()
+        .map(str::trim)
+        .filter(|hint| !hint.is_empty());
+    let synonyms = (!card.synonyms.is_empty()).then(|| card.synonyms.join(", "));
+    let antonyms = (!card.antonyms.is_empty()).then(|| card.antonyms.join(", "));
+    let parts = [
+        (
+            &map.translation,
+            explanation_label(config),
+            Some(card.translation.trim()),
+        ),
+        (&map.hint, "Hint".to_string(), hint),
+        (&map.ipa, "IPA".to_string(), card.ipa.as_deref()),
+        (&map.synonyms, "Synonyms".to_string(), synonyms.as_deref()),
+        (&map.antonyms, "Antonyms".to_string(), antonyms.as_deref()),
+    ];
+
+    let mut back_extra = Vec::new();
+    for (field, label, value) in parts {
+        let Some(value) = value else {
+            continue;
+        };
+        match field {
+            Some(field) => {
+                fields.insert(field.clone(), value.to_string());
+            }
+            None => back_extra.push(format!("{label}: {value}")),
+        }
+    }
+    if let Some(image) = image {
+        back_extra.push(format!("<img src=\"{image}\">"));
+    }
+    if !back_extra.is_empty() {
+        fields.insert("Back Extra".to_string(), back_extra.join("\n"));
+    }
+    add_configured_fields(&mut fields, config, &card.word);
+
+    let tags = collect_tags(
+        &config.tags,
+        word_tags,
+        &word_tag(config, "english", &card.word),
+        "english",
+        config.difficulty,
+        &run_tags(config),
+    );
+
+    Note {
+        deck_name: deck.to_string(),
+        model_name: ENGLISH_MODEL.to_string(),
+        fields,
+        tags,
+        options: Some(note_options(config)),
+    }
+}
+
+fn build_english_basic_note(
+    card: &EnglishDefinitionCard,
+    deck: &str,
+    word_tags: &[String],
+    config: &Config,
+) -> Note {
+    let mut front = card.word.clone();
+    if let Some(ipa) = &card.ipa {
+        front.push_str("<br>");
+        front.push_str(ipa);
+    }
+
+    let mut back = card.definition.clone();
+    if let Some(example) = &card.example {
+        back.push_str("<br><br>Example: ");
+        back.push_str(example);
+    }
+
+    let mut fields = BTreeMap::new();
+    fields.insert("Front".to_string(), front);
+    fields.insert("Back".to_string(), back);
+    add_configured_fields(&mut fields, config, &card.word);
+
+    let tags = collect_tags(
+        &config.tags,
+        word_tags,
+        &word_tag(config, "english", &card.word),
+        "english",
+        config.difficulty,
+        &run_tags(config),
+    );
+
+    Note {
+        deck_name: deck.to_string(),
+        model_name: ENGLISH_BASIC_MODEL.to_string(),
+        fields,
+        tags,
+        options: Some(note_options(config)),
+    }
+}
+
+/// Companion note for `--with-production-card`: the word and its meaning on
+/// the front, the full cloze sentence without markup on the back. It shares
+/// the cloze note's deck, tags and duplicate options.
+fn build_production_note(
+    card: &EnglishClozeCard,
+    deck: &str,
+    word_tags: &[String],
+    config: &Config,
+) -> Note {
+    let mut front = card.word.clone();
+    let meaning = card.translation.trim();
+    if !meaning.is_empty() {
+        front.push_str("<br>");
+        front.push_str(meaning);
+    }
+
+    let mut fields = BTreeMap::new();
+    fields.insert("Front".to_string(), front);
+    fields.insert("Back".to_string(), uncloze(&card.cloze_sentence));
+    add_configured_fields(&mut fields, config, &card.word);
+
+    let mut word_tags = word_tags.to_vec();
+    word_tags.push(PRODUCTION_TAG.to_string());
+    let tags = collect_tags(
+        &config.tags,
+        &word_tags,
+        &word_tag(config, "english", &card.word),
+        "english",
+        config.difficulty,
+        &run_tags(config),
+    );
+
+    Note {
+        deck_name: deck.to_string(),
+        model_name: ENGLISH_BASIC_MODEL.to_string(),
+        fields,
+        tags,
+        options: Some(note_options(config)),
+    }
+}
+
+/// "Explanation", tagged with the language when it is not the default English.
+fn explanation_label(config: &Config) -> String {
+    if config
+        .explanation_language
+        .eq_ignore_ascii_case(DEFAULT_EXPLANATION_LANGUAGE)
+    {
+        "Explanation".to_string()
+    } else {
+        format!("Explanation ({})", config.explanation_language)
+    }
+}
+
+/// Every tag for a note: normalized, deduplicated case-insensitively and sorted.
+fn collect_tags(
+    base: &[String],
+    word_tags: &[String],
+    word_tag: &str,
+    language_tag: &str,
+    difficulty: Option<Difficulty>,
+    run_tags: &[String],
+) -> Vec<String> {
+    let level_tag = difficulty.map(|level| format!("level_{}", level.as_str()));
+    let candidates = base
+        .iter()
+        .chain(word_tags)
+        .map(String::as_str)
+        .chain([language_tag])
+        .chain(level_tag.as_deref())
+        .chain([word_tag])
+        .chain(run_tags.iter().map(String::as_str));
+
+    // Anki treats tags case-insensitively, so the first spelling of each tag
+    // wins; sorting keeps the list stable however the inputs were ordered.
+    let mut tags: Vec<String> = Vec::new();
+    for tag in candidates.map(sanitize_tag) {
+        if !tag.is_empty()
+            && !tags
+                .iter()
+                .any(|existing| existing.eq_ignore_ascii_case(&tag))
+        {
+            tags.push(tag);
+        }
+    }
+    tags.sort_by(|a, b| {
+        a.to_lowercase()
+            .cmp(&b.to_lowercase())
+            .then_with(|| a.cmp(b))
+    });
+    tags
+}
+
+/// Duplicate handling for new notes, from `allow_duplicate` and `duplicate_scope`.
+fn note_options(config: &Config) -> NoteOptions {
+    NoteOptions {
+        allow_duplicate: Some(config.allow_duplicate),
+        duplicate_scope: Some(config.duplicate_scope.as_str().to_string()),
+    }
+}
+
+/// Tags shared by every note of a run: the prompt version and, when
+/// enabled, the date tag.
+fn run_tags(config: &Config) -> Vec<String> {
+    let mut tags = vec![format!("prompt_{}", sanitize_tag(&config.prompt_version))];
+    tags.extend(date_tag(config));
+    tags
+}
+
+/// Fill the optional `word_field`, `prompt_version_field` and `source_field`
+/// of a note.
+fn add_configured_fields(fields: &mut BTreeMap<String, String>, config: &Config, word: &str) {
+    if let Some(field) = &config.word_field {
+        fields.insert(field.clone(), word.to_string());
+    }
+    if let Some(field) = &config.prompt_version_field {
+        fields.insert(field.clone(), config.prompt_version.clone());
+    }
+    if let Some(field) = &config.source_field {
+        fields.insert(field.clone(), source_label(config));
+    }
+}
+
+/// Provenance written to `source_field`, e.g.
+/// `anki-cli 0.1.0 / gpt-4o / 2026-10-17`.
+fn source_label(config: &Config) -> String {
+    format!(
+        "anki-cli {} / {} / {}",
+        env!("CARGO_PKG_VERSION"),
+        config.openai_model,
+        Local::now().format("%Y-%m-%d")
+    )
+}
+
+/// Fail early when the configured `word_field`, `prompt_version_field` or
+/// `source_field`, or a `cloze_field` or `cloze_fields` entry for the Cloze
+/// note type, is missing from a note type the run is about to use.
+pub(crate) async fn ensure_note_fields(ctx: &RunContext<'_>, models: &[&str]) -> Result<()> {
+    for model in models {
+        if *model == ENGLISH_MODEL {
+            cloze_text_field(ctx).await?;
+        }
+        let mut required: Vec<(&str, &str)> = Vec::new();
+        if let Some(field) = &ctx.config.word_field {
+            required.push(("word_field", field));
+        }
+        if let Some(field) = &ctx.config.prompt_version_field {
+            required.push(("prompt_version_field", field));
+        }
+        if let Some(field) = &ctx.config.source_field {
+            required.push(("source_field", field));
+        }
+        if *model == ENGLISH_MODEL {
+            required.extend(
+                ctx.config
+                    .cloze_fields
+                    .fields()
+                    .into_iter()
+                    .map(|field| ("cloze_fields", field)),
+            );
+        }
+        if required.is_empty() {
+            continue;
+        }
+
+        let fields = ctx
+            .anki
+            .model_field_names(model)
+            .await
+            .with_context(|| format!("failed to read the fields of note type '{model}'"))?;
+        for (key, field) in required {
+            if !fields.iter().any(|name| name == field) {
+                anyhow::bail!(
+                    "{key} '{field}' is not a field of note type '{model}' (fields: {})",
+                    fields.join(", ")
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `added_<date>` tag for today when `date_tag` is enabled.
+fn date_tag(config: &Config) -> Option<String> {
+    config.date_tag.then(|| {
+        let date = Local::now().format(&config.date_tag_format).to_string();
+        format!("added_{}", sanitize_tag(&date))
+    })
+}
+
+/// Tag identifying the note's word, e.g. `word_chai`, or `hindi::word_chai`
+/// with `namespace_word_tags`.
+fn word_tag(config: &Config, language_tag: &str, word: &str) -> String {
+    let tag = format!("{}{}", config.word_tag_prefix, word);
+    if config.namespace_word_tags {
+        sanitize_tag(&format!("{language_tag}::{tag}"))
+    } else {
+        sanitize_tag(&tag)
+    }
+}
+
+/// Replace characters Anki does not allow in tags, collapsing each run of
+/// whitespace into one `_` and keeping the `::` separator of hierarchical tags.
+fn sanitize_tag(input: &str) -> String {
+    input
+        .trim()
+        .split("::")
+        .map(|part| {
+            part.split_whitespace()
+                .collect::<Vec<_>>()
+                .join("_")
+                .chars()
+                .map(|c| match c {
+                    ':' | ';' | ',' => '_',
+                    _ => c,
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Split words into per-deck groups, keeping first-seen order. When `routes`
+/// is set, the first word tag with a mapped deck overrides `default_deck`.
+fn group_by_deck(
+    words: Vec<WordEntry>,
+    default_deck: &str,
+    routes: Option<&BTreeMap<String, String>>,
+) -> Vec<(String, Vec<WordEntry>)> {
+    let mut groups: Vec<(String, Vec<WordEntry>)> = Vec::new();
+    for entry in words {
+        let deck = routes
+            .and_then(|routes| {
+                entry.tags.iter().find_map(|tag| {
+                    routes
+                        .iter()
+                        .find(|(mapped, _)| mapped.eq_ignore_ascii_case(tag))
+                        .map(|(_, deck)| deck.clone())
+                })
+            })
+            .unwrap_or_else(|| default_deck.to_string());
+
+        match groups.iter_mut().find(|(existing, _)| *existing == deck) {
+            Some((_, entries)) => entries.push(entry),
+            None => groups.push((deck, vec![entry])),
+        }
+    }
+    groups
+}
+
+/// Normalize and dedupe the batch, drop words listed in a `--dedupe-against`
+/// file, put common words first with `--sort-by-frequency`, drop words Anki
+/// already has cards for (unless `--allow-existing`), then apply `--limit`.
+/// Also returns the duplicates that were dropped so they can be reported once
+/// the batch is done.
+async fn prepare_words(
+    words: Vec<WordEntry>,
+    language_tag: &str,
+    ctx: &RunContext<'_>,
+) -> Result<PreparedWords> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    let mut unique = Vec::new();
+    for entry in normalize_words(words) {
+        if seen.insert(entry.word.to_lowercase()) {
+            unique.push(entry);
+        } else {
+            tracing::debug!("Skipping duplicate word: {}", entry.word);
+            duplicates.push(entry.word);
+        }
+    }
+    let words = match ctx.known_words {
+        Some(known) => filter_known_words(unique, known),
+        None => unique,
+    };
+
+    let words = match ctx.frequency_ranks {
+        Some(ranks) => input::sort_by_frequency(words, ranks),
+        None => words,
+    };
+    let (mut words, existing) = if ctx.allow_existing {
+        (words, Vec::new())
+    } else {
+        filter_existing_words(words, language_tag, ctx).await?
+    };
+    if let Some(limit) = ctx.limit
+        && words.len() > limit
+    {
+        tracing::info!("Processing the first {} of {} words", limit, words.len());
+        words.truncate(limit);
+    }
+    check_run_size(words.len(), ctx)?;
+    confirm_estimated_cost(words.len(), language_tag, ctx)?;
+    Ok(PreparedWords {
+        words,
+        duplicates,
+        existing,
+    })
+}
+
+/// The words of a run after [`prepare_words`].
+struct PreparedWords {
+    /// Words to generate new notes for.
+    words: Vec<WordEntry>,
+    /// Repeated words that were dropped, reported once the batch is done.
+    duplicates: Vec<String>,
+    /// Words that already have notes, kept only for `--append-to-field`.
+    existing: Vec<WordEntry>,
+}
+
+/// Refuse runs larger than `max_words_per_run` unless `--yes-large` was
+/// given, so a wrong `--input` file does not turn into a large OpenAI bill.
+fn check_run_size(words: usize, ctx: &RunContext<'_>) -> Result<()> {
+    let max = ctx.config.max_words_per_run;
+    if words <= max {
+        return Ok(());
+    }
+    if ctx.yes_large {
+        tracing::info!(
+            "Processing {words} words (above max_words_per_run = {max}) because of --yes-large"
+        );
+        return Ok(());
+    }
+    anyhow::bail!(
+        "{words} words to process exceeds max_words_per_run ({max}); pass --yes-large to run them anyway, or use --limit to process fewer"
+    )
+}
+
+/// Ask before starting a batch of more than `cost_confirm_threshold` words,
+/// showing a rough token estimate. Only asked on a terminal and never with
+/// `--auto-approve`.
+fn confirm_estimated_cost(words: usize, language_tag: &str, ctx: &RunContext<'_>) -> Result<()> {
+    if words <= ctx.config.cost_confirm_threshold
+        || ctx.auto_approve
+        || !std::io::stdout().is_terminal()
+    {
+        return Ok(());
+    }
+
+    let language = if language_tag == "hindi" {
+        Language::Hindi
+    } else {
+        Language::English
+    };
+    let (prompt_tokens, completion_tokens) = ctx
+        .llm
+        .estimated_tokens_per_word(language, &ctx.generation_options());
+    let prompt_tokens = prompt_tokens * words as u64;
+    let completion_tokens = completion_tokens * words as u64;
+    let prompt = format!(
+        "Generate cards for {words} words? Estimated usage: ~{prompt_tokens} prompt + ~{completion_tokens} completion tokens"
+    );
+    if !prompt_send_confirmation(&prompt)? {
+        anyhow::bail!("run cancelled before generating {words} words");
+    }
+    Ok(())
+}
+
+/// Drop words listed in the `--dedupe-against` files.
+fn filter_known_words(words: Vec<WordEntry>, known: &HashSet<String>) -> Vec<WordEntry> {
+    let before = words.len();
+    let words: Vec<WordEntry> = words
+        .into_iter()
+        .filter(|entry| {
+            let is_known = known.contains(&input::known_word_key(&entry.word));
+            if is_known {
+                tracing::debug!("Skipping known word: {}", entry.word);
+            }
+            !is_known
+        })
+        .collect();
+    let filtered = before - words.len();
+    if filtered > 0 {
+        tracing::info!(
+            "Filtered {} known word{} listed in --dedupe-against files",
+            filtered,
+            if filtered == 1 { "" } else { "s" }
+        );
+    }
+    words
+}
+
+/// Split off words that already have a note tagged `word_<word>` for this
+/// language anywhere in the collection. They are dropped, or returned as the
+/// second list with `--append-to-field`.
+async fn filter_existing_words(
+    words: Vec<WordEntry>,
+    language_tag: &str,
+    ctx: &RunContext<'_>,
+) -> Result<(Vec<WordEntry>, Vec<WordEntry>)> {
+    let tags: Vec<String> = words
+        .iter()
+        .map(|entry| word_tag(ctx.config, language_tag, &entry.word).to_lowercase())
+        .collect();
+    let found = existing_word_tags(&tags, language_tag, ctx).await?;
+    let mut fresh = Vec::with_capacity(words.len());
+    let mut existing = Vec::new();
+    for (entry, tag) in words.into_iter().zip(&tags) {
+        if found.contains(tag) {
+            existing.push(entry);
+        } else {
+            fresh.push(entry);
+        }
+    }
+
+    // On stderr, so it never mixes with `--output-note-ids` on stdout.
+    if ctx.only_new && !ctx.quiet {
+        eprintln!(
+            "{} of {} already in Anki, generating {} new",
+            existing.len(),
+            fresh.len() + existing.len(),
+            fresh.len()
+        );
+    }
+    if !existing.is_empty() {
+        let names: Vec<&str> = existing.iter().map(|entry| entry.word.as_str()).collect();
+        match ctx.append_to_field {
+            Some(field) => tracing::info!(
+                "{} word{} already in Anki will get a new example in '{}': {}",
+                existing.len(),
+                if existing.len() == 1 { "" } else { "s" },
+                field,
+                names.join(", ")
+            ),
+            None => tracing::info!(
+                "Filtered {} word{} already in Anki: {} (use --allow-existing to keep them)",
+                existing.len(),
+                if existing.len() == 1 { "" } else { "s" },
+                names.join(", ")
+            ),
+        }
+    }
+    if ctx.append_to_field.is_none() {
+        existing.clear();
+    }
+    Ok((fresh, existing))
+}
+
+/// Which of the lowercased word `tags` are on a note of this language,
+/// looked up with one `findNotes` query (OR-ing the tags) and one
+/// `notesInfo` request per [`EXISTING_LOOKUP_CHUNK`] tags.
+async fn existing_word_tags(
+    tags: &[String],
+    language_tag: &str,
+    ctx: &RunContext<'_>,
+) -> Result<HashSet<String>> {
+    let unique: Vec<&String> = tags.iter().collect::<BTreeSet<_>>().into_iter().collect();
+    let mut found = HashSet::new();
+    for chunk in unique.chunks(EXISTING_LOOKUP_CHUNK) {
+        let terms: Vec<String> = chunk
+            .iter()
+            .map(|tag| format!("tag:{}", escape_search_term(tag)))
+            .collect();
+        let query = format!("tag:{language_tag} ({})", terms.join(" OR "));
+        let note_ids = ctx
+            .anki
+            .find_notes(&query)
+            .await
+            .context("failed to look up existing notes")?;
+        if note_ids.is_empty() {
+            continue;
+        }
+        let notes = ctx
+            .anki
+            .notes_info(&note_ids)
+            .await
+            .context("failed to read existing notes")?;
+        found.extend(
+            notes
+                .into_iter()
+                .flat_map(|note| note.tags)
+                .map(|tag| tag.to_lowercase())
+                .filter(|tag| chunk.contains(&tag)),
+        );
+    }
+    Ok(found)
+}
+
+/// Notes tagged with the word tag of `word` for this language.
+async fn find_word_notes(word: &str, language_tag: &str, ctx: &RunContext<'_>) -> Result<Vec<i64>> {
+    let query = format!(
+        "tag:{} tag:{}",
+        escape_search_term(&word_tag(ctx.config, language_tag, word)),
+        language_tag
+    );
+    ctx.anki
+        .find_notes(&query)
+        .await
+        .with_context(|| format!("failed to look up existing notes for '{word}'"))
+}
+
+/// `--append-to-field`: generate a fresh card for each word that already has
+/// notes and append the card's text for the field to those notes, leaving
+/// notes alone once the field holds `append_max_examples` entries. Returns
+/// the number of updated notes.
+async fn append_examples(
+    entries: Vec<WordEntry>,
+    language_tag: &str,
+    ctx: &RunContext<'_>,
+) -> Result<usize> {
+    let Some(field) = ctx.append_to_field else {
+        return Ok(0);
+    };
+    let max = ctx.config.append_max_examples;
+    let mut updated = 0;
+    let progress = progress_bar(ctx, entries.len());
+    for entry in entries {
+        progress.set_message(entry.word.clone());
+        progress.inc(1);
+        let note_ids = find_word_notes(&entry.word, language_tag, ctx).await?;
+        let notes: Vec<NoteInfo> = ctx
+            .anki
+            .notes_info(&note_ids)
+            .await
+            .with_context(|| format!("failed to read the notes for '{}'", entry.word))?
+            .into_iter()
+            .filter(|note| note.note_id.is_some())
+            .collect();
+        let current: Vec<(&NoteInfo, &str)> = notes
+            .iter()
+            .filter_map(|note| Some((note, note.fields.get(field)?.value.as_str())))
+            .collect();
+        if current.is_empty() {
+            tracing::warn!(
+                "No note for '{}' has a '{}' field; skipping",
+                entry.word,
+                field
+            );
+            continue;
+        }
+        if current
+            .iter()
+            .all(|(_, value)| split_examples(value).len() >= max)
+        {
+            tracing::info!(
+                "'{}' already has {} example(s) in '{}'; skipping",
+                entry.word,
+                max,
+                field
+            );
+            continue;
+        }
+
+        let mode =
+            (language_tag == "english").then(|| entry.english_mode.unwrap_or(ctx.english_mode));
+        let options = GenerationOptions {
+            notes_per_word: 1,
+            avoid_sentences: current
+                .iter()
+                .flat_map(|(_, value)| split_examples(value))
+                .map(str::to_string)
+                .collect(),
+            ..ctx.generation_options()
+        };
+        let generation = generate_card_with(&entry, mode, ctx, &options);
+        let Some(card) = within_word_timeout(ctx, &entry.word, 1, generation).await? else {
+            continue;
+        };
+        let Some(mut card) = ensure_safe(&entry, mode, card, &options, ctx).await? else {
+            continue;
+        };
+        enrich_card(&mut card, ctx).await?;
+        let fresh = fresh_notes(&card, ctx).await?;
+
+        for (note, value) in current {
+            let Some(note_id) = note.note_id else {
+                continue;
+            };
+            if split_examples(value).len() >= max {
+                tracing::info!("Note {} already has {} example(s); skipping", note_id, max);
+                continue;
+            }
+            let Some(addition) = matching_note(&fresh, note)
+                .and_then(|fresh| fresh.fields.get(field))
+                .filter(|addition| !addition.trim().is_empty())
+            else {
+                tracing::warn!(
+                    "The new card for '{}' has no text for '{}' on note {}; skipping",
+                    entry.word,
+                    field,
+                    note_id
+                );
+                continue;
+            };
+            let value = if value.trim().is_empty() {
+                addition.clone()
+            } else {
+                format!("{value}{APPEND_SEPARATOR}{addition}")
+            };
+            let fields = BTreeMap::from([(field.to_string(), value)]);
+
+            if ctx.dry_run {
+                show_preview(ctx, &progress, || {
+                    print_note_update(note_id, &entry.word, &fields, "DRY RUN")
+                });
+                continue;
+            }
+            if !ctx.auto_approve {
+                let approved = progress.suspend(|| {
+                    print_note_update(note_id, &entry.word, &fields, "REVIEW");
+                    prompt_send_confirmation("Append this example to the note?")
+                })?;
+                if !approved {
+                    tracing::info!("Skipping note {} for '{}'", note_id, entry.word);
+                    continue;
+                }
+            }
+            ctx.anki
+                .update_note_fields(note_id, &fields)
+                .await
+                .with_context(|| format!("failed to update note {note_id} for '{}'", entry.word))?;
+            tracing::info!(
+                "Appended an example to '{}' of note {} for '{}'",
+                field,
+                note_id,
+                entry.word
+            );
+            updated += 1;
+        }
+    }
+    progress.finish_and_clear();
+    Ok(updated)
+}
+
+/// Entries of a field that `--append-to-field` may have grown.
+fn split_examples(value: &str) -> Vec<&str> {
+    value
+        .split(APPEND_SEPARATOR)
+        .map(str::trim)
+        .filter(|example| !example.is_empty())
+        .collect()
+}
+
+/// Fields a new note for the card would get, without deck or tags.
+async fn fresh_notes(card: &GeneratedCard, ctx: &RunContext<'_>) -> Result<Vec<Note>> {
+    Ok(match card {
+        GeneratedCard::Hindi(card) => build_hindi_notes(card, "", &[], ctx.config),
+        GeneratedCard::Cloze(cards) => {
+            let text_field = cloze_text_field(ctx).await?;
+            cards
+                .iter()
+                .map(|card| build_english_note(card, "", &[], ctx.config, text_field, None))
+                .collect()
+        }
+        GeneratedCard::Definition(card) => {
+            vec![build_english_basic_note(card, "", &[], ctx.config)]
+        }
+    })
+}
+
+/// The new note matching an existing one: same note type and, for the two
+/// notes of a Hindi pair, the same language on the front.
+fn matching_note<'n>(fresh: &'n [Note], existing: &NoteInfo) -> Option<&'n Note> {
+    let front_language = |front: Option<&str>| front.and_then(input::detect_language);
+    let existing_front = front_language(existing.fields.get("Front").map(|f| f.value.as_str()));
+    let mut candidates = fresh
+        .iter()
+        .filter(|note| note.model_name == existing.model_name);
+    let first = candidates.clone().next();
+    candidates
+        .find(|note| {
+            let front = front_language(note.fields.get("Front").map(String::as_str));
+            matches!(
+                (front, existing_front),
+                (Some(Language::Hindi), Some(Language::Hindi))
+                    | (Some(Language::English), Some(Language::English))
+            )
+        })
+        .or(first)
+}
+
+/// Quote an Anki search term so `_`, `*` and `"` match literally.
+fn escape_search_term(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len() + 2);
+    escaped.push('"');
+    for c in term.chars() {
+        if matches!(c, '_' | '*' | '"' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn report_duplicates(duplicates: &[String]) {
+    if duplicates.is_empty() {
+        return;
+    }
+    tracing::info!(
+        "Skipped {} duplicate word{}: {}",
+        duplicates.len(),
+        if duplicates.len() == 1 { "" } else { "s" },
+        duplicates.join(", ")
+    );
+}
+
+fn normalize_words(words: Vec<WordEntry>) -> Vec<WordEntry> {
+    words
+        .into_iter()
+        .map(|mut entry| {
+            let word = input::normalize_unicode(&entry.word);
+            entry.word = word.split_whitespace().collect::<Vec<_>>().join(" ");
+            entry
+        })
+        .filter(|entry| !entry.word.is_empty())
+        .collect()
+}
+
+/// Re-read freshly added notes and warn about any that produced no cards
+/// (usually a malformed cloze) or have empty fields.
+async fn verify_added_notes(ctx: &RunContext<'_>, word: &str, results: &[Option<i64>]) {
+    let note_ids: Vec<i64> = results.iter().flatten().copied().collect();
+    if note_ids.is_empty() {
+        return;
+    }
+
+    let notes = match ctx.anki.notes_info(&note_ids).await {
+        Ok(notes) => notes,
+        Err(err) => {
+            tracing::warn!(
+                "Could not verify notes for '{}': {:#}",
+                word,
+                anyhow::Error::from(err)
+            );
+            return;
+        }
+    };
+
+    for (note_id, note) in note_ids.iter().zip(&notes) {
+        if note.cards.is_empty() {
+            tracing::warn!(
+                "Note {} for '{}' ({}) produced no cards; check the cloze markup",
+                note_id,
+                word,
+                note.model_name
+            );
+        }
+        for (name, field) in &note.fields {
+            if field.value.trim().is_empty() {
+                tracing::warn!(
+                    "Note {} for '{}' has an empty {} field",
+                    note_id,
+                    word,
+                    name
+                );
+            }
+        }
+    }
+}
+
+/// Log the outcome of each note, with Anki's reason for the ones it
+/// rejected, and return the IDs of the notes actually added.
+fn report_add_note_results(
+    word: &str,
+    deck: &str,
+    results: Vec<std::result::Result<i64, String>>,
+) -> Vec<i64> {
+    let mut added = Vec::new();
+    for (idx, outcome) in results.into_iter().enumerate() {
+        match outcome {
+            Ok(note_id) => {
+                added.push(note_id);
+                tracing::info!(
+                    note_id,
+                    "Added note {} for '{}' to deck '{}'",
+                    note_id,
+                    word,
+                    deck
+                )
+            }
+            Err(reason) => tracing::warn!(
+                "Anki did not add note #{} for '{}': {}",
+                idx + 1,
+                word,
+                reason
+            ),
+        }
+    }
+
+    added
+}
+
+/// Print the created note IDs to stdout as a JSON array for `--output-note-ids`.
+fn print_note_ids(note_ids: &[i64]) -> Result<()> {
+    write_note_ids(&mut io::stdout().lock(), note_ids)
+}
+
+/// Write `note_ids` as one JSON array line, and nothing else.
+fn write_note_ids(out: &mut impl Write, note_ids: &[i64]) -> Result<()> {
+    serde_json::to_writer(&mut *out, note_ids).context("failed to serialize note IDs")?;
+    writeln!(out).context("failed to write note IDs")
+}
+
+/// Log how many cards each deck the run added to now holds. Failures are
+/// only logged; the notes are already in.
+async fn report_deck_stats(ctx: &RunContext<'_>, decks: &[String]) {
+    let decks: Vec<&str> = decks.iter().map(String::as_str).collect();
+    match ctx.anki.deck_stats(&decks).await {
+        Ok(stats) => {
+            for stat in stats {
+                tracing::info!(
+                    "Deck '{}' now has {} card(s), {} new",
+                    stat.name,
+                    stat.total_in_deck,
+                    stat.new_count
+                );
+            }
+        }
+        Err(err) => tracing::warn!(
+            "Failed to read deck statistics: {:#}",
+            anyhow::Error::from(err)
+        ),
+    }
+}
+
+/// Sync with AnkiWeb, logging rather than propagating failures so a sync
+/// problem never hides the notes that were already added.
+async fn sync_collection(ctx: &RunContext<'_>) {
+    tracing::info!("Syncing Anki collection...");
+    match ctx.anki.sync().await {
+        Ok(()) => tracing::info!("Sync complete"),
+        Err(err) => tracing::warn!(
+            "Failed to sync Anki collection: {:#}",
+            anyhow::Error::from(err)
+        ),
+    }
+}
+
+/// Open Anki's browser on exactly the notes added in this run.
+async fn browse_added_notes(note_ids: &[i64], ctx: &RunContext<'_>) {
+    let ids: Vec<String> = note_ids.iter().map(i64::to_string).collect();
+    let query = format!("nid:{}", ids.join(","));
+    match ctx.anki.gui_browse(&query).await {
+        Ok(cards) => tracing::info!(
+            "Opened Anki browser with {} card(s) from {} added note(s)",
+            cards.len(),
+            note_ids.len()
+        ),
+        Err(err) => tracing::warn!(
+            "Could not open the Anki browser (is the Anki window open?): {:#}",
+            anyhow::Error::from(err)
+        ),
+    }
+}
+
+/// Print a dry-run preview unless the run is `--quiet`. Review previews are
+/// always shown since the confirmation prompt depends on them.
+fn show_preview(ctx: &RunContext<'_>, progress: &ProgressBar, print: impl FnOnce()) {
+    if !ctx.quiet {
+        progress.suspend(print);
+    }
+}
+
+fn print_hindi_card(card: &HindiCard, deck: &str, label: &str) {
+    println!("[{}][{}] {}", label, deck, card.word);
+    for (idx, example) in card.examples.iter().enumerate() {
+        if card.examples.len() > 1 {
+            println!("  Example {}", idx + 1);
+        }
+        println!("  Hindi : {}", example.hindi_sentence);
+        println!("  English: {}", example.english_sentence);
+    }
+}
+
+fn print_english_cards(cards: &[EnglishClozeCard], deck: &str, label: &str) {
+    let word = cards.first().map_or("", |card| card.word.as_str());
+    println!("[{}][{}] {}", label, deck, word);
+    for (idx, card) in cards.iter().enumerate() {
+        if cards.len() > 1 {
+            println!("  Note {}", idx + 1);
+        }
+        println!("  Cloze       : {}", card.cloze_sentence);
+        println!("  Explanation : {}", card.translation);
+        if let Some(hint) = &card.hint
+            && !hint.trim().is_empty()
+        {
+            println!("  Hint        : {}", hint);
+        }
+        if let Some(ipa) = &card.ipa {
+            println!("  IPA         : {}", ipa);
+        }
+        if !card.synonyms.is_empty() {
+            println!("  Synonyms    : {}", card.synonyms.join(", "));
+        }
+        if !card.antonyms.is_empty() {
+            println!("  Antonyms    : {}", card.antonyms.join(", "));
+        }
+    }
+}
+
+fn print_english_definition(card: &EnglishDefinitionCard, deck: &str, label: &str) {
+    println!("[{}][{}] {}", label, deck, card.word);
+    println!("  Definition  : {}", card.definition);
+    if let Some(example) = &card.example {
+        println!("  Example     : {}", example);
+    }
+    if let Some(ipa) = &card.ipa {
+        println!("  IPA         : {}", ipa);
+    }
+}
+
+fn edit_text(label: &str, current: &str) -> Result<String> {
+    Input::<String>::new()
+        .with_prompt(label)
+        .with_initial_text(current)
+        .interact_text()
+        .context("failed to read edited text")
+}
+
+fn prompt_send_confirmation(prompt: &str) -> Result<bool> {
+    Confirm::new()
+        .with_prompt(prompt)
+        .default(true)
+        .interact()
+        .context("failed to read approval input")
+}
+
+fn prompt_language() -> Result<Option<Language>> {
+    let selections = vec!["Hindi sentence cards", "English cloze cards", "Exit"];
+    let choice = Select::new()
+        .with_prompt("Choose a language workflow")
+        .items(&selections)
+        .default(0)
+        .interact()?;
+
+    match choice {
+        0 => Ok(Some(Language::Hindi)),
+        1 => Ok(Some(Language::English)),
+        _ => Ok(None),
+    }
+}
+
+/// Let the user pick one of the existing decks (or name a new one), with the
+/// configured deck preselected. Falls back to the configured deck when the deck
+/// list cannot be fetched.
+async fn prompt_deck(language: Language, ctx: &RunContext<'_>) -> Result<String> {
+    let configured = match language {
+        Language::Hindi => ctx.config.hindi_deck.clone(),
+        Language::English => ctx.config.english_deck.clone(),
+    };
+
+    let mut decks = match ctx.anki.deck_names().await {
+        Ok(decks) => decks,
+        Err(err) => {
+            tracing::warn!(
+                "Could not list Anki decks ({:#}); using '{}'",
+                anyhow::Error::from(err),
+                configured
+            );
+            return Ok(configured);
+        }
+    };
+    decks.sort();
+
+    let mut items = decks.clone();
+    items.push("Create new deck...".to_string());
+    let default = decks
+        .iter()
+        .position(|deck| *deck == configured)
+        .unwrap_or(items.len() - 1);
+
+    let choice = Select::new()
+        .with_prompt("Choose a deck")
+        .items(&items)
+        .default(default)
+        .interact()
+        .context("failed to read deck selection")?;
+
+    if choice < decks.len() {
+        return Ok(decks[choice].clone());
+    }
+
+    let name = Input::<String>::new()
+        .with_prompt("New deck name")
+        .default(configured)
+        .interact_text()
+        .context("failed to read deck name")?;
+
+    Ok(name.trim().to_string())
+}
+
+/// Split words into Hindi and English batches by script, asking once for the
+/// language of any words that could not be detected.
+fn partition_by_language(words: Vec<WordEntry>) -> Result<Vec<(Language, Vec<WordEntry>)>> {
+    let mut hindi = Vec::new();
+    let mut english = Vec::new();
+    let mut ambiguous = Vec::new();
+    for entry in words {
+        match input::detect_language(&entry.word) {
+            Some(Language::Hindi) => hindi.push(entry),
+            Some(Language::English) => english.push(entry),
+            None => ambiguous.push(entry),
+        }
+    }
+
+    if !ambiguous.is_empty() {
+        let listed: Vec<&str> = ambiguous.iter().map(|entry| entry.word.as_str()).collect();
+        println!("Could not detect the language of: {}", listed.join(", "));
+        match prompt_language()? {
+            Some(Language::Hindi) => hindi.append(&mut ambiguous),
+            Some(Language::English) => english.append(&mut ambiguous),
+            None => tracing::info!("Skipping {} undetected word(s)", ambiguous.len()),
+        }
+    }
+
+    Ok([(Language::Hindi, hindi), (Language::English, english)]
+        .into_iter()
+        .filter(|(_, words)| !words.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::anki::MockAnkiClient;
+    use crate::test_support::{MockOpenAi, completion, test_config};
+
+    fn context<'a>(
+        anki: &'a MockAnkiClient,
+        llm: &'a OpenAiClient,
+        config: &'a Config,
+    ) -> RunContext<'a> {
+        RunContext {
+            anki,
+            llm,
+            config,
+            dry_run: false,
+            auto_approve: true,
+            examples: 1,
+            notes_per_word: 1,
+            uniqueness_threshold: None,
+            strict_deck: false,
+            yes_large: false,
+            word_timeout: None,
+            show_progress: false,
+            deck_from_tag: false,
+            browse: false,
+            english_mode: EnglishMode::Cloze,
+            frequency_ranks: None,
+            known_words: None,
+            html_preview: None,
+            enrichers: &[],
+            limit: None,
+            batch_size: 1,
+            verify: false,
+            allow_existing: false,
+            only_new: false,
+            append_to_field: None,
+            output_note_ids: false,
+            quiet: true,
+            enrich: false,
+            image: false,
+            production_card: false,
+            phrase: false,
+            resume: false,
+            review_all_first: false,
+            cloze_text_field: OnceCell::new(),
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn collect_tags_sanitizes_dedupes_and_sorts() {
+        let tags = collect_tags(
+            &strings(&["generated", "Work::Meetings"]),
+            &strings(&["work::meetings", " spaced  tag ", "b"]),
+            "word_ghar",
+            "hindi",
+            Some(Difficulty::A1),
+            &strings(&["Generated", "a:b"]),
+        );
+
+        assert_eq!(
+            tags,
+            [
+                "a_b",
+                "b",
+                "generated",
+                "hindi",
+                "level_a1",
+                "spaced_tag",
+                "word_ghar",
+                "Work::Meetings"
+            ]
+        );
+
+        let reordered = collect_tags(
+            &strings(&["a:b", "b"]),
+            &strings(&["Work::Meetings", " spaced  tag "]),
+            "word_ghar",
+            "hindi",
+            Some(Difficulty::A1),
+            &strings(&["generated"]),
+        );
+        assert_eq!(reordered, tags);
+    }
+
+    fn words(words: &[&str]) -> Vec<WordEntry> {
+        words
+            .iter()
+            .map(|word| WordEntry::parse(word, None))
+            .collect()
+    }
+
+    fn hindi_reply(word: &str, sentence: &str, translation: &str) -> serde_json::Value {
+        completion(
+            &json!({
+                "word": word,
+                "examples": [{"hindi_sentence": sentence, "english_sentence": translation}]
+            })
+            .to_string(),
+        )
+    }
+
+    fn cloze_reply(word: &str, sentence: &str) -> serde_json::Value {
+        completion(
+            &json!({
+                "word": word,
+                "cloze_sentence": sentence,
+                "translation": "A lucky accident.",
+                "hint": null
+            })
+            .to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn hindi_flow_adds_both_directions_per_word() {
+        let server = MockOpenAi::start(vec![
+            hindi_reply("घर", "मेरा घर बड़ा है।", "My house is big."),
+            hindi_reply("पानी", "मुझे पानी चाहिए।", "I need water."),
+        ]);
+        let config = test_config(&server.url, "");
+        let llm = OpenAiClient::new(&config).unwrap();
+        let anki = MockAnkiClient::new();
+        let ctx = context(&anki, &llm, &config);
+
+        run_hindi_flow(words(&["घर", "पानी"]), Some("Hindi::Test".into()), &ctx)
+            .await
+            .unwrap();
+
+        assert_eq!(anki.decks(), ["Hindi::Test"]);
+        let notes = anki.added_notes();
+        assert_eq!(notes.len(), 4);
+        assert!(notes.iter().all(|note| note.deck_name == "Hindi::Test"));
+        let fronts: Vec<&str> = notes
+            .iter()
+            .map(|note| note.fields["Front"].as_str())
+            .collect();
+        assert_eq!(
+            fronts,
+            [
+                "मेरा घर बड़ा है।",
+                "My house is big.",
+                "मुझे पानी चाहिए।",
+                "I need water."
+            ]
+        );
+        assert!(notes[0].tags.iter().any(|tag| tag == "hindi"));
+        assert_eq!(server.prompts().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn words_already_in_anki_are_skipped() {
+        let server =
+            MockOpenAi::start(vec![hindi_reply("पानी", "मुझे पानी चाहिए।", "I need water.")]);
+        let config = test_config(&server.url, "");
+        let llm = OpenAiClient::new(&config).unwrap();
+        let anki = MockAnkiClient::new();
+        let existing = Note {
+            deck_name: "Hindi".to_string(),
+            model_name: HINDI_MODEL.to_string(),
+            fields: BTreeMap::from([("Front".to_string(), "पुराना".to_string())]),
+            tags: vec!["hindi".to_string(), word_tag(&config, "hindi", "घर")],
+            options: None,
+        };
+        anki.add_notes(&[existing]).await.unwrap();
+        let ctx = context(&anki, &llm, &config);
+
+        run_hindi_flow(words(&["घर", "पानी"]), Some("Hindi".into()), &ctx)
+            .await
+            .unwrap();
+
+        let prompts = server.prompts();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].ends_with("Target word: पानी"), "{}", prompts[0]);
+        assert_eq!(anki.added_notes().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn missing_prompt_version_field_fails_before_generating() {
+        let server = MockOpenAi::start(Vec::new());
+        let config = test_config(&server.url, "prompt_version_field = \"PromptVersion\"");
+        let llm = OpenAiClient::new(&config).unwrap();
+        let anki = MockAnkiClient::new();
+        let ctx = context(&anki, &llm, &config);
+
+        let err = run_hindi_flow(words(&["घर"]), None, &ctx)
+            .await
+            .unwrap_err();
+
+        assert!(
+            err.to_string()
+                .starts_with("prompt_version_field 'PromptVersion' is not a field"),
+            "{err}"
+        );
+        assert!(server.prompts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn english_flow_adds_cloze_notes() {
+        let server = MockOpenAi::start(vec![cloze_reply(
+            "serendipity",
+            "Finding that café was pure {{c1::serendipity}}.",
+        )]);
+        let config = test_config(&server.url, "");
+        let llm = OpenAiClient::new(&config).unwrap();
+        let anki = MockAnkiClient::new();
+        let ctx = context(&anki, &llm, &config);
+
+        run_english_flow(words(&["serendipity"]), Some("English".into()), &ctx)
+            .await
+            .unwrap();
+
+        let notes = anki.added_notes();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].model_name, ENGLISH_MODEL);
+        assert_eq!(
+            notes[0].fields["Text"],
+            "Finding that café was pure {{c1::serendipity}}."
+        );
+        assert!(notes[0].fields["Back Extra"].contains("A lucky accident."));
+    }
+
+    #[tokio::test]
+    async fn word_timeout_does_not_overflow_for_batches() {
+        let config = test_config("http://127.0.0.1:9", "");
+        let llm = OpenAiClient::new(&config).unwrap();
+        let anki = MockAnkiClient::new();
+        let ctx = RunContext {
+            word_timeout: Some(Duration::MAX),
+            ..context(&anki, &llm, &config)
+        };
+
+        let result = within_word_timeout(&ctx, "a, b", 2, async { Ok(7) }).await;
+
+        assert_eq!(result.unwrap(), Some(7));
+    }
+
+    #[tokio::test]
+    async fn timed_out_words_are_written_to_the_failure_list() {
+        let server = MockOpenAi::start(Vec::new());
+        let config = test_config(&server.url, "");
+        let llm = OpenAiClient::new(&config).unwrap();
+        let anki = MockAnkiClient::new();
+        let ctx = RunContext {
+            word_timeout: Some(Duration::ZERO),
+            ..context(&anki, &llm, &config)
+        };
+
+        run_english_flow(words(&["serendipity:noun"]), Some("English".into()), &ctx)
+            .await
+            .unwrap();
+
+        assert!(anki.added_notes().is_empty());
+        let journal_dir = config.config_path().unwrap().with_file_name("journal");
+        let failed: Vec<String> = std::fs::read_dir(journal_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "failed"))
+            .map(|path| std::fs::read_to_string(path).unwrap())
+            .collect();
+        assert_eq!(failed, ["serendipity:noun\n"]);
+    }
+
+    #[tokio::test]
+    async fn unusable_replies_are_recorded_and_the_run_continues() {
+        let server = MockOpenAi::start(vec![
+            completion(r#"{"word": "serendipity", "cloze_sentence": "", "translation": ""}"#),
+            cloze_reply("ephemeral", "Fame on the internet is {{c1::ephemeral}}."),
+        ]);
+        let config = test_config(&server.url, "");
+        let llm = OpenAiClient::new(&config).unwrap();
+        let anki = MockAnkiClient::new();
+        let ctx = context(&anki, &llm, &config);
+
+        run_english_flow(
+            words(&["serendipity", "ephemeral"]),
+            Some("English".into()),
+            &ctx,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(anki.added_notes().len(), 1);
+        let journal_dir = config.config_path().unwrap().with_file_name("journal");
+        let failed: Vec<String> = std::fs::read_dir(journal_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "failed"))
+            .map(|path| std::fs::read_to_string(path).unwrap())
+            .collect();
+        assert_eq!(failed, ["serendipity\n"]);
+    }
+
+    #[test]
+    fn note_id_output_is_only_the_json_array() {
+        let mut out = Vec::new();
+        write_note_ids(&mut out, &[1712345678901, 1712345678902]).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "[1712345678901,1712345678902]\n");
+        let ids: Vec<i64> = serde_json::from_str(&out).unwrap();
+        assert_eq!(ids.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn dry_run_adds_nothing() {
+        let server = MockOpenAi::start(vec![cloze_reply(
+            "serendipity",
+            "Finding that café was pure {{c1::serendipity}}.",
+        )]);
+        let config = test_config(&server.url, "");
+        let llm = OpenAiClient::new(&config).unwrap();
+        let anki = MockAnkiClient::new();
+        let ctx = RunContext {
+            dry_run: true,
+            ..context(&anki, &llm, &config)
+        };
+
+        run_english_flow(words(&["serendipity"]), Some("English".into()), &ctx)
+            .await
+            .unwrap();
+
+        assert!(anki.added_notes().is_empty());
+        assert_eq!(server.prompts().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn regenerate_rebuilds_production_notes_from_a_fresh_cloze() {
+        let server = MockOpenAi::start(vec![cloze_reply(
+            "serendipity",
+            "Finding that café was pure {{c1::serendipity}}.",
+        )]);
+        let config = test_config(&server.url, "");
+        let llm = OpenAiClient::new(&config).unwrap();
+        let anki = MockAnkiClient::new();
+        let old = EnglishClozeCard {
+            word: "serendipity".to_string(),
+            cloze_sentence: "Meeting her was {{c1::serendipity}}.".to_string(),
+            translation: "Luck.".to_string(),
+            hint: None,
+            ipa: None,
+            synonyms: Vec::new(),
+            antonyms: Vec::new(),
+        };
+        let mut production = build_production_note(&old, "English", &[], &config);
+        assert!(production.tags.iter().any(|tag| tag == PRODUCTION_TAG));
+        production.tags.push("prompt_old".to_string());
+        anki.add_notes(&[production]).await.unwrap();
+        let ctx = context(&anki, &llm, &config);
+
+        run_regenerate("prompt_old", &ctx).await.unwrap();
+
+        let prompts = server.prompts();
+        assert_eq!(prompts.len(), 1);
+        let notes = anki.added_notes();
+        assert_eq!(notes[0].model_name, ENGLISH_BASIC_MODEL);
+        assert_eq!(notes[0].fields["Front"], "serendipity<br>A lucky accident.");
+        assert_eq!(
+            notes[0].fields["Back"],
+            "Finding that café was pure serendipity."
+        );
+    }
 }